@@ -0,0 +1,294 @@
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{info, warn};
+
+use crate::config::{ACCEPT_RETRY_DELAY, API_STALE_AFTER};
+use crate::fees::{FeeChange, PoolFee};
+use crate::http::{Replayed, http_response, read_request};
+use crate::live::{LiveBoard, LiveEvent, MonitorStatus};
+use crate::metrics::{QUARANTINED_POOLS, RpcStats, rpc_stats};
+use crate::pool_state::{PoolCache, PoolSnapshot};
+use crate::settings::{Settings, settings};
+
+type SendError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Clone)]
+struct ApiState {
+    board: Arc<LiveBoard>,
+    pool_cache: Arc<PoolCache>,
+    started: Instant,
+}
+
+#[derive(Debug, Serialize)]
+struct ApiPool {
+    pair_name: String,
+    block_number: u64,
+    pool: PoolSnapshot,
+}
+
+#[derive(Debug, Serialize)]
+struct ApiPoolFees {
+    current: PoolFee,
+    // Oldest first, up to FEE_HISTORY_LEN changes
+    history: Vec<FeeChange>,
+}
+
+#[derive(Debug, Serialize)]
+struct Health {
+    // ok, starting before the first block is done, stale when none was done for API_STALE_AFTER
+    status: &'static str,
+    uptime_secs: u64,
+    latest_block: Option<u64>,
+    secs_since_block: Option<u64>,
+    monitors: Vec<MonitorStatus>,
+    pools_cached: usize,
+    quarantined_pools: i64,
+    rpc: Vec<RpcStats>,
+}
+
+// Serves the live view of the scanner as JSON, read-only:
+//   GET /pools          every cached pool state
+//   GET /spreads        latest spread of every pool pair
+//   GET /opportunities  latest profitable pairs, newest first
+//   GET /fees           current fee of every pool, both directions
+//   GET /fees/<pool>    one pool's current fee and its recent changes
+//   GET /config         running settings, with the RPC URL reduced to its host
+//   GET /health         block progress, RPC totals; 503 unless ok
+//   GET /ws             WebSocket of spreads events, one per monitor and block, and
+//                       opportunity and fee_change events as they happen
+pub async fn serve_api(listen: &str, board: Arc<LiveBoard>, pool_cache: Arc<PoolCache>) -> Result<(), SendError> {
+    let listener = TcpListener::bind(listen).await?;
+    info!("🛰️ API on http://{}/ and ws://{}/ws", listener.local_addr()?, listener.local_addr()?);
+    let state = ApiState { board, pool_cache, started: Instant::now() };
+
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            // e.g. out of file descriptors; WebSocket clients already connected keep streaming
+            Err(e) => {
+                warn!("API failed to accept a connection: {}", e);
+                tokio::time::sleep(ACCEPT_RETRY_DELAY).await;
+                continue;
+            }
+        };
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, state).await {
+                warn!("API connection failed: {}", e);
+            }
+        });
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, state: ApiState) -> Result<(), SendError> {
+    let request = read_request(&mut stream).await?;
+    if request.method == "GET" && request.path == "/ws" {
+        let stream = Replayed::new(request, stream);
+        return stream_events(stream, state.board.subscribe(), state.pool_cache.fees().subscribe()).await;
+    }
+
+    let response = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/pools") => json_response("200 OK", &pools(&state.pool_cache)),
+        ("GET", "/spreads") => json_response("200 OK", &state.board.spreads()),
+        ("GET", "/opportunities") => json_response("200 OK", &state.board.opportunities()),
+        ("GET", "/fees") => json_response("200 OK", &state.pool_cache.fees().all()),
+        ("GET", path) if path.starts_with("/fees/") => pool_fees(&state.pool_cache, &path["/fees/".len()..]),
+        ("GET", "/config") => json_response("200 OK", &redacted(&settings())),
+        ("GET", "/health") => {
+            let health = health(&state);
+            let status = if health.status == "ok" { "200 OK" } else { "503 Service Unavailable" };
+            json_response(status, &health)
+        }
+        ("GET", _) => http_response("404 Not Found", "text/plain", b"not found\n"),
+        _ => http_response("405 Method Not Allowed", "text/plain", b"method not allowed\n"),
+    };
+    stream.write_all(&response).await?;
+    Ok(())
+}
+
+fn pools(pool_cache: &PoolCache) -> Vec<ApiPool> {
+    let mut pools: Vec<ApiPool> = pool_cache
+        .all()
+        .into_iter()
+        .map(|cached| ApiPool {
+            pool: cached.state.snapshot(cached.weth_usdc, cached.base_usd),
+            pair_name: cached.pair_name,
+            block_number: cached.block_number,
+        })
+        .collect();
+    pools.sort_by(|a, b| a.pair_name.cmp(&b.pair_name).then_with(|| a.pool.pool_address.cmp(&b.pool.pool_address)));
+    pools
+}
+
+fn pool_fees(pool_cache: &PoolCache, pool: &str) -> Vec<u8> {
+    let fees = pool_cache.fees();
+    match fees.current(pool) {
+        Some(current) => json_response("200 OK", &ApiPoolFees { current, history: fees.history(pool) }),
+        None => http_response("404 Not Found", "text/plain", b"pool not seen\n"),
+    }
+}
+
+// The RPC URL's path and query often hold an API key, and so may a URL that doesn't parse
+fn redacted(settings: &Settings) -> Settings {
+    let mut settings = settings.clone();
+    let url = &settings.endpoints.ws_rpc_url;
+    if !url.is_empty() {
+        settings.endpoints.ws_rpc_url = match url::Url::parse(url) {
+            Ok(parsed) => {
+                let host = parsed.host_str().unwrap_or_default();
+                match parsed.port() {
+                    Some(port) => format!("{}://{}:{}/…", parsed.scheme(), host, port),
+                    None => format!("{}://{}/…", parsed.scheme(), host),
+                }
+            }
+            Err(_) => "<redacted>".to_string(),
+        };
+    }
+    settings
+}
+
+fn health(state: &ApiState) -> Health {
+    let monitors = state.board.monitors();
+    let now_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    let latest = monitors.iter().max_by_key(|monitor| monitor.finished_ms);
+    let secs_since_block = latest.map(|monitor| now_ms.saturating_sub(monitor.finished_ms) / 1000);
+    let status = match secs_since_block {
+        None => "starting",
+        Some(secs) if secs >= API_STALE_AFTER.as_secs() => "stale",
+        Some(_) => "ok",
+    };
+    Health {
+        status,
+        uptime_secs: state.started.elapsed().as_secs(),
+        latest_block: monitors.iter().map(|monitor| monitor.block_number).max(),
+        secs_since_block,
+        pools_cached: state.pool_cache.all().len(),
+        quarantined_pools: QUARANTINED_POOLS.get(),
+        rpc: rpc_stats(),
+        monitors,
+    }
+}
+
+// Sends every live event and fee change as a JSON text message until the client goes away
+async fn stream_events(
+    stream: Replayed,
+    mut events: broadcast::Receiver<LiveEvent>,
+    mut fee_changes: broadcast::Receiver<FeeChange>,
+) -> Result<(), SendError> {
+    let ws = tokio_tungstenite::accept_async(stream).await?;
+    let (mut sink, mut incoming) = ws.split();
+
+    loop {
+        tokio::select! {
+            message = incoming.next() => {
+                match message {
+                    Some(Ok(Message::Ping(payload))) => sink.send(Message::Pong(payload)).await?,
+                    Some(Ok(Message::Close(_))) | None => return Ok(()),
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Err(Box::new(e)),
+                }
+            }
+            event = events.recv() => {
+                match event {
+                    Ok(event) => sink.send(Message::Text(serde_json::to_string(&event)?)).await?,
+                    // A slow client misses the oldest events rather than holding up the rest
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => {
+                        let _ = sink.close().await;
+                        return Ok(());
+                    }
+                }
+            }
+            change = fee_changes.recv() => {
+                match change {
+                    Ok(change) => {
+                        let mut event = serde_json::to_value(&change)?;
+                        event["type"] = "fee_change".into();
+                        sink.send(Message::Text(event.to_string())).await?
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => {
+                        let _ = sink.close().await;
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn json_response<T: Serialize>(status: &str, body: &T) -> Vec<u8> {
+    match serde_json::to_vec(body) {
+        Ok(body) => http_response(status, "application/json", &body),
+        Err(e) => http_response("500 Internal Server Error", "text/plain", e.to_string().as_bytes()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::io::AsyncReadExt;
+
+    fn redacted_url(url: &str) -> String {
+        let mut settings = Settings::default();
+        settings.endpoints.ws_rpc_url = url.to_string();
+        redacted(&settings).endpoints.ws_rpc_url
+    }
+
+    #[test]
+    fn config_shows_only_the_rpc_host() {
+        assert_eq!(redacted_url("wss://arb-mainnet.g.alchemy.com/v2/secret-key"), "wss://arb-mainnet.g.alchemy.com/…");
+        assert_eq!(redacted_url("ws://user:password@node.example:8546/?key=secret"), "ws://node.example:8546/…");
+        assert_eq!(redacted_url("secret-key@node.example"), "<redacted>");
+        assert_eq!(redacted_url(""), "");
+    }
+
+    // Sends one raw request through handle_connection and returns the whole response
+    async fn respond(state: ApiState, request: &str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+        client.write_all(request.as_bytes()).await.unwrap();
+        handle_connection(server, state).await.unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn routes_requests_by_method_and_path() {
+        let state = ApiState {
+            board: Arc::new(LiveBoard::new()),
+            pool_cache: Arc::new(PoolCache::new(None, None)),
+            started: Instant::now(),
+        };
+        let get = |path: &str| format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path);
+
+        // Starting until a monitor finishes a block
+        let response = respond(state.clone(), &get("/health")).await;
+        assert!(response.starts_with("HTTP/1.1 503 Service Unavailable"), "{}", response);
+        assert!(response.contains(r#""status":"starting""#), "{}", response);
+        state.board.end_block("pairs", 1001, Duration::from_millis(20));
+        let response = respond(state.clone(), &get("/health")).await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        assert!(response.contains(r#""latest_block":1001"#), "{}", response);
+
+        let response = respond(state.clone(), &get("/fees/0x0000000000000000000000000000000000000001")).await;
+        assert!(response.starts_with("HTTP/1.1 404 Not Found"), "{}", response);
+        let response = respond(state.clone(), &get("/nowhere")).await;
+        assert!(response.starts_with("HTTP/1.1 404 Not Found"), "{}", response);
+
+        let response = respond(state, "POST /pools HTTP/1.1\r\nHost: localhost\r\nContent-Length: 0\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed"), "{}", response);
+    }
+}
@@ -0,0 +1,350 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use serde::Serialize;
+
+use crate::pool_state::PoolSnapshot;
+use crate::recorder::{RecordedPool, load_recording};
+use crate::trade::local::simulate_local_trade;
+use crate::tiers::{TierCheck, TierTable, best_size, tier_table};
+use crate::websocket::categorize_pairs::price_diff_pct;
+use tracing::info;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct BacktestOpportunity {
+    pub block_number: u64,
+    pub pair_name: String,
+    pub buy_dex: String,
+    pub buy_pool: String,
+    pub buy_fee: u32,
+    pub sell_dex: String,
+    pub sell_pool: String,
+    pub sell_fee: u32,
+    pub tier: u8,
+    pub price_diff_pct: f64,
+    pub amount_usdc: f64,
+    pub profit_usdc: f64,
+    // Best size was the last one in the grid, so a larger size might do better
+    pub at_grid_edge: bool,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct TierStats {
+    pub tier: u8,
+    pub min_spread: f64,
+    // Simulated, whether or not the spread cleared min_spread
+    pub evaluated: u64,
+    // In the tier but skipped because it, or the pair's override, is disabled
+    pub disabled: u64,
+    pub found: u64,
+    pub missed: u64,
+    pub estimated_profit_usdc: f64,
+    pub missed_profit_usdc: f64,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct BacktestReport {
+    // How trade sizes were priced. Always the local constant-liquidity model: the live
+    // monitors call the DEX quoters, which a recording can't answer, so profits on sizes
+    // that would cross a tick read higher than the scanner would see.
+    pub simulator: &'static str,
+    pub blocks: u64,
+    pub pair_evaluations: u64,
+    // Pool pairs whose depth fell in no tier
+    pub no_tier: u64,
+    pub simulation_failures: u64,
+    pub tiers: Vec<TierStats>,
+    // Profitable and above the tier's minimum price difference
+    pub opportunities: Vec<BacktestOpportunity>,
+    // Profitable but filtered out by the tier's minimum price difference
+    pub missed: Vec<BacktestOpportunity>,
+}
+
+// Replays a recording made with [recording] blocks_file through the live tiering and
+// sizing code, simulating each size locally against the recorded pool state. The full
+// report is written to report_path and returned.
+pub fn run_backtest(recording_path: &str, report_path: &str) -> Result<BacktestReport, Box<dyn std::error::Error>> {
+    let records = load_recording(recording_path)?;
+    info!("Loaded {} recorded pool states from {}", records.len(), recording_path);
+
+    let report = backtest(records, &tier_table());
+    let file = File::create(report_path)?;
+    serde_json::to_writer_pretty(file, &report)?;
+
+    Ok(report)
+}
+
+fn backtest(records: Vec<RecordedPool>, tiers: &TierTable) -> BacktestReport {
+    // block -> pair -> pools, one entry per pool address
+    let mut blocks: BTreeMap<u64, BTreeMap<String, Vec<PoolSnapshot>>> = BTreeMap::new();
+    for record in records {
+        let pools = blocks
+            .entry(record.block_number)
+            .or_default()
+            .entry(record.pair_name)
+            .or_default();
+        if !pools.iter().any(|p| p.pool_address == record.pool.pool_address) {
+            pools.push(record.pool);
+        }
+    }
+
+    let mut report = BacktestReport {
+        simulator: "local_constant_liquidity",
+        blocks: blocks.len() as u64,
+        tiers: tiers
+            .tiers
+            .iter()
+            .map(|tier| TierStats { tier: tier.tier, min_spread: tier.min_spread, ..Default::default() })
+            .collect(),
+        ..Default::default()
+    };
+
+    for (block_number, pairs) in &blocks {
+        for (pair_name, pools) in pairs {
+            for (i, pool_a) in pools.iter().enumerate() {
+                for pool_b in pools.iter().skip(i + 1) {
+                    evaluate_pair(tiers, *block_number, pair_name, pool_a, pool_b, &mut report);
+                }
+            }
+        }
+    }
+
+    report.opportunities.sort_by(|a, b| b.profit_usdc.total_cmp(&a.profit_usdc));
+    report.missed.sort_by(|a, b| b.profit_usdc.total_cmp(&a.profit_usdc));
+    report
+}
+
+fn evaluate_pair(
+    tiers: &TierTable,
+    block_number: u64,
+    pair_name: &str,
+    pool_a: &PoolSnapshot,
+    pool_b: &PoolSnapshot,
+    report: &mut BacktestReport,
+) {
+    report.pair_evaluations += 1;
+
+    let usdc_price_a = pool_a.price * pool_a.base_usd();
+    let usdc_price_b = pool_b.price * pool_b.base_usd();
+    let depth_usd = pool_a.depth_ladder().min(&pool_b.depth_ladder()).tier_depth();
+
+    let price_diff_pct = price_diff_pct(usdc_price_a, usdc_price_b);
+    let check = tiers.check(pair_name, depth_usd, price_diff_pct);
+    let stats_index = check.tier().and_then(|tier| report.tiers.iter().position(|stats| stats.tier == tier.tier));
+    let Some(stats_index) = stats_index else {
+        report.no_tier += 1;
+        return;
+    };
+    // Pairs under the tier's spread are simulated too, to count what min_spread filters out
+    let (tier, accepted) = match check {
+        TierCheck::Simulate(tier) => (tier, true),
+        TierCheck::BelowSpread(tier) => (tier, false),
+        TierCheck::Disabled(_) | TierCheck::NoTier => {
+            report.tiers[stats_index].disabled += 1;
+            return;
+        }
+    };
+    report.tiers[stats_index].evaluated += 1;
+
+    // Buy where the token is cheaper, sell where it is dearer
+    let (buy_pool, sell_pool) = if usdc_price_a < usdc_price_b {
+        (pool_a, pool_b)
+    } else {
+        (pool_b, pool_a)
+    };
+    let base_usd = sell_pool.base_usd();
+
+    let test_amounts = tier.trade_amounts(depth_usd);
+    let mut profits = Vec::with_capacity(test_amounts.len());
+
+    for amount in &test_amounts {
+        let amount_base = amount / base_usd;
+        let simulated = simulate_local_trade(buy_pool, amount_base, "BUY")
+            .and_then(|buy_amount_out| simulate_local_trade(sell_pool, buy_amount_out, "SELL"));
+        let sell_amount_out = match simulated {
+            Ok(amount_out) => amount_out,
+            Err(_) => {
+                report.simulation_failures += 1;
+                return;
+            }
+        };
+
+        profits.push(sell_amount_out * base_usd - amount);
+    }
+
+    let Some(index) = best_size(profits.iter().copied()) else {
+        return;
+    };
+    let (amount_usdc, profit_usdc) = (test_amounts[index], profits[index]);
+
+    let opportunity = BacktestOpportunity {
+        block_number,
+        pair_name: pair_name.to_string(),
+        buy_dex: buy_pool.dex.clone(),
+        buy_pool: buy_pool.pool_address.clone(),
+        buy_fee: buy_pool.fee,
+        sell_dex: sell_pool.dex.clone(),
+        sell_pool: sell_pool.pool_address.clone(),
+        sell_fee: sell_pool.fee,
+        tier: tier.tier,
+        price_diff_pct,
+        amount_usdc,
+        profit_usdc,
+        at_grid_edge: index + 1 == test_amounts.len(),
+    };
+
+    let stats = &mut report.tiers[stats_index];
+    if accepted {
+        stats.found += 1;
+        stats.estimated_profit_usdc += profit_usdc;
+        report.opportunities.push(opportunity);
+    } else {
+        stats.missed += 1;
+        stats.missed_profit_usdc += profit_usdc;
+        report.missed.push(opportunity);
+    }
+}
+
+pub fn print_report(report: &BacktestReport) {
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    println!("📊 Backtest over {} blocks, {} pair evaluations ({} in no tier, {} simulation failures)",
+        report.blocks, report.pair_evaluations, report.no_tier, report.simulation_failures);
+    println!("   Sizes priced locally at constant in-range liquidity, not through the DEX quoters");
+    for stats in &report.tiers {
+        println!(
+            "   TIER {} | evaluated: {} | disabled: {} | found: {} (${:.2}) | missed: {} (${:.2}) | min diff: {:.2}%",
+            stats.tier,
+            stats.evaluated,
+            stats.disabled,
+            stats.found,
+            stats.estimated_profit_usdc,
+            stats.missed,
+            stats.missed_profit_usdc,
+            stats.min_spread * 100.0,
+        );
+    }
+
+    println!("🔥 Top opportunities:");
+    for opportunity in report.opportunities.iter().take(10) {
+        println!(
+            "   #{} {} | Buy {} {} ({}) -> Sell {} {} ({}) | Diff: {:.4}% | Size: ${:.2}{} | Profit: ${:.4}",
+            opportunity.block_number,
+            opportunity.pair_name,
+            opportunity.buy_dex,
+            opportunity.buy_pool,
+            opportunity.buy_fee,
+            opportunity.sell_dex,
+            opportunity.sell_pool,
+            opportunity.sell_fee,
+            opportunity.price_diff_pct * 100.0,
+            opportunity.amount_usdc,
+            if opportunity.at_grid_edge { " (grid edge)" } else { "" },
+            opportunity.profit_usdc,
+        );
+    }
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::backtest;
+    use crate::pool_state::PoolSnapshot;
+    use crate::recorder::{RecordedPool, load_recording};
+    use crate::tiers::{Tier, TierOverride, TierTable};
+    use std::collections::HashMap;
+    use std::io::Write;
+
+    const ARB: &str = "0x912ce59144191c1204e64559fe8253a0e49e6548";
+    const WETH: &str = "0x82af49447d8a07e3bd95bd0d56f35241523fbab1";
+    const PRICE: f64 = 0.0003;
+
+    // A 0.05% WETH pool of a token at weth_per_token, deep enough that the sizes barely move it
+    fn record(block_number: u64, pair: &str, pool_address: &str, weth_per_token: f64, liquidity: u128) -> RecordedPool {
+        RecordedPool {
+            block_number,
+            timestamp: 0,
+            pair_name: pair.to_string(),
+            pool: PoolSnapshot {
+                dex: "Uniswap".to_string(),
+                pool_address: pool_address.to_string(),
+                token0: ARB.to_string(),
+                token1: WETH.to_string(),
+                token0_decimals: 18,
+                token1_decimals: 18,
+                sqrt_price_x96: format!("{:.0}", weth_per_token.sqrt() * 2f64.powi(96)),
+                active_liquidity: liquidity,
+                tick: (weth_per_token.ln() / 1.0001f64.ln()).floor() as i32,
+                initialized_ticks: Vec::new(),
+                fee: 500,
+                fee_otz: None,
+                price: weth_per_token,
+                liquidity,
+                base_token: WETH.to_string(),
+                weth_usdc: 3000.0,
+                base_usd: 3000.0,
+            },
+        }
+    }
+
+    #[test]
+    fn replays_a_recording_through_the_tier_table() {
+        let deep = 10u128.pow(26);
+        let records = [
+            // Both monitors of the pair record pool A; it is evaluated once
+            record(1, "ARB/WETH", "0xa", PRICE, deep),
+            record(1, "ARB/WETH", "0xa", PRICE, deep),
+            record(1, "ARB/WETH", "0xb", PRICE * 1.02, deep),
+            record(1, "GMX/WETH", "0xc", PRICE, deep),
+            record(1, "GMX/WETH", "0xd", PRICE * 1.02, deep),
+            record(1, "MAGIC/WETH", "0xe", PRICE, 10u128.pow(15)),
+            record(1, "MAGIC/WETH", "0xf", PRICE * 1.02, 10u128.pow(15)),
+            // Profitable under min_spread, then unprofitable
+            record(2, "ARB/WETH", "0xa", PRICE, deep),
+            record(2, "ARB/WETH", "0xb", PRICE * 1.005, deep),
+            record(3, "ARB/WETH", "0xa", PRICE, deep),
+            record(3, "ARB/WETH", "0xb", PRICE * 1.0005, deep),
+        ];
+        let path = std::env::temp_dir().join(format!("scanner_backtest_{}.jsonl", std::process::id()));
+        let mut file = std::fs::File::create(&path).unwrap();
+        for record in &records {
+            writeln!(file, "{}", serde_json::to_string(record).unwrap()).unwrap();
+        }
+        // A crash can leave a truncated last line behind
+        write!(file, "{{\"block_number\":4,").unwrap();
+        drop(file);
+        let recording = load_recording(path.to_str().unwrap()).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let tier = Tier {
+            tier: 0,
+            min_depth: 1000.0,
+            max_depth: None,
+            min_spread: 0.01,
+            start: 1000.0,
+            step: 1000.0,
+            count: 3,
+            enabled: true,
+        };
+        let disabled = TierOverride { enabled: Some(false), ..Default::default() };
+        let overrides = HashMap::from([("GMX/WETH".to_string(), disabled)]);
+        let report = backtest(recording, &TierTable { tiers: vec![tier], pairs: overrides });
+
+        assert_eq!((report.blocks, report.pair_evaluations, report.no_tier, report.simulation_failures), (3, 5, 1, 0));
+        let stats = &report.tiers[0];
+        assert_eq!((stats.evaluated, stats.disabled, stats.found, stats.missed), (3, 1, 1, 1));
+
+        assert_eq!(report.opportunities.len(), 1);
+        let found = &report.opportunities[0];
+        assert_eq!((found.block_number, found.pair_name.as_str()), (1, "ARB/WETH"));
+        assert_eq!((found.buy_pool.as_str(), found.sell_pool.as_str()), ("0xa", "0xb"));
+        assert!((found.price_diff_pct - 0.02 / 1.02).abs() < 1e-9, "{}", found.price_diff_pct);
+        // 2% apart less two 0.05% fees, on the largest size
+        assert_eq!((found.amount_usdc, found.at_grid_edge), (3000.0, true));
+        assert!((found.profit_usdc - 3000.0 * (1.02 * 0.9995 * 0.9995 - 1.0)).abs() < 0.1, "{}", found.profit_usdc);
+        assert_eq!(stats.estimated_profit_usdc, found.profit_usdc);
+
+        assert_eq!(report.missed.len(), 1);
+        let missed = &report.missed[0];
+        assert_eq!((missed.block_number, missed.amount_usdc), (2, 3000.0));
+        assert!((missed.profit_usdc - 3000.0 * (1.005 * 0.9995 * 0.9995 - 1.0)).abs() < 0.1, "{}", missed.profit_usdc);
+    }
+}
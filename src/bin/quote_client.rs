@@ -0,0 +1,68 @@
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+
+use ethers::types::Address;
+use scanner_bot::pricing::base_symbol;
+use scanner_bot::quote_service::{
+    QUOTE_STATUS_BAD_REQUEST, QUOTE_STATUS_OK, QUOTE_STATUS_SIMULATION_FAILED, QUOTE_STATUS_TOKEN_MISMATCH,
+    QUOTE_STATUS_UNKNOWN_POOL, QuoteRequest, QuoteResponse,
+};
+use scanner_bot::settings::{self, settings};
+
+fn parse_pool(arg: &str) -> Result<[u8; 20], Box<dyn std::error::Error>> {
+    let bytes = hex::decode(arg.trim_start_matches("0x"))?;
+    if bytes.len() != 20 {
+        return Err(format!("Invalid pool address: {}", arg).into());
+    }
+    let mut pool = [0u8; 20];
+    pool.copy_from_slice(&bytes);
+    Ok(pool)
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 4 {
+        eprintln!("Usage: quote_client <buy_pool> <sell_pool> <amount_base>");
+        std::process::exit(1);
+    }
+
+    let request = QuoteRequest {
+        buy_pool: parse_pool(&args[1])?,
+        sell_pool: parse_pool(&args[2])?,
+        amount_base: args[3].parse::<f64>()?,
+    };
+
+    // Same socket as the scanner: ipc.quote_socket, QUOTE_SOCKET_PATH or the default
+    settings::init(None)?;
+    let mut stream = UnixStream::connect(&settings().ipc.quote_socket)?;
+
+    let payload = bincode::encode_to_vec(&request, bincode::config::standard())?;
+    stream.write_all(&(payload.len() as u32).to_be_bytes())?;
+    stream.write_all(&payload)?;
+
+    let mut len_bytes = [0u8; 4];
+    stream.read_exact(&mut len_bytes)?;
+    let mut buf = vec![0u8; u32::from_be_bytes(len_bytes) as usize];
+    stream.read_exact(&mut buf)?;
+
+    let (response, _) = bincode::decode_from_slice::<QuoteResponse, _>(&buf, bincode::config::standard())?;
+
+    match response.status {
+        QUOTE_STATUS_OK => {
+            // Symbol of a base token, the address of any other
+            let base = base_symbol(Address::from(response.base_token));
+            let token = base_symbol(Address::from(response.token));
+            println!("Quote at block {}:", response.block_number);
+            println!("  Buy:  {} {} -> {} {} (fee {})", request.amount_base, base, response.buy_amount_out, token, response.buy_fee);
+            println!("  Sell: {} {} -> {} {} (fee {})", response.buy_amount_out, token, response.sell_amount_out, base, response.sell_fee);
+            println!("  Profit: {} {} / {} USDC", response.profit_base, base, response.profit_usdc);
+        }
+        QUOTE_STATUS_UNKNOWN_POOL => println!("One of the pools is not tracked by the scanner"),
+        QUOTE_STATUS_TOKEN_MISMATCH => println!("Pools do not trade the same token"),
+        QUOTE_STATUS_SIMULATION_FAILED => println!("Simulation failed at block {}", response.block_number),
+        QUOTE_STATUS_BAD_REQUEST => println!("The scanner could not read the request"),
+        status => println!("Unknown status {}", status),
+    }
+
+    Ok(())
+}
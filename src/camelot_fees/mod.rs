@@ -0,0 +1,137 @@
+use ethers::prelude::{Filter, H256, Log};
+use ethers::providers::Middleware;
+use ethers::utils::keccak256;
+use futures_util::StreamExt;
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::pool_state::PoolCache;
+use crate::websocket::block_source::BlockStream;
+
+// Algebra emits Fee(feeZto, feeOtz) whenever a swap moves the dynamic fee. On every block
+// the Camelot pools the monitors are tracking are checked for new Fee events and the cached
+// states are updated, so quotes between monitor passes see the current fee.
+pub async fn handle_camelot_fee_events<M: Middleware + 'static>(
+    provider: Arc<M>,
+    mut blocks: BlockStream,
+    pool_cache: Arc<PoolCache>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fee_topic = H256::from(keccak256("Fee(uint16,uint16)"));
+    let mut next_block: Option<u64> = None;
+
+    while let Some(block_number) = blocks.next().await {
+        let pools = pool_cache.camelot_pools();
+        if pools.is_empty() {
+            continue;
+        }
+        // Set before the first call, so a failed first range is retried from where it started
+        let from_block = *next_block.get_or_insert(block_number);
+        if from_block > block_number {
+            continue;
+        }
+
+        let filter = Filter::new()
+            .address(pools)
+            .topic0(fee_topic)
+            .from_block(from_block)
+            .to_block(block_number);
+        match provider.get_logs(&filter).await {
+            Ok(logs) => {
+                for log in logs {
+                    let Some((fee_zto, fee_otz)) = parse_fee_log(&log) else {
+                        continue;
+                    };
+                    let log_block = log.block_number.map(|b| b.as_u64()).unwrap_or(block_number);
+                    let address = format!("0x{}", hex::encode(log.address.as_bytes()));
+                    pool_cache.update_camelot_fees(&address, log_block, fee_zto, fee_otz);
+                }
+                next_block = Some(block_number + 1);
+            }
+            // The range is retried on the next block
+            Err(e) => warn!("Failed to fetch Camelot Fee logs from #{}: {}", from_block, e),
+        }
+    }
+    Ok(())
+}
+
+// (feeZto, feeOtz) of a Fee event: two uint16s, each right-aligned in its own 32-byte word
+fn parse_fee_log(log: &Log) -> Option<(u32, u32)> {
+    if log.data.len() < 64 {
+        return None;
+    }
+    let fee_zto = u16::from_be_bytes([log.data[30], log.data[31]]) as u32;
+    let fee_otz = u16::from_be_bytes([log.data[62], log.data[63]]) as u32;
+    Some((fee_zto, fee_otz))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_fee_log;
+    use crate::exchange::camelot::CamelotPoolInfo;
+    use crate::pool_state::{PoolCache, PoolState};
+    use ethers::abi::{Token, encode};
+    use ethers::prelude::{Address, Bytes, Log, U256};
+
+    const POOL: &str = "0x00000000000000000000000000000000000000c1";
+
+    fn fee_log(data: Vec<u8>) -> Log {
+        Log { data: Bytes::from(data), ..Log::default() }
+    }
+
+    fn camelot(fee_zto: u32, fee_otz: u32) -> PoolState {
+        PoolState::Camelot(CamelotPoolInfo {
+            token0: Address::repeat_byte(0xee),
+            token1: Address::repeat_byte(0xaa),
+            token0_decimals: 18,
+            token1_decimals: 18,
+            token0_amount: U256::zero(),
+            token1_amount: U256::zero(),
+            token0_amount_adjusted: 0.0,
+            token1_amount_adjusted: 0.0,
+            pair_address: POOL.to_string(),
+            base: Address::repeat_byte(0xee),
+            liquidity: 0,
+            price: 4000.0,
+            tick: 82944,
+            sqrt_price_x96: U256::zero(),
+            active_liquidity: 0,
+            initialized_ticks: Vec::new(),
+            fee_zto,
+            fee_otz,
+        })
+    }
+
+    fn cached_fees(cache: &PoolCache) -> (u32, u32) {
+        let state = cache.get(POOL).unwrap().state;
+        (state.fee_for(true), state.fee_for(false))
+    }
+
+    #[test]
+    fn reads_both_fees_of_an_abi_encoded_event() {
+        let data = encode(&[Token::Uint(U256::from(500)), Token::Uint(U256::from(2_900))]);
+        assert_eq!(data.len(), 64);
+        assert_eq!(parse_fee_log(&fee_log(data.clone())), Some((500, 2_900)));
+        assert_eq!(parse_fee_log(&fee_log(data[..63].to_vec())), None);
+        assert_eq!(parse_fee_log(&fee_log(data[..32].to_vec())), None);
+        assert_eq!(parse_fee_log(&fee_log(Vec::new())), None);
+    }
+
+    #[test]
+    fn fee_events_apply_from_the_cached_block_on() {
+        let cache = PoolCache::new(None, None);
+        cache.update(POOL, "ARB/WETH", camelot(500, 600), 1001, 3000.0, 3000.0);
+
+        // An event from before the cached state is already in it
+        cache.update_camelot_fees(POOL, 1000, 100, 200);
+        assert_eq!(cached_fees(&cache), (500, 600));
+
+        cache.update_camelot_fees(POOL, 1001, 700, 800);
+        assert_eq!(cached_fees(&cache), (700, 800));
+        cache.update_camelot_fees(&POOL.to_uppercase().replace("0X", "0x"), 1002, 900, 1_000);
+        assert_eq!(cached_fees(&cache), (900, 1_000));
+
+        // Pools that aren't cached are left alone
+        cache.update_camelot_fees("0x00000000000000000000000000000000000000c2", 1002, 1, 1);
+        assert!(cache.get("0x00000000000000000000000000000000000000c2").is_none());
+    }
+}
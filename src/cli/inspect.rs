@@ -0,0 +1,119 @@
+use ethers::prelude::Address as EthersAddress;
+use ethers::providers::Middleware;
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::depth::DepthLadder;
+use crate::exchange::model::{Dex, load_pairs};
+use crate::pool_state::{PoolSnapshot, PoolState};
+use crate::pricing::{resolve_base, usd_price, weth};
+use crate::reference_price::{ReferenceMethod, fetch_reference_price};
+use crate::routes::load_route_pools;
+use crate::settings::settings;
+
+#[derive(Serialize, Debug)]
+pub struct PoolInspection {
+    // Pair name from the pair or route pool file, None for a pool given with --dex
+    pub pair: Option<String>,
+    pub block_number: u64,
+    pub pool: PoolSnapshot,
+    pub fee_zero_for_one: u32,
+    pub fee_one_for_zero: u32,
+    pub price_usd: f64,
+    pub depth: DepthLadder,
+}
+
+// Where a pool is listed, in the pair file or the route pool file
+struct ListedPool {
+    pair: String,
+    dex: Dex,
+    // The pair's base token; None for route pools, which are quoted like any other pool
+    base: Option<EthersAddress>,
+}
+
+fn find_pool(address: &str) -> Result<Option<ListedPool>, Box<dyn std::error::Error>> {
+    for pair in &load_pairs(&settings().pairs.file)? {
+        if let Some((dex, _)) = pair.pools().into_iter().find(|(_, info)| info.pair_address.eq_ignore_ascii_case(address)) {
+            let base = resolve_base(pair.base.as_deref())?;
+            return Ok(Some(ListedPool { pair: pair.pair.clone(), dex, base: Some(base) }));
+        }
+    }
+
+    let route_pool = load_route_pools()?
+        .into_iter()
+        .find(|pool| pool.pair_address.eq_ignore_ascii_case(address));
+    Ok(route_pool.and_then(|pool| {
+        let dex = Dex::from_name(&pool.dex)?;
+        Some(ListedPool { pair: pool.pair, dex, base: None })
+    }))
+}
+
+// Fetches the pool's state at the latest block. Pools outside the pair file are quoted in
+// WETH when they hold it and in token1 otherwise; those outside the route pool file too
+// need their DEX.
+pub async fn inspect_pool<M: Middleware + 'static>(
+    address: &str,
+    dex: Option<&str>,
+    provider: Arc<M>,
+) -> Result<PoolInspection, Box<dyn std::error::Error>> {
+    let (pair, dex, base) = match (find_pool(address)?, dex) {
+        (listed, Some(name)) => {
+            let dex = Dex::from_name(name).ok_or_else(|| format!("Unknown DEX {}", name))?;
+            match listed {
+                Some(listed) => (Some(listed.pair), dex, listed.base),
+                None => (None, dex, None),
+            }
+        }
+        (Some(listed), None) => (Some(listed.pair), listed.dex, listed.base),
+        (None, None) => {
+            return Err(format!("{} is not in the pair or route pool file; pass --dex to inspect it anyway", address).into());
+        }
+    };
+
+    let base = match base {
+        Some(base) => base,
+        None => PoolState::quote_token(address, weth(), provider.clone()).await.map_err(|e| e.to_string())?,
+    };
+
+    let block_number = provider.get_block_number().await.map_err(|e| e.to_string())?.as_u64();
+    let reference = fetch_reference_price(ReferenceMethod::from_settings(), block_number, provider.clone())
+        .await
+        .map_err(|e| e.to_string())?;
+    let state = PoolState::fetch(dex, address, base, provider.clone())
+        .await
+        .map_err(|e| e.to_string())?;
+    let base_usd = usd_price(state.base(), reference.weth_usd, provider)
+        .await
+        .map_err(|e| e.to_string())?;
+    let (price_usd, _) = state.usdc_values(base_usd);
+
+    Ok(PoolInspection {
+        pair,
+        block_number,
+        pool: state.snapshot(reference.weth_usd, base_usd),
+        fee_zero_for_one: state.fee_for(true),
+        fee_one_for_zero: state.fee_for(false),
+        price_usd,
+        depth: state.depth_ladder(base_usd),
+    })
+}
+
+pub fn print_inspection(inspection: &PoolInspection) {
+    let pool = &inspection.pool;
+    println!(
+        "🔎 {} {} {} at #{}",
+        pool.dex,
+        inspection.pair.as_deref().unwrap_or("(unlisted)"),
+        pool.pool_address,
+        inspection.block_number
+    );
+    println!("   token0: {} ({} decimals)", pool.token0, pool.token0_decimals);
+    println!("   token1: {} ({} decimals)", pool.token1, pool.token1_decimals);
+    println!("   base: {} (${:.4})", pool.base_token, pool.base_usd);
+    println!("   price: {} base per token (${:.6})", pool.price, inspection.price_usd);
+    println!("   sqrtPriceX96: {} | tick: {}", pool.sqrt_price_x96, pool.tick);
+    println!("   active liquidity: {} | TVL: {:.4} base", pool.active_liquidity, pool.liquidity);
+    println!("   fee: {} (0->1) / {} (1->0)", inspection.fee_zero_for_one, inspection.fee_one_for_zero);
+    println!("   depth: {}", inspection.depth.summary());
+    println!("   WETH/USD: {:.2}", pool.weth_usdc);
+}
@@ -0,0 +1,240 @@
+pub mod inspect;
+pub mod quote;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::history::report::HistoryReport;
+use crate::ipc_handle::subscriber::IpcStream;
+
+pub const USAGE: &str = "Usage: Scanner_Bot [--config <config.toml>] [--json | --tui] <command>
+
+Commands:
+  scan                                    Run the scanner (default)
+  discover [tokens.json] [output.json]    Rebuild the pair file from the DEX factories
+  quote <pair> <amount_usd>               Quote buying on each pool of a pair and selling on the others
+  inspect-pool <address> [--dex <dex>]    Dump the current state of a pool
+  replay <recording.jsonl> [report.json]  Backtest over a recording (alias: backtest)
+  subscribe [opportunities|routes|pools]  Print what the scanner publishes over IPC
+  history [report] [limit]                Query the pair history database; reports are
+                                          top-pairs (default), spreads, outcomes, selected
+  read-export <file>                      Read back a .parquet or .arrow snapshot export
+  mock-node <state.json> [block_ms]       Serve a scripted chain for end-to-end runs
+  help                                    Show this message
+
+Options:
+  --config <path>  Config file, instead of CONFIG_FILE or ./config.toml
+  --json           Machine-readable output: one JSON document, or one JSON object per line
+                   for commands that keep running
+  --tui            Scan with a terminal dashboard instead of log lines";
+
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+static TUI_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+// Whether --json was passed, for output that isn't printed by the command itself
+pub fn json_output() -> bool {
+    JSON_OUTPUT.load(Ordering::Relaxed)
+}
+
+pub fn set_json_output(json: bool) {
+    JSON_OUTPUT.store(json, Ordering::Relaxed);
+}
+
+// Whether --tui was passed; the dashboard owns the terminal then and nothing else may print
+pub fn tui_output() -> bool {
+    TUI_OUTPUT.load(Ordering::Relaxed)
+}
+
+pub fn set_tui_output(tui: bool) {
+    TUI_OUTPUT.store(tui, Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Scan,
+    Discover { token_list: Option<String>, output: String },
+    Quote { pair: String, amount_usd: f64 },
+    InspectPool { pool: String, dex: Option<String> },
+    Replay { recording: String, report: String },
+    Subscribe { stream: IpcStream },
+    History { report: HistoryReport, limit: usize },
+    ReadExport { file: String },
+    MockNode { state: String, block_interval_ms: u64 },
+    Help,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cli {
+    pub command: Command,
+    pub config: Option<String>,
+    pub json: bool,
+    pub tui: bool,
+}
+
+impl Cli {
+    // Parses everything after the program name. Options may come before or after the command.
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut config = None;
+        let mut json = false;
+        let mut tui = false;
+        let mut dex = None;
+        let mut positional = Vec::new();
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--json" => json = true,
+                "--tui" => tui = true,
+                "--config" => config = Some(args.next().ok_or("--config needs a path")?.clone()),
+                "--dex" => dex = Some(args.next().ok_or("--dex needs a DEX name")?.clone()),
+                "-h" | "--help" => positional.insert(0, "help".to_string()),
+                flag if flag.starts_with("--") => return Err(format!("Unknown option {}", flag)),
+                _ => positional.push(arg.clone()),
+            }
+        }
+
+        let name = positional.first().map(String::as_str).unwrap_or("scan");
+        let arg = |i: usize| positional.get(i).cloned();
+        let required = |i: usize, what: &str| arg(i).ok_or_else(|| format!("{} needs {}", name, what));
+
+        let command = match name {
+            "scan" => Command::Scan,
+            "discover" => Command::Discover {
+                token_list: arg(1).filter(|path| !path.is_empty()),
+                output: arg(2).unwrap_or_else(|| "src/data.discovered.json".to_string()),
+            },
+            "quote" => Command::Quote {
+                pair: required(1, "a pair name")?,
+                amount_usd: required(2, "an amount in USD")?
+                    .parse()
+                    .map_err(|_| "quote amount must be a number".to_string())?,
+            },
+            "inspect-pool" => Command::InspectPool { pool: required(1, "a pool address")?, dex: dex.clone() },
+            "replay" | "backtest" => Command::Replay {
+                recording: required(1, "a recording file")?,
+                report: arg(2).unwrap_or_else(|| "backtest_report.json".to_string()),
+            },
+            "subscribe" => {
+                let stream = arg(1).unwrap_or_else(|| "opportunities".to_string());
+                Command::Subscribe {
+                    stream: IpcStream::from_name(&stream).ok_or_else(|| format!("Unknown stream {}", stream))?,
+                }
+            }
+            "history" => {
+                let report = arg(1).unwrap_or_else(|| "top-pairs".to_string());
+                Command::History {
+                    report: HistoryReport::from_name(&report).ok_or_else(|| format!("Unknown report {}", report))?,
+                    limit: match arg(2) {
+                        Some(limit) => limit.parse().map_err(|_| "history limit must be a number".to_string())?,
+                        None => 20,
+                    },
+                }
+            }
+            "read-export" => Command::ReadExport { file: required(1, "an export file")? },
+            "mock-node" => Command::MockNode {
+                state: required(1, "a state file")?,
+                block_interval_ms: arg(2).and_then(|ms| ms.parse().ok()).unwrap_or(250),
+            },
+            "help" => Command::Help,
+            other => return Err(format!("Unknown command {}", other)),
+        };
+        if dex.is_some() && !matches!(command, Command::InspectPool { .. }) {
+            return Err("--dex only applies to inspect-pool".to_string());
+        }
+        if tui && command != Command::Scan {
+            return Err("--tui only applies to scan".to_string());
+        }
+        if tui && json {
+            return Err("--tui and --json can't be combined".to_string());
+        }
+
+        Ok(Self { command, config, json, tui })
+    }
+}
+
+// Prints a command's result as pretty JSON
+pub fn print_json<T: serde::Serialize>(value: &T) -> Result<(), Box<dyn std::error::Error>> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cli, Command};
+    use crate::history::report::HistoryReport;
+    use crate::ipc_handle::subscriber::IpcStream;
+
+    fn parse(args: &[&str]) -> Result<Cli, String> {
+        Cli::parse(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn no_arguments_scans_with_defaults() {
+        assert_eq!(parse(&[]).unwrap(), Cli { command: Command::Scan, config: None, json: false, tui: false });
+        assert_eq!(
+            parse(&["discover"]).unwrap().command,
+            Command::Discover { token_list: None, output: "src/data.discovered.json".to_string() }
+        );
+        assert_eq!(
+            parse(&["backtest", "blocks.jsonl"]).unwrap().command,
+            Command::Replay { recording: "blocks.jsonl".to_string(), report: "backtest_report.json".to_string() }
+        );
+        assert_eq!(parse(&["subscribe"]).unwrap().command, Command::Subscribe { stream: IpcStream::Opportunities });
+        assert_eq!(
+            parse(&["history"]).unwrap().command,
+            Command::History { report: HistoryReport::TopPairs, limit: 20 }
+        );
+        assert_eq!(
+            parse(&["mock-node", "state.json"]).unwrap().command,
+            Command::MockNode { state: "state.json".to_string(), block_interval_ms: 250 }
+        );
+        assert_eq!(parse(&["--help"]).unwrap().command, Command::Help);
+    }
+
+    #[test]
+    fn options_go_before_or_after_the_command() {
+        let expected = Cli {
+            command: Command::Quote { pair: "ARB/WETH".to_string(), amount_usd: 1000.0 },
+            config: Some("prod.toml".to_string()),
+            json: true,
+            tui: false,
+        };
+        for args in [
+            ["--config", "prod.toml", "--json", "quote", "ARB/WETH", "1000"],
+            ["quote", "ARB/WETH", "1000", "--json", "--config", "prod.toml"],
+            ["quote", "--json", "ARB/WETH", "--config", "prod.toml", "1000"],
+        ] {
+            assert_eq!(parse(&args).unwrap(), expected, "{:?}", args);
+        }
+        assert!(parse(&["scan", "--tui"]).unwrap().tui);
+        assert!(parse(&["--tui"]).unwrap().tui);
+    }
+
+    #[test]
+    fn dex_only_applies_to_inspect_pool() {
+        assert_eq!(
+            parse(&["--dex", "camelot", "inspect-pool", "0xabc"]).unwrap().command,
+            Command::InspectPool { pool: "0xabc".to_string(), dex: Some("camelot".to_string()) }
+        );
+        assert_eq!(
+            parse(&["inspect-pool", "0xabc"]).unwrap().command,
+            Command::InspectPool { pool: "0xabc".to_string(), dex: None }
+        );
+        for args in [&["quote", "ARB/WETH", "10", "--dex", "uniswap"][..], &["--dex", "uniswap"]] {
+            assert_eq!(parse(args).unwrap_err(), "--dex only applies to inspect-pool");
+        }
+        assert_eq!(parse(&["inspect-pool", "0xabc", "--dex"]).unwrap_err(), "--dex needs a DEX name");
+    }
+
+    #[test]
+    fn rejects_bad_combinations_and_arguments() {
+        assert_eq!(parse(&["--tui", "--json"]).unwrap_err(), "--tui and --json can't be combined");
+        assert_eq!(parse(&["--tui", "history"]).unwrap_err(), "--tui only applies to scan");
+        assert_eq!(parse(&["--config"]).unwrap_err(), "--config needs a path");
+        assert_eq!(parse(&["--verbose"]).unwrap_err(), "Unknown option --verbose");
+        assert_eq!(parse(&["trade"]).unwrap_err(), "Unknown command trade");
+        assert_eq!(parse(&["quote", "ARB/WETH"]).unwrap_err(), "quote needs an amount in USD");
+        assert_eq!(parse(&["quote", "ARB/WETH", "lots"]).unwrap_err(), "quote amount must be a number");
+        assert_eq!(parse(&["subscribe", "trades"]).unwrap_err(), "Unknown stream trades");
+        assert_eq!(parse(&["history", "top-pairs", "all"]).unwrap_err(), "history limit must be a number");
+    }
+}
@@ -0,0 +1,149 @@
+use ethers::providers::Middleware;
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::exchange::model::{Dex, PairData, load_pairs};
+use crate::pool_state::PoolState;
+use crate::pricing::{base_symbol, resolve_base, usd_price};
+use crate::reference_price::{ReferenceMethod, fetch_reference_price};
+use crate::settings::settings;
+use tracing::warn;
+
+#[derive(Serialize, Debug)]
+pub struct PairQuote {
+    pub pair: String,
+    pub block_number: u64,
+    pub weth_usd: f64,
+    pub base: String,
+    pub base_usd: f64,
+    pub amount_usd: f64,
+    pub amount_base: f64,
+    // Every buy/sell pool combination, most profitable first
+    pub quotes: Vec<DirectionQuote>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DirectionQuote {
+    pub buy_dex: String,
+    pub buy_pool: String,
+    pub buy_fee: u32,
+    pub sell_dex: String,
+    pub sell_pool: String,
+    pub sell_fee: u32,
+    // Tokens out of the buy leg
+    pub buy_amount_out: f64,
+    // Base tokens out of the sell leg
+    pub sell_amount_out: f64,
+    pub profit_usd: f64,
+}
+
+// Pair entry from the pair file, matched on its name case-insensitively
+pub fn find_pair(pair_name: &str) -> Result<PairData, Box<dyn std::error::Error>> {
+    let path = &settings().pairs.file;
+    load_pairs(path)?
+        .into_iter()
+        .find(|pair| pair.pair.eq_ignore_ascii_case(pair_name))
+        .ok_or_else(|| format!("{} is not in {}", pair_name, path).into())
+}
+
+// Quotes buying amount_usd worth of the base token's counterpart on each pool of the pair
+// and selling it on each other pool, with the same quoters the scanner uses
+pub async fn quote_pair<M: Middleware + 'static>(
+    pair_name: &str,
+    amount_usd: f64,
+    provider: Arc<M>,
+) -> Result<PairQuote, Box<dyn std::error::Error>> {
+    let pair = find_pair(pair_name)?;
+    let base = resolve_base(pair.base.as_deref())?;
+
+    let block_number = provider.get_block_number().await.map_err(|e| e.to_string())?.as_u64();
+    let reference = fetch_reference_price(ReferenceMethod::from_settings(), block_number, provider.clone())
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut pools: Vec<(Dex, String, PoolState)> = Vec::new();
+    for (dex, info) in pair.pools() {
+        match PoolState::fetch(dex, &info.pair_address, base, provider.clone()).await {
+            Ok(state) => pools.push((dex, info.pair_address.clone(), state)),
+            Err(e) => warn!("⚠️ Skipping {} {}: {}", dex.name(), info.pair_address, e),
+        }
+    }
+    let Some((_, _, first)) = pools.first() else {
+        return Err(format!("none of the pools of {} could be fetched", pair.pair).into());
+    };
+    let base_usd = usd_price(first.base(), reference.weth_usd, provider.clone())
+        .await
+        .map_err(|e| e.to_string())?;
+    let amount_base = amount_usd / base_usd;
+
+    let mut quotes = Vec::new();
+    for (buy_dex, buy_pool, buy_state) in &pools {
+        for (sell_dex, sell_pool, sell_state) in &pools {
+            if buy_pool == sell_pool {
+                continue;
+            }
+            let (buy_amount_out, buy_fee) = match buy_state.simulate(amount_base, "BUY", provider.clone()).await {
+                Ok(result) => result,
+                Err(e) => {
+                    warn!("⚠️ Buy on {} {} failed: {}", buy_dex.name(), buy_pool, e);
+                    continue;
+                }
+            };
+            let (sell_amount_out, sell_fee) = match sell_state.simulate(buy_amount_out, "SELL", provider.clone()).await {
+                Ok(result) => result,
+                Err(e) => {
+                    warn!("⚠️ Sell on {} {} failed: {}", sell_dex.name(), sell_pool, e);
+                    continue;
+                }
+            };
+            quotes.push(DirectionQuote {
+                buy_dex: buy_dex.name().to_string(),
+                buy_pool: buy_pool.clone(),
+                buy_fee,
+                sell_dex: sell_dex.name().to_string(),
+                sell_pool: sell_pool.clone(),
+                sell_fee,
+                buy_amount_out,
+                sell_amount_out,
+                profit_usd: sell_amount_out * base_usd - amount_usd,
+            });
+        }
+    }
+    quotes.sort_by(|a, b| b.profit_usd.total_cmp(&a.profit_usd));
+
+    Ok(PairQuote {
+        pair: pair.pair,
+        block_number,
+        weth_usd: reference.weth_usd,
+        base: base_symbol(base),
+        base_usd,
+        amount_usd,
+        amount_base,
+        quotes,
+    })
+}
+
+pub fn print_quote(quote: &PairQuote) {
+    println!(
+        "💱 {} at #{}: ${:.2} = {:.6} {} (WETH/USD {:.2})",
+        quote.pair, quote.block_number, quote.amount_usd, quote.amount_base, quote.base, quote.weth_usd
+    );
+    if quote.quotes.is_empty() {
+        println!("   No pool combination could be quoted");
+    }
+    for direction in &quote.quotes {
+        println!(
+            "   Buy {} {} ({}) -> Sell {} {} ({}) | Out: {:.6} -> {:.6} {} | Profit: ${:.4}",
+            direction.buy_dex,
+            direction.buy_pool,
+            direction.buy_fee,
+            direction.sell_dex,
+            direction.sell_pool,
+            direction.sell_fee,
+            direction.buy_amount_out,
+            direction.sell_amount_out,
+            quote.base,
+            direction.profit_usd
+        );
+    }
+}
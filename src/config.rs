@@ -3,6 +3,8 @@ use core::time::Duration;
 
 pub const CHAIN_ID: u64 = 42161;
 pub const UNISWAP_V3_FACTORY_ADDRESS: &str = "0x1F98431c8aD98523631AE4a59f267346ea31F984";
+pub const PANCAKESWAP_V3_FACTORY_ADDRESS: &str = "0x0BFbCF9fa4f9C56B0F40a671Ad40E0805A091865";
+pub const CAMELOT_V3_FACTORY_ADDRESS: &str = "0x1a3c9B1d2F0529D97f2afC5136Cc23e58f1FD35B";
 pub const IS_LOGGING_ENABLED: bool = false;
 
 pub fn is_simulation_logging_enabled() -> bool {
@@ -12,12 +14,114 @@ pub fn is_simulation_logging_enabled() -> bool {
 }
 
 pub const WETH_ADDRESS: &str = "82aF49447D8a07e3bd95BD0d56f35241523fBab1";
+pub const USDC_ADDRESS: &str = "af88d065e77c8cC2239327C5EDb3A432268e5831";
+pub const USDC_E_ADDRESS: &str = "FF970A61A04b1cA14834A43f5dE4533eBDDB5CC8";
+pub const USDT_ADDRESS: &str = "Fd086bC7CD5C481DCC9C85ebE478A1C0b69FCbb9";
+pub const WBTC_ADDRESS: &str = "2f2a2543B76A4166549F7aaB2e75Bef0aefC5B0f";
+
+// Tokens a pair can be quoted in via "Base" in data.json. Pairs without one are quoted in WETH.
+pub const BASE_TOKENS: [(&str, &str); 5] = [
+    ("WETH", WETH_ADDRESS),
+    ("USDC", USDC_ADDRESS),
+    ("USDC.e", USDC_E_ADDRESS),
+    ("USDT", USDT_ADDRESS),
+    ("WBTC", WBTC_ADDRESS),
+];
+// WETH/USDC pools the shared WETH/USD reference price is built from, by DEX name
+pub const WETH_USDC_REFERENCE_POOLS: [(&str, &str); 3] = [
+    ("Uniswap", "0xc6962004f452be9203591991d15f6b388e09e8d0"),
+    ("PancakeSwap", "0x7fcdc35463e3770c2fb992716cd070b63540b947"),
+    ("Camelot", "0xb1026b8e7276e7ac75410f1fcbbe21796e8f7526"),
+];
+// Blocks the last reference price may be reused for while its pools can't be fetched
+pub const MAX_REFERENCE_PRICE_AGE: u64 = 5;
+
+// Chainlink USD aggregators on Arbitrum, by token, for the optional oracle cross-check
+pub const CHAINLINK_USD_FEEDS: [(&str, &str); 7] = [
+    (WETH_ADDRESS, "0x639Fe6ab55C921f74e7fac1ee960C0B6293ba612"),
+    (WBTC_ADDRESS, "0x6ce185860a4963106506C203335A2910413708e9"),
+    (USDC_ADDRESS, "0x50834F3163758fcC1Df9973b6e91f0F0F0434aD3"),
+    (USDC_E_ADDRESS, "0x50834F3163758fcC1Df9973b6e91f0F0F0434aD3"),
+    (USDT_ADDRESS, "0x3f3f5dF88dC9F13eac63DF89EC16ef6e7E25DdE7"),
+    ("912CE59144191C1204E64559FE8253a0e49E6548", "0xb2A824043730FE05F3DA2efaFa1CBbe83fa548D6"),
+    ("f97f4df75117a78c1A5a0DBb814Af92458539FB4", "0x86E53CF1B870786351Da77A57575e79CB55812CB"),
+];
+
+// Stablecoins are priced at $1
+pub const USD_STABLECOINS: [&str; 3] = [USDC_ADDRESS, USDC_E_ADDRESS, USDT_ADDRESS];
+// Uniswap V3 WBTC/WETH 0.05% pool used to price WBTC
+pub const WBTC_WETH_REFERENCE_POOL: &str = "0x2f5e87C9312fa29aed5c179E456625D79015299c";
 
 pub const UNISWAP_V3_SWAP_ROUTER_ADDRESS: &str = "0xE592427A0AEce92De3Edee1F18E0157C05861564";
 pub const PANCAKESWAP_V3_SWAP_ROUTER_ADDRESS: &str = "0x1b81D678ffb9C0263b24A97847620C99d213eB14";
 pub const CAMELOT_V3_SWAP_ROUTER_ADDRESS: &str = "0x1F721E2E82F6676FCE4eA07A5958cF098D339e18";
 
+pub const UNISWAP_V3_QUOTER_ADDRESS: &str = "0xb27308f9F90D607463bb33eA1BeBb41C27CE5AB6";
+pub const PANCAKESWAP_V3_QUOTER_ADDRESS: &str = "0xB048Bbc1Ee6b733FFfCFb9e9CeF7375518e25997";
+pub const CAMELOT_V3_QUOTER_ADDRESS: &str = "0x0Fc73040b26E9bC8514fA028D998E73A254Fa76E";
+
+pub const PAIRS_FILE: &str = "src/data.json";
+pub const ROUTE_POOLS_FILE: &str = "src/route_pools.json";
+
 pub const IPC_CYCLE_TIME: Duration = Duration::from_millis(200);
+pub const QUOTE_SERVICE_SOCKET_PATH: &str = "/tmp/arbiscan_quote.sock";
+// Where GET /metrics is served; an empty address turns the endpoint off
+pub const METRICS_LISTEN_ADDR: &str = "127.0.0.1:9184";
+// Where the HTTP/WebSocket API is served; an empty address turns it off. /health reports
+// stale when no monitor finished a block for API_STALE_AFTER.
+pub const API_LISTEN_ADDR: &str = "127.0.0.1:9185";
+pub const API_STALE_AFTER: Duration = Duration::from_secs(30);
+// Longest request line and headers the metrics and API listeners accept
+pub const HTTP_MAX_HEAD_LEN: usize = 8192;
+// Time a client gets to send the whole request head
+pub const HTTP_READ_TIMEOUT: Duration = Duration::from_secs(10);
+// Pause after a listener fails to accept a connection, e.g. out of file descriptors
+pub const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
+// Profitable pairs kept for the live view, and live events queued per subscriber
+pub const LIVE_OPPORTUNITIES_LEN: usize = 100;
+pub const LIVE_EVENT_CHANNEL_CAPACITY: usize = 1024;
+// How often the --tui dashboard redraws and checks for keys
+pub const TUI_REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+// Current fee per pool, rewritten from the fee registry when a fee changed
+pub const FEE_SNAPSHOT_FILE: &str = "fee.log";
+pub const FEE_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(10);
+// Fee changes kept per pool, and queued per fee change subscriber
+pub const FEE_HISTORY_LEN: usize = 32;
+pub const FEE_CHANGE_CHANNEL_CAPACITY: usize = 256;
+// Pair history is written in transactions of up to HISTORY_BATCH_SIZE records, at least
+// every HISTORY_FLUSH_INTERVAL; records beyond HISTORY_QUEUE_LEN waiting are dropped
+pub const HISTORY_BATCH_SIZE: usize = 1000;
+pub const HISTORY_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+pub const HISTORY_QUEUE_LEN: usize = 50_000;
+// Snapshot export: file format when none is configured, and rows per written batch, at
+// least every EXPORT_FLUSH_INTERVAL; rows beyond EXPORT_QUEUE_LEN waiting are dropped
+pub const EXPORT_FORMAT: &str = "parquet";
+pub const EXPORT_BATCH_ROWS: usize = 4096;
+pub const EXPORT_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+pub const EXPORT_QUEUE_LEN: usize = 100_000;
+// Tracing filter used when RUST_LOG isn't set, and how often the JSON log file rolls over
+pub const LOG_LEVEL: &str = "info";
+pub const LOG_ROTATION: &str = "daily";
+// Per-pool feed written by the monitors, read with shm_reader
+pub const SHM_PATH: &str = "/tmp/pool_data.shm";
+
+// Longest cycle the route engine searches, and how many blocks a cached pool
+// state may lag behind the current block before routes stop using it
+pub const MAX_ROUTE_HOPS: usize = 3;
+pub const MAX_ROUTE_POOL_AGE: u64 = 2;
+
+// Consecutive failures after which a pool is skipped, and for how many blocks. Each
+// quarantine in a row doubles the length, up to MAX_QUARANTINE_BLOCKS.
+pub const QUARANTINE_AFTER_FAILURES: u32 = 5;
+pub const QUARANTINE_BLOCKS: u64 = 50;
+pub const MAX_QUARANTINE_BLOCKS: u64 = 3200;
+
+// Price moves the depth ladder is measured at, and which of them sets the tier and caps
+// the trade sizes (±1%)
+pub const DEPTH_BANDS: [f64; 3] = [0.005, 0.01, 0.02];
+pub const TIER_DEPTH_BAND: usize = 1;
+// Minimum USD depth within the tier band for tiers 0 to 4
+pub const TIER_MIN_DEPTH: [f64; 5] = [0.0, 2_000.0, 5_000.0, 20_000.0, 100_000.0];
 
 pub const TIER0_PRICE_DIFF: f64 = 0.035;
 pub const TIER1_PRICE_DIFF: f64 = 0.016;
@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::{DEPTH_BANDS, TIER_DEPTH_BAND};
+
+// USD that can be traded before the price moves by each of DEPTH_BANDS, taking the
+// thinner of the two directions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct DepthLadder {
+    pub usd: [f64; DEPTH_BANDS.len()],
+}
+
+impl DepthLadder {
+    // Depth at the band that sets the tier and caps the trade sizes
+    pub fn tier_depth(&self) -> f64 {
+        self.usd[TIER_DEPTH_BAND]
+    }
+
+    // Band by band minimum, for a pair or route that is only as deep as its thinnest pool
+    pub fn min(&self, other: &DepthLadder) -> DepthLadder {
+        let mut usd = self.usd;
+        for (depth, other) in usd.iter_mut().zip(other.usd) {
+            *depth = depth.min(other);
+        }
+        DepthLadder { usd }
+    }
+
+    // e.g. "±0.5% $12k / ±1% $25k / ±2% $51k"
+    pub fn summary(&self) -> String {
+        DEPTH_BANDS
+            .iter()
+            .zip(self.usd)
+            .map(|(band, usd)| format!("±{}% ${:.0}k", band * 100.0, usd / 1000.0))
+            .collect::<Vec<_>>()
+            .join(" / ")
+    }
+}
+
+// Ticks between the current tick and the farthest band bound, which the fetchers read
+// initialized ticks over. The lower bound is the farther one.
+pub fn band_tick_range() -> i32 {
+    let widest = DEPTH_BANDS.iter().copied().fold(0.0, f64::max);
+    ((1.0 - widest).ln() / 1.0001f64.ln()).abs().ceil() as i32
+}
+
+// Depth of a concentrated-liquidity pool from its current tick, active liquidity and the
+// initialized ticks around it with their liquidityNet. Each band's price bounds are converted
+// to ticks and the swap is walked one liquidity range at a time, adding liquidityNet when a
+// tick is crossed upwards and removing it downwards. Token prices are USD per whole token.
+pub fn depth_ladder(
+    tick: i32,
+    active_liquidity: u128,
+    initialized_ticks: &[(i32, i128)],
+    decimals: (u8, u8),
+    token_usd: (f64, f64),
+) -> DepthLadder {
+    let tick_base = 1.0001f64.ln();
+    let sqrt_at = |tick: f64| (tick * tick_base / 2.0).exp();
+    let current = sqrt_at(tick as f64);
+
+    // Ticks in the order a swap meets them, as (sqrt price, liquidity change once crossed)
+    let mut above: Vec<(f64, f64)> = initialized_ticks
+        .iter()
+        .filter(|(initialized, _)| *initialized > tick)
+        .map(|&(initialized, net)| (sqrt_at(initialized as f64), net as f64))
+        .collect();
+    above.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut below: Vec<(f64, f64)> = initialized_ticks
+        .iter()
+        .filter(|(initialized, _)| *initialized <= tick)
+        .map(|&(initialized, net)| (sqrt_at(initialized as f64), -(net as f64)))
+        .collect();
+    below.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let liquidity = active_liquidity as f64;
+    let mut ladder = DepthLadder::default();
+    for (depth, band) in ladder.usd.iter_mut().zip(DEPTH_BANDS) {
+        let upper = sqrt_at(tick as f64 + (1.0 + band).ln() / tick_base);
+        let lower = sqrt_at(tick as f64 + (1.0 - band).ln() / tick_base);
+
+        // token1 in pushes the price up to the upper bound, token0 in pushes it down
+        let token1_in = swap_in(current, upper, liquidity, &above, |from, to| to - from)
+            / 10f64.powi(decimals.1 as i32);
+        let token0_in = swap_in(current, lower, liquidity, &below, |from, to| 1.0 / to - 1.0 / from)
+            / 10f64.powi(decimals.0 as i32);
+
+        let usd = (token1_in * token_usd.1).min(token0_in * token_usd.0);
+        *depth = if usd.is_finite() { usd.max(0.0) } else { 0.0 };
+    }
+    ladder
+}
+
+// Raw input that moves the sqrt price from `current` to `target`. `crossed` are the
+// initialized ticks nearest first and `per_liquidity` the input per unit of liquidity
+// between two sqrt prices.
+fn swap_in(
+    current: f64,
+    target: f64,
+    mut liquidity: f64,
+    crossed: &[(f64, f64)],
+    per_liquidity: impl Fn(f64, f64) -> f64,
+) -> f64 {
+    let mut sqrt_price = current;
+    let mut amount = 0.0;
+    for &(next, change) in crossed.iter().take_while(|(next, _)| (target - next) * (target - current) > 0.0) {
+        amount += liquidity.max(0.0) * per_liquidity(sqrt_price, next);
+        sqrt_price = next;
+        liquidity += change;
+    }
+    amount + liquidity.max(0.0) * per_liquidity(sqrt_price, target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{band_tick_range, depth_ladder};
+
+    const LIQUIDITY: u128 = 1_000_000_000_000_000_000;
+
+    fn ladder(initialized_ticks: &[(i32, i128)]) -> [f64; 3] {
+        depth_ladder(0, LIQUIDITY, initialized_ticks, (18, 18), (1.0, 1.0)).usd
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() <= expected.abs() * 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn without_ticks_the_ladder_is_the_active_liquidity() {
+        let flat = ladder(&[]);
+        // token1 in is the thinner side: L * (sqrt(1.01) - 1)
+        assert_close(flat[1], (1.01f64.sqrt() - 1.0) * LIQUIDITY as f64 / 1e18);
+        assert!(flat[0] < flat[1] && flat[1] < flat[2]);
+        // Ticks outside the widest band change nothing
+        assert_eq!(ladder(&[(band_tick_range() + 1, -(LIQUIDITY as i128))]), flat);
+    }
+
+    #[test]
+    fn liquidity_ending_inside_a_band_caps_the_depth() {
+        // Every position ends 30 ticks up, inside the ±0.5% band
+        let capped = ladder(&[(30, -(LIQUIDITY as i128))]);
+        let expected = ((30.0 * 1.0001f64.ln() / 2.0).exp() - 1.0) * LIQUIDITY as f64 / 1e18;
+        for depth in capped {
+            assert_close(depth, expected);
+        }
+    }
+
+    #[test]
+    fn liquidity_coming_into_range_deepens_the_wider_bands() {
+        // Another position of the same size starts 60 ticks away on either side
+        let flat = ladder(&[]);
+        let stepped = ladder(&[(60, LIQUIDITY as i128), (-60, -(LIQUIDITY as i128))]);
+        // ±0.5% stays inside the current range
+        assert_close(stepped[0], flat[0]);
+        assert!(stepped[1] > flat[1]);
+        // ±2% is now more than twice ±1%, which constant liquidity can't give
+        assert!(stepped[2] > 2.2 * stepped[1]);
+    }
+
+    #[test]
+    fn band_range_covers_the_lower_bound_of_the_widest_band() {
+        // 0.98 = 1.0001^-202.03
+        assert_eq!(band_tick_range(), 203);
+    }
+}
@@ -0,0 +1,301 @@
+use ethers::prelude::{abigen, Address as EthersAddress};
+use ethers::providers::Middleware;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+
+use crate::config::WETH_ADDRESS;
+use crate::exchange::FetchDepth;
+use crate::exchange::camelot::get_camelot_info;
+use crate::exchange::model::{Dex, ExchangeInfo, ExchangePools, PairData, load_pairs};
+use crate::exchange::pancakeswap::get_pancakeswap_info;
+use crate::exchange::uniswap::get_uniswap_info;
+use crate::reference_price::{ReferenceMethod, fetch_reference_price};
+use crate::settings::settings;
+
+pub mod watcher;
+
+const UNISWAP_FEE_TIERS: [u32; 4] = [100, 500, 3000, 10000];
+const PANCAKESWAP_FEE_TIERS: [u32; 4] = [100, 500, 2500, 10000];
+
+abigen!(
+    V3Factory,
+    r#"[
+        function getPool(address tokenA, address tokenB, uint24 fee) external view returns (address pool)
+    ]"#,
+);
+
+abigen!(
+    AlgebraFactory,
+    r#"[
+        function poolByPair(address tokenA, address tokenB) external view returns (address pool)
+    ]"#,
+);
+
+abigen!(
+    PairTokens,
+    r#"[
+        function token0() external view returns (address)
+        function token1() external view returns (address)
+    ]"#,
+);
+
+// Entry of the optional token list passed to `discover`
+#[derive(Deserialize, Debug, Clone)]
+pub struct TokenListEntry {
+    #[serde(rename = "Symbol")]
+    pub symbol: String,
+    #[serde(rename = "Address")]
+    pub address: String,
+}
+
+struct DiscoveredPool {
+    dex: Dex,
+    address: String,
+    tvl_usd: f64,
+}
+
+// Scans the Uniswap V3, PancakeSwap V3 and Camelot factories for WETH pools of every
+// token in the list (or of every token already in src/data.json), measures their TVL
+// and writes every pool above the minimum, one per fee tier, to a pair file in the
+// data.json format. Progress goes to stderr; the summary is returned.
+pub async fn run_discover<M: Middleware + 'static>(
+    provider: Arc<M>,
+    token_list_path: Option<&str>,
+    output_path: &str,
+) -> Result<DiscoverSummary, Box<dyn std::error::Error>> {
+    let min_tvl = settings().discovery.min_tvl;
+
+    // No pair file yet is an empty one
+    let existing = match load_pairs(&settings().pairs.file) {
+        Ok(pairs) => pairs,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let existing_pools: HashMap<String, (String, Dex)> = existing
+        .iter()
+        .flat_map(|pair| {
+            pair.pools()
+                .into_iter()
+                .map(|(dex, info)| (info.pair_address.to_lowercase(), (pair.pair.clone(), dex)))
+        })
+        .collect();
+
+    let weth: EthersAddress = format!("0x{}", WETH_ADDRESS).parse()?;
+    let tokens = match token_list_path {
+        Some(path) => {
+            let list: Vec<TokenListEntry> = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+            list.into_iter()
+                .map(|entry| Ok((entry.address.parse::<EthersAddress>()?, format!("{}/WETH", entry.symbol))))
+                .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?
+        }
+        None => tokens_from_existing(&existing, weth, provider.clone()).await,
+    };
+
+    let block_number = provider.get_block_number().await?.as_u64();
+    let reference = fetch_reference_price(ReferenceMethod::from_settings(), block_number, provider.clone())
+        .await
+        .map_err(|e| e.to_string())?;
+    let weth_usdc = reference.weth_usd;
+    eprintln!("🔍 Discovering pools for {} tokens (WETH/USD {:.2} {}, min TVL ${:.0})", tokens.len(), weth_usdc, reference.source(), min_tvl);
+
+    let settings = settings();
+    let uniswap_factory = V3Factory::new(settings.dex(Dex::Uniswap).factory.parse::<EthersAddress>()?, provider.clone());
+    let pancake_factory = V3Factory::new(settings.dex(Dex::PancakeSwap).factory.parse::<EthersAddress>()?, provider.clone());
+    let camelot_factory = AlgebraFactory::new(settings.dex(Dex::Camelot).factory.parse::<EthersAddress>()?, provider.clone());
+
+    let mut pairs = Vec::new();
+    let mut discovered: HashSet<String> = HashSet::new();
+    // Pools found on chain but left out of the file, with the reason
+    let mut rejected: BTreeMap<String, String> = BTreeMap::new();
+
+    for (token, pair_name) in &tokens {
+        let mut candidates = Vec::new();
+        for fee in UNISWAP_FEE_TIERS {
+            if let Ok(pool) = uniswap_factory.get_pool(*token, weth, fee).call().await {
+                candidates.push((Dex::Uniswap, pool));
+            }
+        }
+        for fee in PANCAKESWAP_FEE_TIERS {
+            if let Ok(pool) = pancake_factory.get_pool(*token, weth, fee).call().await {
+                candidates.push((Dex::PancakeSwap, pool));
+            }
+        }
+        if let Ok(pool) = camelot_factory.pool_by_pair(*token, weth).call().await {
+            candidates.push((Dex::Camelot, pool));
+        }
+
+        // Every fee tier that holds enough liquidity is kept, so the monitors can also
+        // arbitrage between tiers of the same DEX
+        let mut kept: Vec<DiscoveredPool> = Vec::new();
+        for (dex, pool) in candidates {
+            if pool.is_zero() {
+                continue;
+            }
+            let address = format!("0x{}", hex::encode(pool.as_bytes()));
+            let tvl_usd = match measure_pool(dex, &address, *token, weth, provider.clone()).await {
+                Ok(tvl_weth) => tvl_weth * weth_usdc,
+                Err(e) => {
+                    rejected.insert(address, format!("{} {} failed validation: {}", pair_name, dex.key(), e));
+                    continue;
+                }
+            };
+            if tvl_usd < min_tvl {
+                rejected.insert(address, format!("{} {} TVL ${:.0} below minimum", pair_name, dex.key(), tvl_usd));
+                continue;
+            }
+            kept.push(DiscoveredPool { dex, address, tvl_usd });
+        }
+
+        // The monitors compare pools against each other, so a pair needs at least two
+        if kept.len() < 2 {
+            for pool in &kept {
+                rejected.insert(pool.address.clone(), format!("{} only has one pool on {}", pair_name, pool.dex.key()));
+            }
+            continue;
+        }
+
+        let mut by_dex: BTreeMap<Dex, Vec<ExchangeInfo>> = BTreeMap::new();
+        for pool in kept {
+            eprintln!(
+                "   {} {} {} TVL ${:.0}{}",
+                pair_name,
+                pool.dex.key(),
+                pool.address,
+                pool.tvl_usd,
+                if existing_pools.contains_key(&pool.address) { "" } else { " 🆕 new" }
+            );
+            discovered.insert(pool.address.clone());
+            by_dex.entry(pool.dex).or_default().push(ExchangeInfo {
+                pair_address: pool.address,
+                liquidity: pool.tvl_usd as u64,
+            });
+        }
+
+        let mut pair = PairData {
+            pair: pair_name.clone(),
+            base: None,
+            uni_swap: None,
+            camelot: None,
+            pancake_swap: None,
+        };
+        for (dex, mut infos) in by_dex {
+            // Deepest pool first
+            infos.sort_by_key(|info| std::cmp::Reverse(info.liquidity));
+            let pools = ExchangePools::from_pools(infos);
+            match dex {
+                Dex::Uniswap => pair.uni_swap = pools,
+                Dex::PancakeSwap => pair.pancake_swap = pools,
+                Dex::Camelot => pair.camelot = pools,
+            }
+        }
+        pairs.push(pair);
+    }
+
+    let new_pools = discovered.iter().filter(|p| !existing_pools.contains_key(*p)).count();
+    let mut dead_pools = Vec::new();
+    for (address, (pair_name, dex)) in &existing_pools {
+        if discovered.contains(address) {
+            continue;
+        }
+        dead_pools.push(address.clone());
+        let reason = rejected.get(address).map(String::as_str).unwrap_or("not returned by the factory");
+        eprintln!("   💀 {} {} {} dropped: {}", pair_name, dex.key(), address, reason);
+    }
+    dead_pools.sort();
+
+    let file = File::create(output_path)?;
+    serde_json::to_writer_pretty(file, &pairs)?;
+
+    Ok(DiscoverSummary {
+        output: output_path.to_string(),
+        pairs: pairs.len(),
+        new_pools,
+        dead_pools,
+        rejected,
+    })
+}
+
+#[derive(Serialize, Debug)]
+pub struct DiscoverSummary {
+    pub output: String,
+    pub pairs: usize,
+    pub new_pools: usize,
+    // Pools in the current pair file that were not written again
+    pub dead_pools: Vec<String>,
+    // Pools found on chain but left out, with the reason
+    pub rejected: BTreeMap<String, String>,
+}
+
+impl DiscoverSummary {
+    pub fn print(&self) {
+        println!(
+            "✅ Wrote {} pairs to {} ({} new pools, {} dead pools, {} rejected candidates)",
+            self.pairs,
+            self.output,
+            self.new_pools,
+            self.dead_pools.len(),
+            self.rejected.len()
+        );
+    }
+}
+
+// Without a token list, rediscover the non-WETH side of every pair already in data.json
+async fn tokens_from_existing<M: Middleware + 'static>(
+    existing: &[PairData],
+    weth: EthersAddress,
+    provider: Arc<M>,
+) -> Vec<(EthersAddress, String)> {
+    let mut tokens = Vec::new();
+    for pair in existing {
+        for (_, info) in pair.pools() {
+            let Ok(address) = info.pair_address.parse::<EthersAddress>() else {
+                continue;
+            };
+            let pool = PairTokens::new(address, provider.clone());
+            let (Ok(token0), Ok(token1)) = (pool.token_0().call().await, pool.token_1().call().await) else {
+                continue;
+            };
+            let token = if token0 == weth { token1 } else { token0 };
+            if !tokens.iter().any(|(t, _)| *t == token) {
+                tokens.push((token, pair.pair.clone()));
+            }
+            break;
+        }
+    }
+    tokens
+}
+
+// Fetches the pool through the regular exchange code, checks it trades the expected
+// tokens and returns its TVL in WETH
+pub async fn measure_pool<M: Middleware + 'static>(
+    dex: Dex,
+    address: &str,
+    token: EthersAddress,
+    weth: EthersAddress,
+    provider: Arc<M>,
+) -> Result<f64, Box<dyn std::error::Error>> {
+    let (token0, token1, liquidity) = match dex {
+        Dex::Uniswap => {
+            let info = get_uniswap_info(address.to_string(), weth, FetchDepth::PriceOnly, provider).await?;
+            (info.token0, info.token1, info.liquidity)
+        }
+        Dex::PancakeSwap => {
+            let info = get_pancakeswap_info(address.to_string(), weth, FetchDepth::PriceOnly, provider).await?;
+            (info.token0, info.token1, info.liquidity)
+        }
+        Dex::Camelot => {
+            let info = get_camelot_info(address.to_string(), weth, FetchDepth::PriceOnly, provider).await?;
+            (info.token0, info.token1, info.liquidity)
+        }
+    };
+
+    let expected = (token0 == token && token1 == weth) || (token0 == weth && token1 == token);
+    if !expected {
+        return Err(format!("pool trades {:?}/{:?}", token0, token1).into());
+    }
+
+    Ok(liquidity as f64)
+}
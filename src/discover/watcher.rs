@@ -0,0 +1,236 @@
+use ethers::prelude::{Address as EthersAddress, Filter, Log, H256};
+use ethers::providers::Middleware;
+use ethers::utils::keccak256;
+use futures_util::StreamExt;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+use crate::config::WETH_ADDRESS;
+use crate::discover::measure_pool;
+use crate::exchange::model::Dex;
+use crate::ipc_handle::{NewPool, PoolAnnouncer};
+use crate::pool_state::PoolCache;
+use crate::reference_price::ReferencePriceService;
+use crate::routes::{address_bytes, router_address};
+use crate::settings::settings;
+use crate::websocket::block_source::BlockStream;
+use crate::websocket::stream_handler::LivePairs;
+
+// Pool seen in a PoolCreated event, waiting out the warm-up before its TVL is checked
+struct PendingPool {
+    dex: Dex,
+    pool: EthersAddress,
+    token0: EthersAddress,
+    token1: EthersAddress,
+    fee: u32,
+    pair_name: String,
+    created_block: u64,
+}
+
+// Watches the factories' PoolCreated events on every block. A new WETH pool for a token
+// we already trade is added to the live pair set once it has passed the warm-up and
+// holds at least [discovery] min_tvl, then announced on the arbiscan_pools IPC service.
+pub async fn handle_pool_discovery<M: Middleware + 'static>(
+    provider: Arc<M>,
+    mut blocks: BlockStream,
+    pool_cache: Arc<PoolCache>,
+    live_pairs: LivePairs,
+    announcer: PoolAnnouncer,
+    reference_price: Arc<ReferencePriceService>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    // New pools get the warm-up to attract liquidity before they are judged
+    let warmup = settings().discovery.warmup_blocks;
+    let min_tvl = settings().discovery.min_tvl;
+    let weth: EthersAddress = format!("0x{}", WETH_ADDRESS).parse()?;
+    let settings = settings();
+    let uniswap_factory: EthersAddress = settings.dex(Dex::Uniswap).factory.parse()?;
+    let pancake_factory: EthersAddress = settings.dex(Dex::PancakeSwap).factory.parse()?;
+    let camelot_factory: EthersAddress = settings.dex(Dex::Camelot).factory.parse()?;
+    let v3_pool_created = H256::from(keccak256("PoolCreated(address,address,uint24,int24,address)"));
+    let algebra_pool_created = H256::from(keccak256("Pool(address,address,address)"));
+
+    let filter = Filter::new()
+        .address(vec![uniswap_factory, pancake_factory, camelot_factory])
+        .topic0(vec![v3_pool_created, algebra_pool_created]);
+
+    info!("🛰️ Pool discovery watching factories (warm-up {} blocks, min TVL ${:.0})", warmup, min_tvl);
+
+    let mut next_block: Option<u64> = None;
+    let mut pending: Vec<PendingPool> = Vec::new();
+
+    while let Some(block_number) = blocks.next().await {
+        // Set before the first call, so a failed first range is retried from where it started
+        let from_block = *next_block.get_or_insert(block_number);
+        if from_block <= block_number {
+            let range = filter.clone().from_block(from_block).to_block(block_number);
+            match provider.get_logs(&range).await {
+                Ok(logs) => {
+                    // Only tokens the monitors already trade against WETH qualify
+                    let traded = traded_tokens(&pool_cache, weth);
+                    for log in logs {
+                        let dex = if log.address == uniswap_factory {
+                            Dex::Uniswap
+                        } else if log.address == pancake_factory {
+                            Dex::PancakeSwap
+                        } else {
+                            Dex::Camelot
+                        };
+                        let Some((token0, token1, fee, pool)) = parse_pool_created(&log, dex) else {
+                            continue;
+                        };
+                        let token = if token0 == weth { token1 } else if token1 == weth { token0 } else { continue };
+                        let Some(pair_name) = traded.get(&token) else {
+                            continue;
+                        };
+
+                        info!(
+                            "🛰️ New {} pool {:?} for {} created at #{}, warming up",
+                            dex.name(),
+                            pool,
+                            pair_name,
+                            log.block_number.map(|b| b.as_u64()).unwrap_or(block_number)
+                        );
+                        pending.push(PendingPool {
+                            dex,
+                            pool,
+                            token0,
+                            token1,
+                            fee,
+                            pair_name: pair_name.clone(),
+                            created_block: log.block_number.map(|b| b.as_u64()).unwrap_or(block_number),
+                        });
+                    }
+                    next_block = Some(block_number + 1);
+                }
+                // The range is retried on the next block
+                Err(e) => warn!("Failed to fetch PoolCreated logs from #{}: {}", from_block, e),
+            }
+        }
+
+        let (ready, waiting): (Vec<PendingPool>, Vec<PendingPool>) = pending
+            .into_iter()
+            .partition(|p| p.created_block + warmup <= block_number);
+        pending = waiting;
+        if ready.is_empty() {
+            continue;
+        }
+
+        let weth_usdc = match reference_price.for_block(block_number, provider.clone()).await {
+            Ok(reference) => reference.weth_usd,
+            Err(_) => {
+                pending.extend(ready);
+                continue;
+            }
+        };
+
+        for candidate in ready {
+            let address = format!("0x{}", hex::encode(candidate.pool.as_bytes()));
+            let token = if candidate.token0 == weth { candidate.token1 } else { candidate.token0 };
+            let tvl_usd = match measure_pool(candidate.dex, &address, token, weth, provider.clone()).await {
+                Ok(tvl_weth) => tvl_weth * weth_usdc,
+                Err(e) => {
+                    let reason = e.to_string();
+                    warn!("🗑️ Skipping new {} pool {} for {}: {}", candidate.dex.name(), address, candidate.pair_name, reason);
+                    continue;
+                }
+            };
+            if tvl_usd < min_tvl {
+                info!(
+                    "🗑️ Skipping new {} pool {} for {}: TVL ${:.0} below ${:.0} after warm-up",
+                    candidate.dex.name(), address, candidate.pair_name, tvl_usd, min_tvl
+                );
+                continue;
+            }
+
+            let added = live_pairs.add_pool(candidate.dex, &address, &candidate.pair_name, weth);
+            info!(
+                "✨ Added {} pool {} for {} (TVL ${:.0}, fee {}) to {} monitored pairings",
+                candidate.dex.name(), address, candidate.pair_name, tvl_usd, candidate.fee, added
+            );
+
+            let announcement = NewPool {
+                dex: address_bytes(&router_address(candidate.dex)),
+                pool: candidate.pool.0,
+                token0: candidate.token0.0,
+                token1: candidate.token1.0,
+                fee: candidate.fee,
+                liquidity_usdc: tvl_usd,
+            };
+            if let Err(e) = announcer.announce(&announcement) {
+                error!("❌ Failed to announce new pool {}: {}", address, e);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Non-WETH token of every cached WETH-based pool, mapped to its pair name
+fn traded_tokens(pool_cache: &PoolCache, weth: EthersAddress) -> HashMap<EthersAddress, String> {
+    let mut tokens = HashMap::new();
+    for cached in pool_cache.all().into_iter().filter(|p| p.state.base() == weth) {
+        let (token0, token1) = cached.state.tokens();
+        if token0 == weth {
+            tokens.insert(token1, cached.pair_name);
+        } else if token1 == weth {
+            tokens.insert(token0, cached.pair_name);
+        }
+    }
+    tokens
+}
+
+// Returns (token0, token1, fee, pool). Algebra pools have no fixed fee, so it is reported as 0.
+fn parse_pool_created(log: &Log, dex: Dex) -> Option<(EthersAddress, EthersAddress, u32, EthersAddress)> {
+    let token0 = EthersAddress::from(*log.topics.get(1)?);
+    let token1 = EthersAddress::from(*log.topics.get(2)?);
+    match dex {
+        // PoolCreated(token0, token1, fee indexed, tickSpacing, pool)
+        Dex::Uniswap | Dex::PancakeSwap => {
+            let fee = log.topics.get(3)?.to_low_u64_be() as u32;
+            let pool = EthersAddress::from_slice(log.data.get(44..64)?);
+            Some((token0, token1, fee, pool))
+        }
+        // Pool(token0, token1, pool)
+        Dex::Camelot => {
+            let pool = EthersAddress::from_slice(log.data.get(12..32)?);
+            Some((token0, token1, 0, pool))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_pool_created;
+    use crate::exchange::model::Dex;
+    use ethers::abi::{Token, encode};
+    use ethers::prelude::{Address, Bytes, H256, Log, U256};
+
+    fn log(topics: Vec<H256>, data: Vec<u8>) -> Log {
+        Log { topics, data: Bytes::from(data), ..Log::default() }
+    }
+
+    #[test]
+    fn reads_v3_pool_created() {
+        let (token0, token1, pool) = (Address::repeat_byte(0x0a), Address::repeat_byte(0x0e), Address::repeat_byte(0x90));
+        // token0, token1 and fee are indexed; tickSpacing and pool are in the data
+        let topics = vec![H256::zero(), H256::from(token0), H256::from(token1), H256::from_low_u64_be(500)];
+        let data = encode(&[Token::Int(U256::from(10)), Token::Address(pool)]);
+        for dex in [Dex::Uniswap, Dex::PancakeSwap] {
+            assert_eq!(parse_pool_created(&log(topics.clone(), data.clone()), dex), Some((token0, token1, 500, pool)));
+        }
+
+        assert_eq!(parse_pool_created(&log(topics[..3].to_vec(), data.clone()), Dex::Uniswap), None);
+        assert_eq!(parse_pool_created(&log(topics, data[..63].to_vec()), Dex::Uniswap), None);
+    }
+
+    #[test]
+    fn reads_algebra_pool() {
+        let (token0, token1, pool) = (Address::repeat_byte(0x0a), Address::repeat_byte(0x0e), Address::repeat_byte(0xc0));
+        // Pool(token0 indexed, token1 indexed, pool)
+        let topics = vec![H256::zero(), H256::from(token0), H256::from(token1)];
+        let data = encode(&[Token::Address(pool)]);
+        assert_eq!(parse_pool_created(&log(topics.clone(), data.clone()), Dex::Camelot), Some((token0, token1, 0, pool)));
+        assert_eq!(parse_pool_created(&log(topics, data[..31].to_vec()), Dex::Camelot), None);
+    }
+}
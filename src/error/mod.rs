@@ -0,0 +1,160 @@
+use ethers::contract::ContractError;
+use ethers::prelude::Address as EthersAddress;
+use ethers::providers::{Middleware, ProviderError};
+use std::fmt;
+use std::sync::{Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+// Where a failure came from. Failure counters are kept per kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ErrorKind {
+    Rpc,
+    Decode,
+    Math,
+    Config,
+    Ipc,
+}
+
+impl ErrorKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::Rpc => "rpc",
+            ErrorKind::Decode => "decode",
+            ErrorKind::Math => "math",
+            ErrorKind::Config => "config",
+            ErrorKind::Ipc => "ipc",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanError {
+    // Node calls that failed, reverted or timed out
+    Rpc(String),
+    // Contract responses and messages that don't decode to what we expect
+    Decode(String),
+    // Overflows, empty pools and prices that aren't finite
+    Math(String),
+    // Addresses and settings that can't work
+    Config(String),
+    // Shared memory and iceoryx2 publishing
+    Ipc(String),
+}
+
+impl ScanError {
+    pub fn rpc(e: impl fmt::Display) -> Self {
+        ScanError::Rpc(e.to_string())
+    }
+
+    pub fn decode(e: impl fmt::Display) -> Self {
+        ScanError::Decode(e.to_string())
+    }
+
+    pub fn math(e: impl fmt::Display) -> Self {
+        ScanError::Math(e.to_string())
+    }
+
+    pub fn config(e: impl fmt::Display) -> Self {
+        ScanError::Config(e.to_string())
+    }
+
+    pub fn ipc(e: impl fmt::Display) -> Self {
+        ScanError::Ipc(e.to_string())
+    }
+
+    // Same kind, with what was being done in front of the message
+    pub fn context(self, what: &str) -> Self {
+        let message = format!("{} failed: {}", what, self.message());
+        match self.kind() {
+            ErrorKind::Rpc => ScanError::Rpc(message),
+            ErrorKind::Decode => ScanError::Decode(message),
+            ErrorKind::Math => ScanError::Math(message),
+            ErrorKind::Config => ScanError::Config(message),
+            ErrorKind::Ipc => ScanError::Ipc(message),
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ScanError::Rpc(_) => ErrorKind::Rpc,
+            ScanError::Decode(_) => ErrorKind::Decode,
+            ScanError::Math(_) => ErrorKind::Math,
+            ScanError::Config(_) => ErrorKind::Config,
+            ScanError::Ipc(_) => ErrorKind::Ipc,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ScanError::Rpc(m) | ScanError::Decode(m) | ScanError::Math(m) | ScanError::Config(m) | ScanError::Ipc(m) => m,
+        }
+    }
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} error: {}", self.kind().name(), self.message())
+    }
+}
+
+impl std::error::Error for ScanError {}
+
+impl From<ProviderError> for ScanError {
+    fn from(e: ProviderError) -> Self {
+        ScanError::rpc(e)
+    }
+}
+
+// Reverts and transport failures are RPC errors; return data that doesn't match the ABI is a decode error
+impl<M: Middleware> From<ContractError<M>> for ScanError {
+    fn from(e: ContractError<M>) -> Self {
+        match e {
+            ContractError::DecodingError(_) | ContractError::AbiError(_) | ContractError::DetokenizationError(_) => {
+                ScanError::decode(e)
+            }
+            _ => ScanError::rpc(e),
+        }
+    }
+}
+
+impl From<serde_json::Error> for ScanError {
+    fn from(e: serde_json::Error) -> Self {
+        ScanError::decode(e)
+    }
+}
+
+pub type ScanResult<T> = Result<T, ScanError>;
+
+pub fn parse_address(address: &str) -> ScanResult<EthersAddress> {
+    address
+        .parse()
+        .map_err(|e| ScanError::Config(format!("'{}' is not an address: {}", address, e)))
+}
+
+// U256 amounts the quoters return, which don't always fit the f64 math downstream
+pub fn to_u128(value: ethers::types::U256, what: &str) -> ScanResult<u128> {
+    if value.bits() > 128 {
+        return Err(ScanError::Math(format!("{} {} overflows u128", what, value)));
+    }
+    Ok(value.as_u128())
+}
+
+// 10^decimals for token amounts, which overflows past 38 decimals
+pub fn decimals_scale(decimals: u8) -> ScanResult<u128> {
+    10u128
+        .checked_pow(decimals as u32)
+        .ok_or_else(|| ScanError::Math(format!("{} decimals overflow u128", decimals)))
+}
+
+// A task that panicked while holding one of these locks leaves the data as it was, which is
+// still better than taking every other monitor down with it
+pub fn read_lock<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+pub fn write_lock<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+pub fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -1,4 +1,186 @@
 pub mod uniswap;
 pub mod pancakeswap;
 pub mod camelot;
-pub mod model;
\ No newline at end of file
+pub mod model;
+
+use ethers::types::{Address, U256};
+use futures::future::try_join_all;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::RwLock;
+
+use crate::error::{ScanError, ScanResult, read_lock, write_lock};
+
+lazy_static! {
+    // tickSpacing by lowercase pool address; it is set when a pool is created and never changes
+    static ref TICK_SPACINGS: RwLock<HashMap<String, i32>> = RwLock::new(HashMap::new());
+}
+
+// How much of a pool a fetch reads. The depth ladder needs the initialized ticks around the
+// price, which take a bitmap word or more and a call per tick; a price and TVL don't.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchDepth {
+    PriceOnly,
+    WithTicks,
+}
+
+// Whether token0 is the base token prices and liquidity are quoted in. A pool holding
+// neither side of its pair's base is misconfigured and can't be priced.
+pub fn token0_is_base(pool_address: &str, token0: Address, token1: Address, base: Address) -> ScanResult<bool> {
+    if token0 == base {
+        Ok(true)
+    } else if token1 == base {
+        Ok(false)
+    } else {
+        Err(ScanError::Config(format!(
+            "pool {} trades {:?}/{:?} and not its base token {:?}",
+            pool_address, token0, token1, base
+        )))
+    }
+}
+
+// Price of the non-base token in base units from the raw token1/token0 ratio
+// (sqrtPriceX96² / 2^192). The decimal shift is signed so pools whose quote token has
+// fewer decimals (e.g. USDC) don't underflow.
+pub fn base_price(raw_price: f64, token0_decimals: u8, token1_decimals: u8, token0_is_base: bool) -> f64 {
+    let shift = 10f64.powi(token0_decimals as i32 - token1_decimals as i32);
+    if token0_is_base {
+        1.0 / raw_price / shift
+    } else {
+        raw_price * shift
+    }
+}
+
+// The pool's tick spacing, read with `fetch` the first time and from memory after that
+pub async fn cached_tick_spacing<F>(pool_address: &str, fetch: F) -> ScanResult<i32>
+where
+    F: Future<Output = ScanResult<i32>>,
+{
+    let key = pool_address.to_lowercase();
+    if let Some(spacing) = read_lock(&TICK_SPACINGS).get(&key) {
+        return Ok(*spacing);
+    }
+    let spacing = fetch.await?;
+    write_lock(&TICK_SPACINGS).insert(key, spacing);
+    Ok(spacing)
+}
+
+// Initialized ticks within `range` ticks of the current tick and their liquidityNet, read
+// from the pool's tick bitmap. Uniswap and PancakeSwap call it tickBitmap and Camelot
+// tickTable; each holds one bit per tick divided by the tick spacing, 256 bits to a word.
+pub async fn initialized_ticks<W, WordCall, N, NetCall>(
+    tick: i32,
+    tick_spacing: i32,
+    range: i32,
+    word_at: W,
+    liquidity_net_at: N,
+) -> ScanResult<Vec<(i32, i128)>>
+where
+    W: Fn(i16) -> WordCall,
+    WordCall: Future<Output = ScanResult<U256>>,
+    N: Fn(i32) -> NetCall,
+    NetCall: Future<Output = ScanResult<i128>>,
+{
+    if tick_spacing <= 0 {
+        return Err(ScanError::Decode(format!("tick spacing {} is not positive", tick_spacing)));
+    }
+    let lowest = (tick - range).div_euclid(tick_spacing);
+    let highest = (tick + range).div_euclid(tick_spacing);
+    let words: Vec<i32> = ((lowest >> 8)..=(highest >> 8)).collect();
+    let bitmaps = try_join_all(words.iter().map(|&word| word_at(word as i16))).await?;
+
+    let mut ticks = Vec::new();
+    for (word, bitmap) in words.into_iter().zip(bitmaps) {
+        for bit in (0..256).filter(|&bit| bitmap.bit(bit)) {
+            let compressed = (word << 8) + bit as i32;
+            if (lowest..=highest).contains(&compressed) {
+                ticks.push(compressed * tick_spacing);
+            }
+        }
+    }
+    let nets = try_join_all(ticks.iter().map(|&tick| liquidity_net_at(tick))).await?;
+    Ok(ticks.into_iter().zip(nets).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base_price, cached_tick_spacing, initialized_ticks, token0_is_base};
+    use crate::error::{ScanError, ScanResult};
+    use ethers::types::{Address, U256};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const WETH: &str = "0x82af49447d8a07e3bd95bd0d56f35241523fbab1";
+    const USDC: &str = "0xaf88d065e77c8cc2239327c5edb3a432268e5831";
+    const WBTC: &str = "0x2f2a2543b76a4166549f7aab2e75bef0aefc5b0f";
+    const ARB: &str = "0x912ce59144191c1204e64559fe8253a0e49e6548";
+
+    fn address(address: &str) -> Address {
+        address.parse().unwrap()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() <= expected.abs() * 1e-12, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn usdc_base_is_token1() {
+        // WETH (18) / USDC (6) at 3000 USDC per WETH: 3000e6 raw USDC per 1e18 raw WETH
+        let token0_is_base = token0_is_base("weth_usdc", address(WETH), address(USDC), address(USDC)).unwrap();
+        assert!(!token0_is_base);
+        assert_close(base_price(3000.0 * 1e-12, 18, 6, token0_is_base), 3000.0);
+    }
+
+    #[test]
+    fn wbtc_base_is_token0_with_8_decimals() {
+        // WBTC (8) / WETH (18) at 20 WETH per WBTC: 20e18 raw WETH per 1e8 raw WBTC
+        let token0_is_base = token0_is_base("wbtc_weth", address(WBTC), address(WETH), address(WBTC)).unwrap();
+        assert!(token0_is_base);
+        assert_close(base_price(20.0 * 1e10, 8, 18, token0_is_base), 0.05);
+    }
+
+    #[tokio::test]
+    async fn initialized_ticks_come_from_the_bitmap_words_in_range() {
+        // Spacing 10: tick -20 is compressed -2, bit 254 of word -1; tick 30 is bit 3 of
+        // word 0 and tick 2570 (compressed 257) is bit 1 of word 1, past the range
+        let word_at = |word: i16| async move {
+            Ok::<_, ScanError>(match word {
+                -1 => U256::one() << 254,
+                0 => U256::one() << 3,
+                1 => U256::one() << 1,
+                _ => U256::zero(),
+            })
+        };
+        let net_at = |tick: i32| async move { Ok::<_, ScanError>(tick as i128 * 1000) };
+        let ticks = initialized_ticks(5, 10, 100, word_at, net_at).await.unwrap();
+        assert_eq!(ticks, vec![(-20, -20_000), (30, 30_000)]);
+
+        assert!(initialized_ticks(5, 0, 100, word_at, net_at).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn tick_spacing_is_read_once_per_pool() {
+        let pool = "0x00000000000000000000000000000000000000A1";
+        let reads = AtomicUsize::new(0);
+        let read = |result: ScanResult<i32>| {
+            let reads = &reads;
+            async move {
+                reads.fetch_add(1, Ordering::Relaxed);
+                result
+            }
+        };
+        // A failed read isn't kept
+        let failed = cached_tick_spacing(pool, read(Err(ScanError::rpc("timeout")))).await;
+        assert_eq!(failed, Err(ScanError::rpc("timeout")));
+        assert_eq!(cached_tick_spacing(pool, read(Ok(60))).await, Ok(60));
+        // Served from memory after that, whatever the address case
+        assert_eq!(cached_tick_spacing(&pool.to_lowercase(), read(Ok(1))).await, Ok(60));
+        assert_eq!(reads.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn pool_without_base_is_a_config_error() {
+        let error = token0_is_base("weth_usdc", address(WETH), address(USDC), address(ARB)).unwrap_err();
+        assert_eq!(error.kind().name(), "config");
+    }
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 use alloy_primitives::U160;
+use std::fs::File;
+use std::io::BufReader;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ExchangeInfo {
@@ -9,16 +11,102 @@ pub struct ExchangeInfo {
     pub liquidity: u64,
 }
 
+// A DEX entry in data.json is either a single pool or a list of pools, e.g. one per fee tier
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ExchangePools {
+    One(ExchangeInfo),
+    Many(Vec<ExchangeInfo>),
+}
+
+impl ExchangePools {
+    pub fn pools(&self) -> &[ExchangeInfo] {
+        match self {
+            ExchangePools::One(info) => std::slice::from_ref(info),
+            ExchangePools::Many(infos) => infos,
+        }
+    }
+
+    pub fn from_pools(mut pools: Vec<ExchangeInfo>) -> Option<Self> {
+        match pools.len() {
+            0 => None,
+            1 => pools.pop().map(ExchangePools::One),
+            _ => Some(ExchangePools::Many(pools)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub enum Dex {
+    Uniswap,
+    PancakeSwap,
+    Camelot,
+}
+
+impl Dex {
+    // Key used for the DEX in data.json
+    pub fn key(&self) -> &'static str {
+        match self {
+            Dex::Uniswap => "UniSwap",
+            Dex::PancakeSwap => "PancakeSwap",
+            Dex::Camelot => "Camelot",
+        }
+    }
+
+    // Name used in logs, snapshots and the live pair set
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dex::Uniswap => "Uniswap",
+            Dex::PancakeSwap => "PancakeSwap",
+            Dex::Camelot => "Camelot",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Dex::Uniswap, Dex::PancakeSwap, Dex::Camelot]
+            .into_iter()
+            .find(|dex| dex.name().eq_ignore_ascii_case(name) || dex.key().eq_ignore_ascii_case(name))
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PairData {
     #[serde(rename = "Pair")]
     pub pair: String,
-    #[serde(rename = "UniSwap")]
-    pub uni_swap: Option<ExchangeInfo>,
-    #[serde(rename = "Camelot")]
-    pub camelot: Option<ExchangeInfo>,
-    #[serde(rename = "PancakeSwap")]
-    pub pancake_swap: Option<ExchangeInfo>,
+    // Symbol from BASE_TOKENS or token address the pair is quoted in, WETH when absent
+    #[serde(rename = "Base", default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    #[serde(rename = "UniSwap", skip_serializing_if = "Option::is_none")]
+    pub uni_swap: Option<ExchangePools>,
+    #[serde(rename = "Camelot", skip_serializing_if = "Option::is_none")]
+    pub camelot: Option<ExchangePools>,
+    #[serde(rename = "PancakeSwap", skip_serializing_if = "Option::is_none")]
+    pub pancake_swap: Option<ExchangePools>,
+}
+
+impl PairData {
+    // Every pool of the pair across all DEXes
+    pub fn pools(&self) -> Vec<(Dex, &ExchangeInfo)> {
+        [
+            (Dex::Uniswap, &self.uni_swap),
+            (Dex::PancakeSwap, &self.pancake_swap),
+            (Dex::Camelot, &self.camelot),
+        ]
+        .into_iter()
+        .flat_map(|(dex, pools)| {
+            pools
+                .iter()
+                .flat_map(|pools| pools.pools())
+                .map(move |info| (dex, info))
+        })
+        .collect()
+    }
+}
+
+// Every pair in a pair file in the data.json format
+pub fn load_pairs(path: &str) -> std::io::Result<Vec<PairData>> {
+    let file = File::open(path).map_err(|e| std::io::Error::new(e.kind(), format!("{}: {}", path, e)))?;
+    serde_json::from_reader(BufReader::new(file)).map_err(|e| std::io::Error::other(format!("{}: {}", path, e)))
 }
 
 #[derive(Debug, Serialize, Deserialize)]
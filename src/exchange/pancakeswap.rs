@@ -1,8 +1,10 @@
-use alloy_primitives::hex;
 use ethers::prelude::{abigen, Address as EthersAddress, U256};
 use ethers::providers::Middleware;
 use std::sync::Arc;
-use crate::config::WETH_ADDRESS;
+
+use crate::error::{ScanError, ScanResult, parse_address};
+use crate::depth::band_tick_range;
+use crate::exchange::{FetchDepth, base_price, cached_tick_spacing, initialized_ticks, token0_is_base};
 
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
@@ -16,16 +18,24 @@ pub struct PancakeSwapPoolInfo {
     pub token0_amount_adjusted: f64,
     pub token1_amount_adjusted: f64,
     pub pair_address: String,
+    // Token the price and liquidity are expressed in
+    pub base: EthersAddress,
     pub liquidity: u128,
     pub price: f64,
     pub tick: i32,
+    pub sqrt_price_x96: U256,
+    pub active_liquidity: u128,
+    // Initialized ticks within the widest depth band and their liquidityNet
+    pub initialized_ticks: Vec<(i32, i128)>,
     pub fee: u32,
 }
 
 pub async fn get_pancakeswap_info<M: Middleware + 'static>(
     pancake_pool_address: String,
+    base: EthersAddress,
+    depth: FetchDepth,
     provider: Arc<M>,
-) -> Result<PancakeSwapPoolInfo, Box<dyn std::error::Error>> {
+) -> ScanResult<PancakeSwapPoolInfo> {
     let client = Arc::new(provider.clone());
     
     // Generate bindings for PancakeSwap V3 pool contract
@@ -43,7 +53,7 @@ pub async fn get_pancakeswap_info<M: Middleware + 'static>(
     );
     
     // Convert address string to ethers Address
-    let pool_address: EthersAddress = pancake_pool_address.parse()?;
+    let pool_address = parse_address(&pancake_pool_address)?;
     
     // Create contract instance
     let pancake_pool = PancakeV3Pool::new(pool_address, client.clone());
@@ -52,22 +62,14 @@ pub async fn get_pancakeswap_info<M: Middleware + 'static>(
     let token0 = match pancake_pool.token_0().call().await {
         Ok(addr) => addr,
         Err(e) => {
-            println!("Failed to call token0(): {}", e);
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other, 
-                format!("token0() call failed: {}", e)
-            )));
+            return Err(ScanError::from(e).context("token0() call"));
         }
     };
     
     let token1 = match pancake_pool.token_1().call().await {
         Ok(addr) => addr,
         Err(e) => {
-            println!("Failed to call token1(): {}", e);
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other, 
-                format!("token1() call failed: {}", e)
-            )));
+            return Err(ScanError::from(e).context("token1() call"));
         }
     };
     
@@ -78,22 +80,14 @@ pub async fn get_pancakeswap_info<M: Middleware + 'static>(
     let token0_decimals_bytes = match token0_contract.decimals().call().await {
         Ok(dec) => dec,
         Err(e) => {
-            println!("Failed to get token0 decimals: {}", e);
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other, 
-                format!("token0 decimals call failed: {}", e)
-            )));
+            return Err(ScanError::from(e).context("token0 decimals call"));
         }
     };
     
     let token1_decimals_bytes = match token1_contract.decimals().call().await {
         Ok(dec) => dec,
         Err(e) => {
-            println!("Failed to get token1 decimals: {}", e);
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other, 
-                format!("token1 decimals call failed: {}", e)
-            )));
+            return Err(ScanError::from(e).context("token1 decimals call"));
         }
     };
     
@@ -102,24 +96,52 @@ pub async fn get_pancakeswap_info<M: Middleware + 'static>(
         Ok(s) => s,
         Err(e) => {
             // println!("Failed to call global_state(): {}", e);
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other, 
-                format!("global_state() call failed: {}", e)
-            )));
+            return Err(ScanError::from(e).context("global_state() call"));
         }
     };
 
     let fee_bytes = match pancake_pool.fee().call().await {
         Ok(f) => f,
         Err(e) => {
-            println!("Failed to call fee(): {}", e);
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other, 
-                format!("fee() call failed: {}", e)
-            )));
+            return Err(ScanError::from(e).context("fee() call"));
         }
         
     };
+
+    // In-range liquidity at the current tick
+    let active_liquidity = match pancake_pool.liquidity().call().await {
+        Ok(l) => l,
+        Err(e) => {
+            return Err(ScanError::from(e).context("liquidity() call"));
+        }
+    };
+
+    // Initialized ticks the depth ladder walks through, when the caller wants the depth
+    let initialized_ticks = match depth {
+        FetchDepth::PriceOnly => Vec::new(),
+        FetchDepth::WithTicks => {
+            let spacing_call = pancake_pool.tick_spacing();
+            let tick_spacing = cached_tick_spacing(&pancake_pool_address, async move {
+                spacing_call.call().await.map_err(|e| ScanError::from(e).context("tickSpacing() call"))
+            })
+            .await?;
+            initialized_ticks(
+                global_state.1,
+                tick_spacing,
+                band_tick_range(),
+                |word| {
+                    let call = pancake_pool.tick_bitmap(word);
+                    async move { call.call().await.map_err(ScanError::from) }
+                },
+                |tick| {
+                    let call = pancake_pool.ticks(tick);
+                    async move { call.call().await.map(|tick| tick.1).map_err(ScanError::from) }
+                },
+            )
+            .await
+            .map_err(|e| e.context("tickBitmap() calls"))?
+        }
+    };
     
     let sqrt_price_x96 = global_state.0;  // price
     let sqrt_price_f64 = sqrt_price_x96.to_string().parse::<f64>().unwrap_or(0.0);
@@ -128,7 +150,6 @@ pub async fn get_pancakeswap_info<M: Middleware + 'static>(
     let mut raw_price = price_x96_squared / two_pow_192;
     let tick = global_state.1;   // tick
 
-    let token0_is_weth = hex::encode(token0.as_bytes()) == WETH_ADDRESS.to_lowercase();
 
     let token0_decimals = token0_decimals_bytes.to_string().parse::<u8>().unwrap_or(18);
     let token1_decimals = token1_decimals_bytes.to_string().parse::<u8>().unwrap_or(18);
@@ -146,20 +167,16 @@ pub async fn get_pancakeswap_info<M: Middleware + 'static>(
     let amount0_adjusted = amount0_f64 / 10.0f64.powi(token0_decimals as i32);
     let amount1_adjusted = amount1_f64 / 10.0f64.powi(token1_decimals as i32);
 
-    let decimal_adjustment = if token0_is_weth {
-        10_u128.pow(token0_decimals as u32 - token1_decimals as u32)
-    } else {
-        10_u128.pow(token1_decimals as u32 - token0_decimals as u32)
-    };
-    let adjustment_factor_f64 = decimal_adjustment.to_string().parse::<f64>().unwrap_or(1.0);
+    // Prices are quoted in the base token
+    let token0_is_base = token0_is_base(&pancake_pool_address, token0, token1, base)?;
 
-    if token0_is_weth {
-        raw_price = 1.0 / raw_price / adjustment_factor_f64;
-    } else {
-        raw_price = raw_price / adjustment_factor_f64;
+    // Price of the other token in base units
+    raw_price = base_price(raw_price, token0_decimals, token1_decimals, token0_is_base);
+    if !raw_price.is_finite() || raw_price <= 0.0 {
+        return Err(ScanError::Math(format!("pool {} has no usable price (sqrtPriceX96 {})", pancake_pool_address, sqrt_price_x96)));
     }
 
-    let liquidity = if token0_is_weth {
+    let liquidity = if token0_is_base {
         amount0_adjusted * 1.0 + amount1_adjusted * raw_price
     } else {
         amount0_adjusted * raw_price + amount1_adjusted * 1.0
@@ -175,19 +192,13 @@ pub async fn get_pancakeswap_info<M: Middleware + 'static>(
         token0_amount_adjusted: amount0_adjusted,
         token1_amount_adjusted: amount1_adjusted,
         pair_address: pancake_pool_address,
+        base,
         liquidity: liquidity as u128,
         price: raw_price,
         tick: tick as i32,
+        sqrt_price_x96,
+        active_liquidity,
+        initialized_ticks,
         fee,
     })
 }
-
-pub async fn pancake_weth_to_usdc<M: Middleware + 'static> (price: f64, liquidity: f64, provider: Arc<M>) -> Result<(f64, f64, f64), Box<dyn std::error::Error>> {
-    let weth_usdc_pair = "0x7fcdc35463e3770c2fb992716cd070b63540b947".to_lowercase();
-
-    let weth_usdc_pair_info = get_pancakeswap_info(weth_usdc_pair.to_string(), provider).await?;
-    let weth_usdc_price = 1.0 / weth_usdc_pair_info.price;
-    let usdc_price = price * weth_usdc_price;
-    let usdc_liquidity = liquidity * weth_usdc_price;
-    Ok((usdc_price, usdc_liquidity, weth_usdc_price))
-}
\ No newline at end of file
@@ -1,17 +1,20 @@
 use ethers::prelude::{abigen, Address as EthersAddress, U256};
-use alloy_primitives::{Address as AlloyAddress, hex};
 use ethers::providers::Middleware;
-use uniswap_v3_sdk::prelude::{FeeAmount, NoTickDataProvider, Pool, get_pool};
 use std::sync::Arc;
-use crate::config::{CHAIN_ID, UNISWAP_V3_FACTORY_ADDRESS, WETH_ADDRESS};
+use crate::error::{ScanError, ScanResult, parse_address};
+use crate::depth::band_tick_range;
+use crate::exchange::{FetchDepth, base_price, cached_tick_spacing, initialized_ticks, token0_is_base};
 
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct UniswapPoolInfo {
-    pub pool: Pool<NoTickDataProvider>,
     pub price: f64,
     pub liquidity: u128,
     pub tick: i32,
+    pub sqrt_price_x96: U256,
+    pub active_liquidity: u128,
+    // Initialized ticks within the widest depth band and their liquidityNet
+    pub initialized_ticks: Vec<(i32, i128)>,
     pub token0: EthersAddress,
     pub token1: EthersAddress,
     pub token0_decimals: u8,
@@ -22,13 +25,17 @@ pub struct UniswapPoolInfo {
     pub token1_amount_adjusted: f64,
     #[allow(dead_code)]
     pub pair_address: String,
+    // Token the price and liquidity are expressed in
+    pub base: EthersAddress,
     pub fee: u32,
 }
 
 pub async fn get_uniswap_info<M: Middleware + 'static>(
     uni_v3_pool_address: String,
+    base: EthersAddress,
+    depth: FetchDepth,
     provider: Arc<M>,
-) -> Result<UniswapPoolInfo, Box<dyn std::error::Error>> {
+) -> ScanResult<UniswapPoolInfo> {
     let client = Arc::new(provider.clone());
     abigen!(
         UniswapV3Pool,
@@ -44,7 +51,7 @@ pub async fn get_uniswap_info<M: Middleware + 'static>(
     );
     
     let v3_pool = UniswapV3Pool::new(
-        uni_v3_pool_address.parse::<EthersAddress>()?,
+        parse_address(&uni_v3_pool_address)?,
         client.clone()
     );
     
@@ -62,7 +69,7 @@ pub async fn get_uniswap_info<M: Middleware + 'static>(
     let token1_decimals = token1_decimals_bytes.to_string().parse::<u8>().unwrap_or(18);
 
     // Calculation Liquidity using ERC20 Contract
-    let pair_address = uni_v3_pool_address.parse()?;
+    let pair_address = parse_address(&uni_v3_pool_address)?;
 
     let amount0 = token0_contract.balance_of(pair_address).call().await?;
     let amount1 = token1_contract.balance_of(pair_address).call().await?;
@@ -74,66 +81,72 @@ pub async fn get_uniswap_info<M: Middleware + 'static>(
     let amount1_adjusted = amount1_f64 / 10.0f64.powi(token1_decimals as i32);
 
     let fee_amount_bytes = v3_pool.fee().call().await?;
-    let fee_amount = match fee_amount_bytes {
-        100 => FeeAmount::LOWEST,
-        500 => FeeAmount::LOW,
-        3000 => FeeAmount::MEDIUM,
-        10000 => FeeAmount::HIGH,
-        _ => panic!("invalid fee amount")
-    };
-
-    // get pool
-    let factory_address_str = UNISWAP_V3_FACTORY_ADDRESS;
-
-    let pool = get_pool(
-        CHAIN_ID,
-        factory_address_str.parse::<AlloyAddress>()?,
-        token0.to_fixed_bytes().into(),
-        token1.to_fixed_bytes().into(),
-        fee_amount,
-        Arc::new(provider),
-        None
-    ).await?;
-
-    let tick = pool.tick_current;
-
-    // Check if token0 is WETH
-    let token0_is_weth = hex::encode(token0.as_bytes()) == WETH_ADDRESS.to_lowercase();
+    // Fee tiers the factory doesn't enable can't be priced; the pool gets quarantined instead
+    if ![100, 500, 3000, 10000].contains(&fee_amount_bytes) {
+        return Err(ScanError::Decode(format!("pool {} has unsupported fee tier {}", uni_v3_pool_address, fee_amount_bytes)));
+    }
 
-    let decimal_adjustment = if token0_is_weth {
-        10_u128.pow(token0_decimals as u32 - token1_decimals as u32)
-    } else {
-        10_u128.pow(token1_decimals as u32 - token0_decimals as u32)
+    // Price and tick, read from the pool like the other fetchers so they go through the
+    // same provider (and replay) as every other call
+    let slot0 = v3_pool.slot_0().call().await.map_err(|e| ScanError::from(e).context("slot0() call"))?;
+    let active_liquidity = v3_pool.liquidity().call().await.map_err(|e| ScanError::from(e).context("liquidity() call"))?;
+    let sqrt_price_x96 = slot0.0;
+    let tick = slot0.1;
+
+    // Initialized ticks the depth ladder walks through, when the caller wants the depth
+    let initialized_ticks = match depth {
+        FetchDepth::PriceOnly => Vec::new(),
+        FetchDepth::WithTicks => {
+            let spacing_call = v3_pool.tick_spacing();
+            let tick_spacing = cached_tick_spacing(&uni_v3_pool_address, async move {
+                spacing_call.call().await.map_err(|e| ScanError::from(e).context("tickSpacing() call"))
+            })
+            .await?;
+            initialized_ticks(
+                tick,
+                tick_spacing,
+                band_tick_range(),
+                |word| {
+                    let call = v3_pool.tick_bitmap(word);
+                    async move { call.call().await.map_err(ScanError::from) }
+                },
+                |tick| {
+                    let call = v3_pool.ticks(tick);
+                    async move { call.call().await.map(|tick| tick.1).map_err(ScanError::from) }
+                },
+            )
+            .await
+            .map_err(|e| e.context("tickBitmap() calls"))?
+        }
     };
 
-    let adjustment_factor_f64 = decimal_adjustment.to_string().parse::<f64>().unwrap_or(1.0);
-
-    let sqrt_price_x96 = pool.sqrt_ratio_x96;
-    let price_x192 = sqrt_price_x96 * sqrt_price_x96;
 
-    let price_x192_f64 = price_x192.to_string().parse::<f64>().unwrap_or(0.0);
+    // Prices are quoted in the base token
+    let token0_is_base = token0_is_base(&uni_v3_pool_address, token0, token1, base)?;
 
+    let sqrt_price_f64 = sqrt_price_x96.to_string().parse::<f64>().unwrap_or(0.0);
     let two_pow_192_f64 = 2.0f64.powi(192);
-    let mut price_ratio = price_x192_f64 / two_pow_192_f64;
+    let mut price_ratio = sqrt_price_f64.powi(2) / two_pow_192_f64;
 
-    // If token0 is not WETH, invert the price
-    if token0_is_weth {
-        price_ratio = 1.0 / price_ratio / adjustment_factor_f64;
-    } else {
-        price_ratio = price_ratio / adjustment_factor_f64;
+    // Price of the other token in base units
+    price_ratio = base_price(price_ratio, token0_decimals, token1_decimals, token0_is_base);
+    if !price_ratio.is_finite() || price_ratio <= 0.0 {
+        return Err(ScanError::Math(format!("pool {} has no usable price (sqrtPriceX96 {})", uni_v3_pool_address, sqrt_price_x96)));
     }
 
-    let liquidity = if token0_is_weth {
+    let liquidity = if token0_is_base {
         amount0_adjusted * 1.0 + amount1_adjusted * price_ratio
     } else {
         amount0_adjusted * price_ratio + amount1_adjusted * 1.0
     };
 
     Ok(UniswapPoolInfo {
-        pool,
         price: price_ratio,
         liquidity: liquidity as u128,
         tick,
+        sqrt_price_x96,
+        active_liquidity,
+        initialized_ticks,
         token0,
         token1,
         token0_decimals,
@@ -143,16 +156,7 @@ pub async fn get_uniswap_info<M: Middleware + 'static>(
         token0_amount_adjusted: amount0_adjusted,
         token1_amount_adjusted: amount1_adjusted,
         pair_address: uni_v3_pool_address,
+        base,
         fee: fee_amount_bytes.to_string().parse::<u32>().unwrap_or(0),
     })
 }
-
-pub async fn uniswap_weth_to_usdc<M: Middleware + 'static> (price: f64, liquidity: f64, provider: Arc<M>) -> Result<(f64, f64, f64), Box<dyn std::error::Error>> {
-    let weth_usdc_pair = "0xC6962004f452bE9203591991D15f6b388e09E8D0".to_lowercase();
-
-    let weth_usdc_pair_info = get_uniswap_info(weth_usdc_pair.to_string(), provider).await?;
-    let weth_usdc_price = 1.0 / weth_usdc_pair_info.price;
-    let usdc_price = price * weth_usdc_price;
-    let usdc_liquidity = liquidity * weth_usdc_price;
-    Ok((usdc_price, usdc_liquidity, weth_usdc_price))
-}
\ No newline at end of file
@@ -0,0 +1,560 @@
+pub mod schema;
+
+use arrow::array::{Array, UInt64Array};
+use arrow::datatypes::SchemaRef;
+use arrow::error::ArrowError;
+use arrow::ipc::reader::FileReader;
+use arrow::ipc::writer::FileWriter;
+use arrow::record_batch::RecordBatch;
+use chrono::DateTime;
+use parquet::arrow::ArrowWriter;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde::Serialize;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tracing::{error, info};
+
+use crate::config::{EXPORT_BATCH_ROWS, EXPORT_FLUSH_INTERVAL, EXPORT_QUEUE_LEN};
+use crate::error::lock;
+use crate::history::PairBlockRecord;
+use crate::metrics::EXPORT_ROWS;
+use crate::settings::settings;
+use schema::{PoolRow, SCHEMA_VERSION_KEY, pool_batch, pool_schema, spread_batch, spread_schema};
+
+type SendError = Box<dyn std::error::Error + Send + Sync>;
+
+const HOUR_MS: u64 = 3_600_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Parquet,
+    Arrow,
+}
+
+impl ExportFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "parquet" => Some(ExportFormat::Parquet),
+            "arrow" => Some(ExportFormat::Arrow),
+            _ => None,
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Parquet => "parquet",
+            ExportFormat::Arrow => "arrow",
+        }
+    }
+}
+
+enum ExportRow {
+    Pool(PoolRow),
+    Spread(PairBlockRecord),
+    // Closes both streams' segments and stops the writer thread
+    Close,
+}
+
+enum SegmentWriter {
+    Parquet(ArrowWriter<File>),
+    Arrow(FileWriter<File>),
+}
+
+impl SegmentWriter {
+    fn create(format: ExportFormat, path: &Path, schema: &SchemaRef) -> Result<Self, SendError> {
+        let file = File::create(path)?;
+        Ok(match format {
+            ExportFormat::Parquet => {
+                let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+                SegmentWriter::Parquet(ArrowWriter::try_new(file, schema.clone(), Some(properties))?)
+            }
+            ExportFormat::Arrow => SegmentWriter::Arrow(FileWriter::try_new(file, schema)?),
+        })
+    }
+
+    fn write(&mut self, batch: &RecordBatch) -> Result<(), SendError> {
+        match self {
+            SegmentWriter::Parquet(writer) => writer.write(batch)?,
+            SegmentWriter::Arrow(writer) => writer.write(batch)?,
+        }
+        Ok(())
+    }
+
+    // Writes the footer; the file is unreadable until then
+    fn close(self) -> Result<(), SendError> {
+        match self {
+            SegmentWriter::Parquet(writer) => {
+                writer.close()?;
+            }
+            SegmentWriter::Arrow(mut writer) => writer.finish()?,
+        }
+        Ok(())
+    }
+}
+
+// The open file of one stream for the current hour, written under a .partial name
+struct Segment {
+    partial: PathBuf,
+    path: PathBuf,
+    writer: SegmentWriter,
+}
+
+// One kind of row, buffered and written to one file per hour
+struct Stream<R> {
+    name: &'static str,
+    schema: SchemaRef,
+    to_batch: fn(&[R]) -> Result<RecordBatch, ArrowError>,
+    rows: Vec<R>,
+    // Hours since the epoch of the buffered rows and the open segment
+    hour: Option<u64>,
+    segment: Option<Segment>,
+}
+
+impl<R> Stream<R> {
+    fn new(name: &'static str, schema: SchemaRef, to_batch: fn(&[R]) -> Result<RecordBatch, ArrowError>) -> Self {
+        Self { name, schema, to_batch, rows: Vec::new(), hour: None, segment: None }
+    }
+
+    fn push(&mut self, sink: &Sink, row: R, timestamp_ms: u64) {
+        let hour = timestamp_ms / HOUR_MS;
+        if self.hour != Some(hour) {
+            self.close(sink);
+            self.hour = Some(hour);
+        }
+        self.rows.push(row);
+        if self.rows.len() >= EXPORT_BATCH_ROWS {
+            self.flush(sink);
+        }
+    }
+
+    fn flush(&mut self, sink: &Sink) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let count = self.rows.len() as u64;
+        match self.write_rows(sink) {
+            Ok(()) => EXPORT_ROWS.with_label_values(&[self.name, "written"]).inc_by(count),
+            Err(e) => {
+                EXPORT_ROWS.with_label_values(&[self.name, "failed"]).inc_by(count);
+                error!("❌ Failed to export {} {} rows: {}", count, self.name, e);
+            }
+        }
+        self.rows.clear();
+    }
+
+    fn write_rows(&mut self, sink: &Sink) -> Result<(), SendError> {
+        let batch = (self.to_batch)(&self.rows)?;
+        if self.segment.is_none() {
+            let hour = self.hour.unwrap_or_default();
+            let (partial, path) = sink.segment_paths(self.name, hour);
+            let writer = SegmentWriter::create(sink.format, &partial, &self.schema)?;
+            self.segment = Some(Segment { partial, path, writer });
+        }
+        match &mut self.segment {
+            Some(segment) => segment.writer.write(&batch),
+            None => Ok(()),
+        }
+    }
+
+    // Writes what is buffered and publishes the hour's file under its final name
+    fn close(&mut self, sink: &Sink) {
+        self.flush(sink);
+        let Some(segment) = self.segment.take() else {
+            return;
+        };
+        let closed = segment.writer.close().and_then(|()| Ok(std::fs::rename(&segment.partial, &segment.path)?));
+        match closed {
+            Ok(()) => info!("🧊 Exported {}", segment.path.display()),
+            Err(e) => error!("❌ Failed to close export {}: {}", segment.partial.display(), e),
+        }
+    }
+
+    // Closes the segment once the wall clock has moved past its hour, so a stream that
+    // went quiet still publishes its file
+    fn roll(&mut self, sink: &Sink, now_ms: u64) {
+        if self.hour.is_some_and(|hour| hour < now_ms / HOUR_MS) {
+            self.close(sink);
+            self.hour = None;
+        }
+    }
+}
+
+struct Sink {
+    directory: PathBuf,
+    format: ExportFormat,
+}
+
+impl Sink {
+    // {directory}/{stream}-YYYY-MM-DDTHH.{ext}, in UTC. A restart within the hour gets a
+    // numbered file next to the earlier one instead of overwriting it.
+    fn segment_paths(&self, stream: &str, hour: u64) -> (PathBuf, PathBuf) {
+        let stamp = DateTime::from_timestamp_millis((hour * HOUR_MS) as i64)
+            .map(|time| time.format("%Y-%m-%dT%H").to_string())
+            .unwrap_or_else(|| hour.to_string());
+        let extension = self.format.extension();
+        let mut sequence = 0;
+        loop {
+            let name = if sequence == 0 {
+                format!("{}-{}.{}", stream, stamp, extension)
+            } else {
+                format!("{}-{}-{}.{}", stream, stamp, sequence, extension)
+            };
+            let path = self.directory.join(&name);
+            let partial = self.directory.join(format!("{}.partial", name));
+            if !path.exists() && !partial.exists() {
+                return (partial, path);
+            }
+            sequence += 1;
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+// Writes the pool states the monitors fetch and the spreads they derive from them to
+// columnar files for research, one pools and one spreads file per hour (see schema.rs).
+// Rows are queued without blocking and written by a dedicated thread in batches of up to
+// EXPORT_BATCH_ROWS, at least every EXPORT_FLUSH_INTERVAL. Files are written under a
+// .partial name and renamed when their hour is over or on close(), so anything without
+// the suffix is complete; a scanner killed without closing leaves the current hour's
+// .partial files unreadable. A full queue drops rows rather than slowing the monitors down.
+#[derive(Clone)]
+pub struct SnapshotExporter {
+    tx: mpsc::SyncSender<ExportRow>,
+    writer: Arc<Mutex<Option<thread::JoinHandle<()>>>>,
+}
+
+impl SnapshotExporter {
+    // Enabled by [export] directory or EXPORT_DIR
+    pub fn from_settings() -> Result<Option<Self>, SendError> {
+        let export = settings().export.clone();
+        if export.directory.is_empty() {
+            return Ok(None);
+        }
+        let format = ExportFormat::from_name(&export.format).ok_or_else(|| format!("unknown export format '{}'", export.format))?;
+        Self::new(&export.directory, format).map(Some)
+    }
+
+    pub fn new(directory: &str, format: ExportFormat) -> Result<Self, SendError> {
+        std::fs::create_dir_all(directory)?;
+        let sink = Sink { directory: PathBuf::from(directory), format };
+        let (tx, rx) = mpsc::sync_channel::<ExportRow>(EXPORT_QUEUE_LEN);
+
+        info!("🧊 Exporting pool and spread snapshots as {} to {}", format.extension(), directory);
+
+        let writer = thread::spawn(move || {
+            let mut pools = Stream::new("pools", pool_schema(), pool_batch);
+            let mut spreads = Stream::new("spreads", spread_schema(), spread_batch);
+            let mut deadline = Instant::now() + EXPORT_FLUSH_INTERVAL;
+            loop {
+                match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                    Ok(ExportRow::Pool(row)) => {
+                        let timestamp_ms = row.timestamp_ms;
+                        pools.push(&sink, row, timestamp_ms);
+                    }
+                    Ok(ExportRow::Spread(record)) => {
+                        let timestamp_ms = record.timestamp_ms;
+                        spreads.push(&sink, record, timestamp_ms);
+                    }
+                    Err(RecvTimeoutError::Timeout) => {
+                        let now = now_ms();
+                        pools.flush(&sink);
+                        spreads.flush(&sink);
+                        pools.roll(&sink, now);
+                        spreads.roll(&sink, now);
+                        deadline = Instant::now() + EXPORT_FLUSH_INTERVAL;
+                    }
+                    // Closed, or every exporter handle is gone
+                    Ok(ExportRow::Close) | Err(RecvTimeoutError::Disconnected) => {
+                        pools.close(&sink);
+                        spreads.close(&sink);
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Self { tx, writer: Arc::new(Mutex::new(Some(writer))) })
+    }
+
+    // Publishes the open segments under their final names and waits for the writer
+    // thread to stop. Rows from handles still in use afterwards are dropped.
+    pub fn close(&self) {
+        let Some(writer) = lock(&self.writer).take() else {
+            return;
+        };
+        // Blocks while the queue is full, which the writer is draining
+        if self.tx.send(ExportRow::Close).is_ok() && writer.join().is_err() {
+            error!("❌ Export writer thread panicked");
+        }
+    }
+
+    pub fn pool(&self, row: PoolRow) {
+        self.send(ExportRow::Pool(row), "pools");
+    }
+
+    pub fn spread(&self, record: PairBlockRecord) {
+        self.send(ExportRow::Spread(record), "spreads");
+    }
+
+    fn send(&self, row: ExportRow, stream: &str) {
+        match self.tx.try_send(row) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => EXPORT_ROWS.with_label_values(&[stream, "dropped"]).inc(),
+            // The writer thread only stops if the process is shutting down
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportColumn {
+    pub name: String,
+    pub data_type: String,
+    pub nullable: bool,
+}
+
+// What an exported file holds, read back through the same readers research tools use
+#[derive(Debug, Serialize)]
+pub struct ExportSummary {
+    pub path: String,
+    pub format: &'static str,
+    pub schema_version: Option<String>,
+    pub columns: Vec<ExportColumn>,
+    pub batches: usize,
+    pub rows: usize,
+    pub first_block: Option<u64>,
+    pub last_block: Option<u64>,
+}
+
+// Reads every batch of a .parquet or .arrow export file
+pub fn read_export(path: &str) -> Result<(SchemaRef, Vec<RecordBatch>), SendError> {
+    let file = File::open(path)?;
+    let name = path.strip_suffix(".partial").unwrap_or(path);
+    if name.ends_with(".parquet") {
+        let builder = ParquetRecordBatchReaderBuilder::try_new(file)?;
+        // The built reader's schema leaves out the metadata
+        let schema = builder.schema().clone();
+        let batches = builder.build()?.collect::<Result<Vec<_>, _>>()?;
+        Ok((schema, batches))
+    } else if name.ends_with(".arrow") {
+        let reader = FileReader::try_new(file, None)?;
+        let schema = reader.schema();
+        let batches = reader.collect::<Result<Vec<_>, _>>()?;
+        Ok((schema, batches))
+    } else {
+        Err(format!("{}: expected a .parquet or .arrow file", path).into())
+    }
+}
+
+pub fn summarize_export(path: &str) -> Result<ExportSummary, SendError> {
+    let (schema, batches) = read_export(path)?;
+    let mut first_block: Option<u64> = None;
+    let mut last_block: Option<u64> = None;
+    for batch in &batches {
+        let Some(blocks) = batch
+            .column_by_name("block_number")
+            .and_then(|column| column.as_any().downcast_ref::<UInt64Array>())
+        else {
+            continue;
+        };
+        for block in blocks.iter().flatten() {
+            first_block = Some(first_block.map_or(block, |first| first.min(block)));
+            last_block = Some(last_block.map_or(block, |last| last.max(block)));
+        }
+    }
+    Ok(ExportSummary {
+        path: path.to_string(),
+        format: if path.contains(".parquet") { "parquet" } else { "arrow" },
+        schema_version: schema.metadata().get(SCHEMA_VERSION_KEY).cloned(),
+        columns: schema
+            .fields()
+            .iter()
+            .map(|field| ExportColumn {
+                name: field.name().clone(),
+                data_type: field.data_type().to_string(),
+                nullable: field.is_nullable(),
+            })
+            .collect(),
+        batches: batches.len(),
+        rows: batches.iter().map(|batch| batch.num_rows()).sum(),
+        first_block,
+        last_block,
+    })
+}
+
+pub fn print_export_summary(summary: &ExportSummary) {
+    println!("🧊 {} ({}, schema version {})", summary.path, summary.format, summary.schema_version.as_deref().unwrap_or("unknown"));
+    match (summary.first_block, summary.last_block) {
+        (Some(first), Some(last)) => println!("   {} rows in {} batches, blocks #{} to #{}", summary.rows, summary.batches, first, last),
+        _ => println!("   {} rows in {} batches", summary.rows, summary.batches),
+    }
+    for column in &summary.columns {
+        println!("   {:<18} {}{}", column.name, column.data_type, if column.nullable { ", nullable" } else { "" });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::schema::{PoolRow, SCHEMA_VERSION, SCHEMA_VERSION_KEY, pool_schema, spread_schema};
+    use super::{ExportFormat, HOUR_MS, Sink, SnapshotExporter, Stream, read_export};
+    use crate::history::{Outcome, PairBlockRecord, SimulationRecord};
+    use crate::pool_state::PoolSnapshot;
+    use arrow::array::{Array, Decimal128Array, Float64Array, StringArray, UInt8Array, UInt32Array, UInt64Array};
+    use arrow::record_batch::RecordBatch;
+    use std::path::{Path, PathBuf};
+
+    // 2024-01-01T05:00Z plus a few seconds
+    const TIMESTAMP_MS: u64 = 473_357 * HOUR_MS + 5_000;
+
+    fn temp_dir(label: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("scanner_export_{}_{}", label, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn pool_row(block_number: u64, active_liquidity: u128) -> PoolRow {
+        PoolRow {
+            block_number,
+            timestamp_ms: TIMESTAMP_MS,
+            pair_name: "ARB/WETH".to_string(),
+            pool: PoolSnapshot {
+                dex: "Camelot".to_string(),
+                pool_address: "0xe51635ae8136abac44906a8f230c2d235e9c195f".to_string(),
+                token0: "0x82af49447d8a07e3bd95bd0d56f35241523fbab1".to_string(),
+                token1: "0x912ce59144191c1204e64559fe8253a0e49e6548".to_string(),
+                token0_decimals: 18,
+                token1_decimals: 18,
+                sqrt_price_x96: "5010828967500958623728276031185".to_string(),
+                active_liquidity,
+                tick: 82944,
+                initialized_ticks: Vec::new(),
+                fee: 500,
+                fee_otz: Some(600),
+                price: 0.00025,
+                liquidity: 1_000_000,
+                base_token: "0x82af49447d8a07e3bd95bd0d56f35241523fbab1".to_string(),
+                weth_usdc: 3000.0,
+                base_usd: 3000.0,
+            },
+        }
+    }
+
+    fn spread_row(block_number: u64) -> PairBlockRecord {
+        PairBlockRecord {
+            block_number,
+            timestamp_ms: TIMESTAMP_MS,
+            monitor: "camelot_pancakeswap".to_string(),
+            pair_name: "ARB/WETH".to_string(),
+            dex_a: "Camelot",
+            pool_a: "0xe51635ae8136abac44906a8f230c2d235e9c195f".to_string(),
+            price_a: 0.75,
+            dex_b: "PancakeSwap",
+            pool_b: "0x11d53ec50bc8f54b9357fbfe2a7de034fc00f8b3".to_string(),
+            price_b: 0.765,
+            spread: 0.02,
+            min_liquidity_usd: 250_000.0,
+            depth_usd: 120_000.0,
+            tier: Some(2),
+            simulations: vec![
+                SimulationRecord { amount_usd: 1000.0, amount_in: 0.33, amount_out: 0.335, profit_usd: 4.5 },
+                SimulationRecord { amount_usd: 5000.0, amount_in: 1.66, amount_out: 1.68, profit_usd: 6.25 },
+            ],
+            outcome: Outcome::Selected,
+            suspect_reason: None,
+        }
+    }
+
+    // The only file in the directory, which must have been published under its final name
+    fn exported_file(dir: &Path, stream: &str) -> String {
+        let files: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with(stream))
+            .collect();
+        assert_eq!(files.len(), 1, "{:?}", files);
+        assert!(!files[0].ends_with(".partial"), "{} was not closed", files[0]);
+        dir.join(&files[0]).to_string_lossy().into_owned()
+    }
+
+    fn column<'a, T: 'static>(batch: &'a RecordBatch, name: &str) -> &'a T {
+        batch.column_by_name(name).unwrap().as_any().downcast_ref::<T>().unwrap()
+    }
+
+    #[test]
+    fn segments_round_trip_through_read_export() {
+        for (format, extension) in [(ExportFormat::Parquet, "parquet"), (ExportFormat::Arrow, "arrow")] {
+            let dir = temp_dir(extension);
+            let sink = Sink { directory: dir.clone(), format };
+            let mut pools = Stream::new("pools", pool_schema(), super::pool_batch);
+            let mut spreads = Stream::new("spreads", spread_schema(), super::spread_batch);
+
+            pools.push(&sink, pool_row(1001, 42_000), TIMESTAMP_MS);
+            pools.push(&sink, pool_row(1002, u128::MAX), TIMESTAMP_MS);
+            spreads.push(&sink, spread_row(1001), TIMESTAMP_MS);
+            pools.close(&sink);
+            spreads.close(&sink);
+
+            let path = exported_file(&dir, "pools");
+            assert!(path.ends_with(&format!("pools-2024-01-01T05.{}", extension)), "{}", path);
+            let (schema, batches) = read_export(&path).unwrap();
+            assert_eq!(schema.fields(), pool_schema().fields());
+            assert_eq!(schema.metadata().get(SCHEMA_VERSION_KEY).map(String::as_str), Some(SCHEMA_VERSION));
+            assert_eq!(batches.len(), 1);
+            let batch = &batches[0];
+            assert_eq!(batch.num_rows(), 2);
+            assert_eq!(column::<UInt64Array>(batch, "block_number").values(), &[1001, 1002]);
+            assert_eq!(column::<StringArray>(batch, "dex").value(0), "Camelot");
+            assert_eq!(column::<UInt8Array>(batch, "token0_decimals").value(1), 18);
+            assert_eq!(column::<StringArray>(batch, "sqrt_price_x96").value(0), "5010828967500958623728276031185");
+            // Liquidity past i128::MAX doesn't fit the decimal column and is stored as null
+            let active_liquidity = column::<Decimal128Array>(batch, "active_liquidity");
+            assert_eq!(active_liquidity.value(0), 42_000);
+            assert!(active_liquidity.is_null(1));
+            assert_eq!(column::<UInt32Array>(batch, "fee_otz").value(0), 600);
+            assert_eq!(column::<Float64Array>(batch, "weth_usd").value(0), 3000.0);
+
+            let path = exported_file(&dir, "spreads");
+            let (schema, batches) = read_export(&path).unwrap();
+            assert_eq!(schema.fields(), spread_schema().fields());
+            assert_eq!(schema.metadata().get(SCHEMA_VERSION_KEY).map(String::as_str), Some(SCHEMA_VERSION));
+            let batch = &batches[0];
+            assert_eq!(batch.num_rows(), 1);
+            assert_eq!(column::<StringArray>(batch, "monitor").value(0), "camelot_pancakeswap");
+            assert_eq!(column::<UInt8Array>(batch, "tier").value(0), 2);
+            assert_eq!(column::<UInt32Array>(batch, "simulations").value(0), 2);
+            // The best of the simulated sizes
+            assert_eq!(column::<Float64Array>(batch, "best_amount_usd").value(0), 5000.0);
+            assert_eq!(column::<Float64Array>(batch, "best_profit_usd").value(0), 6.25);
+            assert_eq!(column::<StringArray>(batch, "outcome").value(0), "selected");
+
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+    }
+
+    #[test]
+    fn close_publishes_the_open_segments() {
+        let dir = temp_dir("close");
+        let exporter = SnapshotExporter::new(dir.to_str().unwrap(), ExportFormat::Arrow).unwrap();
+        // A handle still held elsewhere doesn't keep the files open
+        let _held = exporter.clone();
+        exporter.pool(pool_row(1001, 42_000));
+        exporter.spread(spread_row(1001));
+        exporter.close();
+
+        let (_, batches) = read_export(&exported_file(&dir, "pools")).unwrap();
+        assert_eq!(batches.iter().map(|batch| batch.num_rows()).sum::<usize>(), 1);
+        let (_, batches) = read_export(&exported_file(&dir, "spreads")).unwrap();
+        assert_eq!(batches.iter().map(|batch| batch.num_rows()).sum::<usize>(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
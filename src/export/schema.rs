@@ -0,0 +1,257 @@
+use arrow::array::{
+    ArrayRef, Decimal128Builder, Float64Builder, Int32Builder, StringBuilder, UInt8Builder, UInt32Builder,
+    UInt64Builder,
+};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::error::ArrowError;
+use arrow::record_batch::RecordBatch;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::history::PairBlockRecord;
+use crate::pool_state::PoolSnapshot;
+
+// Bumped whenever a column is added, removed or changes meaning. Columns are only ever
+// appended, so readers of an older version keep working on the columns they know.
+pub const SCHEMA_VERSION: &str = "1";
+pub const SCHEMA_VERSION_KEY: &str = "scanner.schema_version";
+
+// One row per pool per block, for every state accepted into the pool cache: the pools of
+// every monitored pair plus the route-only pools.
+//
+//   block_number       u64          block the state was fetched on
+//   timestamp_ms       u64          local wall clock when it was cached
+//   pair               utf8         pair name from the pair or route pool file
+//   dex                utf8         Uniswap, PancakeSwap or Camelot
+//   pool               utf8         lowercase pool address
+//   token0, token1     utf8         0x-prefixed lowercase token addresses
+//   token0_decimals    u8
+//   token1_decimals    u8
+//   sqrt_price_x96     utf8         decimal string, it doesn't fit 128 bits
+//   tick               i32
+//   active_liquidity   decimal(38)  in-range liquidity; null past i128::MAX
+//   liquidity          decimal(38)  pool liquidity as reported by the DEX adapter
+//   fee                u32          token0 -> token1 fee in hundredths of a bip
+//   fee_otz            u32, null    token1 -> token0 fee where it differs (Camelot)
+//   price              f64          token1 per token0, decimal adjusted
+//   base_token         utf8         token the pool is quoted in
+//   weth_usd           f64          block's WETH/USD reference price
+//   base_usd           f64          USD price of base_token
+pub fn pool_schema() -> SchemaRef {
+    let fields = vec![
+        Field::new("block_number", DataType::UInt64, false),
+        Field::new("timestamp_ms", DataType::UInt64, false),
+        Field::new("pair", DataType::Utf8, false),
+        Field::new("dex", DataType::Utf8, false),
+        Field::new("pool", DataType::Utf8, false),
+        Field::new("token0", DataType::Utf8, false),
+        Field::new("token1", DataType::Utf8, false),
+        Field::new("token0_decimals", DataType::UInt8, false),
+        Field::new("token1_decimals", DataType::UInt8, false),
+        Field::new("sqrt_price_x96", DataType::Utf8, false),
+        Field::new("tick", DataType::Int32, false),
+        Field::new("active_liquidity", DataType::Decimal128(38, 0), true),
+        Field::new("liquidity", DataType::Decimal128(38, 0), true),
+        Field::new("fee", DataType::UInt32, false),
+        Field::new("fee_otz", DataType::UInt32, true),
+        Field::new("price", DataType::Float64, false),
+        Field::new("base_token", DataType::Utf8, false),
+        Field::new("weth_usd", DataType::Float64, false),
+        Field::new("base_usd", DataType::Float64, false),
+    ];
+    Arc::new(with_version(fields))
+}
+
+// One row per pool pair per block that was fetched and priced, however its evaluation
+// ended. Pairs that failed to fetch or quote have no row.
+//
+//   block_number       u64
+//   timestamp_ms       u64          local wall clock at the start of the block
+//   monitor            utf8         camelot_uniswap, camelot_pancakeswap, pancakeswap_uniswap or same_dex
+//   pair               utf8
+//   dex_a, pool_a      utf8         first pool of the pairing
+//   price_a            f64          its USD price
+//   dex_b, pool_b      utf8
+//   price_b            f64
+//   spread             f64          price difference as a fraction, 0.01 is 1%
+//   min_liquidity_usd  f64          USD liquidity of the thinner pool
+//   depth_usd          f64          depth that picked the tier
+//   tier               u8, null     null when too shallow for any tier
+//   simulations        u32          trade sizes simulated, 0 below the tier's spread
+//   best_amount_usd    f64, null    most profitable size
+//   best_profit_usd    f64, null    its profit, negative when nothing paid
+//   outcome            utf8         no_tier, tier_disabled, below_spread, unprofitable, outbid,
+//                                   suspect or selected
+pub fn spread_schema() -> SchemaRef {
+    let fields = vec![
+        Field::new("block_number", DataType::UInt64, false),
+        Field::new("timestamp_ms", DataType::UInt64, false),
+        Field::new("monitor", DataType::Utf8, false),
+        Field::new("pair", DataType::Utf8, false),
+        Field::new("dex_a", DataType::Utf8, false),
+        Field::new("pool_a", DataType::Utf8, false),
+        Field::new("price_a", DataType::Float64, false),
+        Field::new("dex_b", DataType::Utf8, false),
+        Field::new("pool_b", DataType::Utf8, false),
+        Field::new("price_b", DataType::Float64, false),
+        Field::new("spread", DataType::Float64, false),
+        Field::new("min_liquidity_usd", DataType::Float64, false),
+        Field::new("depth_usd", DataType::Float64, false),
+        Field::new("tier", DataType::UInt8, true),
+        Field::new("simulations", DataType::UInt32, false),
+        Field::new("best_amount_usd", DataType::Float64, true),
+        Field::new("best_profit_usd", DataType::Float64, true),
+        Field::new("outcome", DataType::Utf8, false),
+    ];
+    Arc::new(with_version(fields))
+}
+
+fn with_version(fields: Vec<Field>) -> Schema {
+    Schema::new_with_metadata(fields, HashMap::from([(SCHEMA_VERSION_KEY.to_string(), SCHEMA_VERSION.to_string())]))
+}
+
+// A pool state as it was cached on a block
+#[derive(Debug, Clone)]
+pub struct PoolRow {
+    pub block_number: u64,
+    pub timestamp_ms: u64,
+    pub pair_name: String,
+    pub pool: PoolSnapshot,
+}
+
+fn decimal(builder: &mut Decimal128Builder, value: u128) {
+    builder.append_option(i128::try_from(value).ok());
+}
+
+pub fn pool_batch(rows: &[PoolRow]) -> Result<RecordBatch, ArrowError> {
+    let mut block_number = UInt64Builder::new();
+    let mut timestamp_ms = UInt64Builder::new();
+    let mut pair = StringBuilder::new();
+    let mut dex = StringBuilder::new();
+    let mut pool = StringBuilder::new();
+    let mut token0 = StringBuilder::new();
+    let mut token1 = StringBuilder::new();
+    let mut token0_decimals = UInt8Builder::new();
+    let mut token1_decimals = UInt8Builder::new();
+    let mut sqrt_price_x96 = StringBuilder::new();
+    let mut tick = Int32Builder::new();
+    let mut active_liquidity = Decimal128Builder::new().with_precision_and_scale(38, 0)?;
+    let mut liquidity = Decimal128Builder::new().with_precision_and_scale(38, 0)?;
+    let mut fee = UInt32Builder::new();
+    let mut fee_otz = UInt32Builder::new();
+    let mut price = Float64Builder::new();
+    let mut base_token = StringBuilder::new();
+    let mut weth_usd = Float64Builder::new();
+    let mut base_usd = Float64Builder::new();
+
+    for row in rows {
+        let snapshot = &row.pool;
+        block_number.append_value(row.block_number);
+        timestamp_ms.append_value(row.timestamp_ms);
+        pair.append_value(&row.pair_name);
+        dex.append_value(&snapshot.dex);
+        pool.append_value(&snapshot.pool_address);
+        token0.append_value(&snapshot.token0);
+        token1.append_value(&snapshot.token1);
+        token0_decimals.append_value(snapshot.token0_decimals);
+        token1_decimals.append_value(snapshot.token1_decimals);
+        sqrt_price_x96.append_value(&snapshot.sqrt_price_x96);
+        tick.append_value(snapshot.tick);
+        decimal(&mut active_liquidity, snapshot.active_liquidity);
+        decimal(&mut liquidity, snapshot.liquidity);
+        fee.append_value(snapshot.fee);
+        fee_otz.append_option(snapshot.fee_otz);
+        price.append_value(snapshot.price);
+        base_token.append_value(&snapshot.base_token);
+        weth_usd.append_value(snapshot.weth_usdc);
+        base_usd.append_value(snapshot.base_usd);
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(block_number.finish()),
+        Arc::new(timestamp_ms.finish()),
+        Arc::new(pair.finish()),
+        Arc::new(dex.finish()),
+        Arc::new(pool.finish()),
+        Arc::new(token0.finish()),
+        Arc::new(token1.finish()),
+        Arc::new(token0_decimals.finish()),
+        Arc::new(token1_decimals.finish()),
+        Arc::new(sqrt_price_x96.finish()),
+        Arc::new(tick.finish()),
+        Arc::new(active_liquidity.finish()),
+        Arc::new(liquidity.finish()),
+        Arc::new(fee.finish()),
+        Arc::new(fee_otz.finish()),
+        Arc::new(price.finish()),
+        Arc::new(base_token.finish()),
+        Arc::new(weth_usd.finish()),
+        Arc::new(base_usd.finish()),
+    ];
+    RecordBatch::try_new(pool_schema(), columns)
+}
+
+pub fn spread_batch(rows: &[PairBlockRecord]) -> Result<RecordBatch, ArrowError> {
+    let mut block_number = UInt64Builder::new();
+    let mut timestamp_ms = UInt64Builder::new();
+    let mut monitor = StringBuilder::new();
+    let mut pair = StringBuilder::new();
+    let mut dex_a = StringBuilder::new();
+    let mut pool_a = StringBuilder::new();
+    let mut price_a = Float64Builder::new();
+    let mut dex_b = StringBuilder::new();
+    let mut pool_b = StringBuilder::new();
+    let mut price_b = Float64Builder::new();
+    let mut spread = Float64Builder::new();
+    let mut min_liquidity_usd = Float64Builder::new();
+    let mut depth_usd = Float64Builder::new();
+    let mut tier = UInt8Builder::new();
+    let mut simulations = UInt32Builder::new();
+    let mut best_amount_usd = Float64Builder::new();
+    let mut best_profit_usd = Float64Builder::new();
+    let mut outcome = StringBuilder::new();
+
+    for row in rows {
+        let best = row.best();
+        block_number.append_value(row.block_number);
+        timestamp_ms.append_value(row.timestamp_ms);
+        monitor.append_value(&row.monitor);
+        pair.append_value(&row.pair_name);
+        dex_a.append_value(row.dex_a);
+        pool_a.append_value(&row.pool_a);
+        price_a.append_value(row.price_a);
+        dex_b.append_value(row.dex_b);
+        pool_b.append_value(&row.pool_b);
+        price_b.append_value(row.price_b);
+        spread.append_value(row.spread);
+        min_liquidity_usd.append_value(row.min_liquidity_usd);
+        depth_usd.append_value(row.depth_usd);
+        tier.append_option(row.tier);
+        simulations.append_value(row.simulations.len() as u32);
+        best_amount_usd.append_option(best.map(|b| b.amount_usd));
+        best_profit_usd.append_option(best.map(|b| b.profit_usd));
+        outcome.append_value(row.outcome.name());
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(block_number.finish()),
+        Arc::new(timestamp_ms.finish()),
+        Arc::new(monitor.finish()),
+        Arc::new(pair.finish()),
+        Arc::new(dex_a.finish()),
+        Arc::new(pool_a.finish()),
+        Arc::new(price_a.finish()),
+        Arc::new(dex_b.finish()),
+        Arc::new(pool_b.finish()),
+        Arc::new(price_b.finish()),
+        Arc::new(spread.finish()),
+        Arc::new(min_liquidity_usd.finish()),
+        Arc::new(depth_usd.finish()),
+        Arc::new(tier.finish()),
+        Arc::new(simulations.finish()),
+        Arc::new(best_amount_usd.finish()),
+        Arc::new(best_profit_usd.finish()),
+        Arc::new(outcome.finish()),
+    ];
+    RecordBatch::try_new(spread_schema(), columns)
+}
@@ -0,0 +1,220 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+use tracing::info;
+
+use crate::config::MAX_QUARANTINE_BLOCKS;
+use crate::error::{ScanError, read_lock, write_lock};
+use crate::settings::settings;
+
+// Failures counted by error kind, plus the latest one
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FailureCounts {
+    pub total: u64,
+    pub by_kind: BTreeMap<&'static str, u64>,
+    pub last_error: Option<String>,
+}
+
+impl FailureCounts {
+    fn add(&mut self, error: &ScanError) {
+        self.total += 1;
+        *self.by_kind.entry(error.kind().name()).or_default() += 1;
+        self.last_error = Some(error.to_string());
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PoolHealth {
+    pub pair_name: String,
+    pub failures: FailureCounts,
+    // Blocks with a failure since the pool last fetched and quoted cleanly
+    pub consecutive: u32,
+    pub last_failed_block: Option<u64>,
+    // Quarantines in a row, each twice as long as the one before
+    pub quarantines: u32,
+    // Last block the pool is skipped on
+    pub quarantined_until: Option<u64>,
+}
+
+// Failure counters per pool and per pair, shared by every monitor and the route scan.
+// A pool that keeps failing is quarantined: skipped for a while, then tried once more.
+#[derive(Default)]
+pub struct FailureTracker {
+    pools: RwLock<HashMap<String, PoolHealth>>,
+    pairs: RwLock<HashMap<String, FailureCounts>>,
+}
+
+impl FailureTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_quarantined(&self, pool_address: &str, block_number: u64) -> bool {
+        read_lock(&self.pools)
+            .get(&pool_address.to_lowercase())
+            .and_then(|health| health.quarantined_until)
+            .is_some_and(|until| block_number <= until)
+    }
+
+    // A pool that failed elsewhere on the same block hasn't recovered yet
+    pub fn record_success(&self, pool_address: &str, block_number: u64) {
+        let key = pool_address.to_lowercase();
+        // Healthy pools are the common case and only need the read lock
+        if read_lock(&self.pools).get(&key).is_none_or(|health| health.consecutive == 0) {
+            return;
+        }
+        let mut pools = write_lock(&self.pools);
+        let Some(health) = pools.get_mut(&key).filter(|health| health.last_failed_block != Some(block_number)) else {
+            return;
+        };
+        if health.quarantines > 0 {
+            info!("✅ {} pool {} recovered after {} quarantine(s)", health.pair_name, key, health.quarantines);
+        }
+        health.consecutive = 0;
+        health.quarantines = 0;
+        health.quarantined_until = None;
+    }
+
+    // Counts a failure against the pool. Returns the last block of the quarantine when
+    // this failure started one.
+    pub fn record_failure(&self, pool_address: &str, pair_name: &str, error: &ScanError, block_number: u64) -> Option<u64> {
+        let thresholds = settings().thresholds.clone();
+        let key = pool_address.to_lowercase();
+        let mut pools = write_lock(&self.pools);
+        let health = pools.entry(key.clone()).or_insert_with(|| PoolHealth {
+            pair_name: pair_name.to_string(),
+            ..PoolHealth::default()
+        });
+        health.failures.add(error);
+        // A pool shared by several pairs fails once per pair; only the first counts towards quarantine
+        if health.last_failed_block == Some(block_number) {
+            return None;
+        }
+        health.last_failed_block = Some(block_number);
+        health.consecutive = health.consecutive.saturating_add(1);
+
+        let in_quarantine = health.quarantined_until.is_some_and(|until| block_number <= until);
+        if health.consecutive < thresholds.quarantine_after || in_quarantine {
+            return None;
+        }
+        let blocks = thresholds
+            .quarantine_blocks
+            .saturating_mul(1u64 << health.quarantines.min(16))
+            .min(MAX_QUARANTINE_BLOCKS.max(thresholds.quarantine_blocks));
+        let until = block_number + blocks;
+        health.quarantines += 1;
+        health.quarantined_until = Some(until);
+        info!(
+            "🚧 Quarantined {} pool {} for {} blocks after failing {} blocks in a row: {}",
+            health.pair_name, key, blocks, health.consecutive, error
+        );
+        Some(until)
+    }
+
+    pub fn record_pair_failure(&self, pair_name: &str, error: &ScanError) {
+        write_lock(&self.pairs).entry(pair_name.to_string()).or_default().add(error);
+    }
+
+    // Pools that have failed at least once, by address
+    pub fn pools(&self) -> BTreeMap<String, PoolHealth> {
+        read_lock(&self.pools).iter().map(|(address, health)| (address.clone(), health.clone())).collect()
+    }
+
+    pub fn pairs(&self) -> BTreeMap<String, FailureCounts> {
+        read_lock(&self.pairs).iter().map(|(pair, counts)| (pair.clone(), counts.clone())).collect()
+    }
+
+    pub fn quarantined_count(&self, block_number: u64) -> usize {
+        read_lock(&self.pools)
+            .values()
+            .filter(|health| health.quarantined_until.is_some_and(|until| block_number <= until))
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FailureTracker;
+    use crate::config::MAX_QUARANTINE_BLOCKS;
+    use crate::error::ScanError;
+    use crate::settings::settings;
+
+    const POOL: &str = "0xC6F780497A95E246EB9449F5E4770916DCD6396A";
+
+    // Fails the pool on every block from `from` until a failure starts a quarantine
+    fn fail_until_quarantined(tracker: &FailureTracker, from: u64) -> (u64, u64) {
+        let error = ScanError::rpc("timeout");
+        (from..from + 1000)
+            .find_map(|block| tracker.record_failure(POOL, "WETH/USDC", &error, block).map(|until| (block, until)))
+            .unwrap()
+    }
+
+    #[test]
+    fn quarantines_after_the_failure_threshold() {
+        let thresholds = settings().thresholds.clone();
+        let tracker = FailureTracker::new();
+        let error = ScanError::rpc("timeout");
+        let last = 100 + thresholds.quarantine_after as u64 - 1;
+        for block in 100..last {
+            assert_eq!(tracker.record_failure(POOL, "WETH/USDC", &error, block), None);
+            // Another pair sharing the pool fails on the same block without counting again
+            assert_eq!(tracker.record_failure(POOL, "ARB/WETH", &error, block), None);
+            assert!(!tracker.is_quarantined(POOL, block + 1));
+        }
+
+        let until = tracker.record_failure(POOL, "WETH/USDC", &error, last).unwrap();
+        assert_eq!(until, last + thresholds.quarantine_blocks);
+        assert!(tracker.is_quarantined(&POOL.to_lowercase(), until));
+        assert!(!tracker.is_quarantined(POOL, until + 1));
+        assert_eq!(tracker.quarantined_count(last), 1);
+
+        let health = &tracker.pools()[&POOL.to_lowercase()];
+        assert_eq!(health.pair_name, "WETH/USDC");
+        assert_eq!(health.consecutive, thresholds.quarantine_after);
+        assert_eq!(health.failures.total, 2 * thresholds.quarantine_after as u64 - 1);
+        assert_eq!(health.failures.by_kind["rpc"], health.failures.total);
+    }
+
+    #[test]
+    fn each_quarantine_in_a_row_doubles_until_the_cap() {
+        let thresholds = settings().thresholds.clone();
+        let tracker = FailureTracker::new();
+        let mut block = 100;
+        let mut expected = thresholds.quarantine_blocks;
+        let cap = MAX_QUARANTINE_BLOCKS.max(thresholds.quarantine_blocks);
+        let mut lengths = Vec::new();
+        while lengths.last() != Some(&cap) {
+            let (started, until) = fail_until_quarantined(&tracker, block);
+            // Failures while quarantined don't start another one
+            assert_eq!(tracker.record_failure(POOL, "WETH/USDC", &ScanError::rpc("timeout"), started + 1), None);
+            lengths.push(until - started);
+            assert_eq!(until - started, expected);
+            expected = (expected * 2).min(cap);
+            block = until + 1;
+            assert!(lengths.len() < 64);
+        }
+        // Capped lengths stay capped
+        let (started, until) = fail_until_quarantined(&tracker, block);
+        assert_eq!(until - started, cap);
+    }
+
+    #[test]
+    fn a_clean_block_resets_the_quarantine_length() {
+        let thresholds = settings().thresholds.clone();
+        let cap = MAX_QUARANTINE_BLOCKS.max(thresholds.quarantine_blocks);
+        let tracker = FailureTracker::new();
+        let (_, until) = fail_until_quarantined(&tracker, 100);
+        let (started, again) = fail_until_quarantined(&tracker, until + 1);
+        assert_eq!(again - started, (thresholds.quarantine_blocks * 2).min(cap));
+
+        // A success on a block the pool also failed on doesn't count as recovery
+        tracker.record_success(POOL, started);
+        assert_eq!(tracker.pools()[&POOL.to_lowercase()].quarantines, 2);
+
+        tracker.record_success(POOL, again + 1);
+        let health = &tracker.pools()[&POOL.to_lowercase()];
+        assert_eq!((health.consecutive, health.quarantines, health.quarantined_until), (0, 0, None));
+        let (started, until) = fail_until_quarantined(&tracker, again + 2);
+        assert_eq!(until - started, thresholds.quarantine_blocks);
+    }
+}
@@ -0,0 +1,306 @@
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+use crate::config::{FEE_CHANGE_CHANNEL_CAPACITY, FEE_HISTORY_LEN, FEE_SNAPSHOT_FILE, FEE_SNAPSHOT_INTERVAL};
+use crate::error::{read_lock, write_lock};
+use crate::exchange::model::Dex;
+use crate::metrics::FEE_CHANGES;
+
+// Target of the fee change events, e.g. RUST_LOG=info,fee_change=off
+pub const FEE_CHANGE_TARGET: &str = "fee_change";
+
+// Fee of one pool as last seen, per swap direction. Only Camelot's differ.
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolFee {
+    pub dex: Dex,
+    pub pair_name: String,
+    pub pool: String,
+    // token0 -> token1
+    pub fee_zto: u32,
+    // token1 -> token0
+    pub fee_otz: u32,
+    // Block the current fee was first seen on
+    pub since_block: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeeChange {
+    pub dex: Dex,
+    pub pair_name: String,
+    pub pool: String,
+    // None the first time the pool is seen
+    pub old_fee_zto: Option<u32>,
+    pub old_fee_otz: Option<u32>,
+    pub new_fee_zto: u32,
+    pub new_fee_otz: u32,
+    pub block_number: u64,
+    pub timestamp_ms: u64,
+    // "state" for a fetched pool state, "event" for a Camelot Fee event
+    pub source: &'static str,
+}
+
+struct FeeEntry {
+    current: PoolFee,
+    history: VecDeque<FeeChange>,
+}
+
+// Current fee of every pool the scanner has seen, keyed by lowercase pool address, with
+// the last FEE_HISTORY_LEN changes of each. Unchanged fees only take the read lock, so
+// observing every pool on every block stays cheap; changes are counted, logged and sent
+// to subscribers, and the snapshot task writes the current fees to disk now and then.
+pub struct FeeRegistry {
+    pools: RwLock<HashMap<String, FeeEntry>>,
+    changes: broadcast::Sender<FeeChange>,
+    // Bumped on every change so unchanged registries aren't written again
+    version: AtomicU64,
+}
+
+impl Default for FeeRegistry {
+    fn default() -> Self {
+        Self {
+            pools: RwLock::new(HashMap::new()),
+            changes: broadcast::channel(FEE_CHANGE_CHANNEL_CAPACITY).0,
+            version: AtomicU64::new(0),
+        }
+    }
+}
+
+impl FeeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Returns the change when either direction's fee, given as (token0 -> token1,
+    // token1 -> token0), differs from the one on record. Fees from blocks older than the
+    // recorded one are ignored.
+    pub fn observe(
+        &self,
+        dex: Dex,
+        pair_name: &str,
+        pool_address: &str,
+        (fee_zto, fee_otz): (u32, u32),
+        block_number: u64,
+        source: &'static str,
+    ) -> Option<FeeChange> {
+        let key = pool_address.to_lowercase();
+        let unchanged = |entry: &FeeEntry| {
+            (entry.current.fee_zto == fee_zto && entry.current.fee_otz == fee_otz) || entry.current.since_block > block_number
+        };
+        if read_lock(&self.pools).get(&key).is_some_and(unchanged) {
+            return None;
+        }
+
+        let mut pools = write_lock(&self.pools);
+        let previous = pools.get(&key);
+        if previous.is_some_and(unchanged) {
+            return None;
+        }
+        let change = FeeChange {
+            dex,
+            pair_name: pair_name.to_string(),
+            pool: key.clone(),
+            old_fee_zto: previous.map(|entry| entry.current.fee_zto),
+            old_fee_otz: previous.map(|entry| entry.current.fee_otz),
+            new_fee_zto: fee_zto,
+            new_fee_otz: fee_otz,
+            block_number,
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            source,
+        };
+        let entry = pools.entry(key.clone()).or_insert_with(|| FeeEntry {
+            current: PoolFee {
+                dex,
+                pair_name: pair_name.to_string(),
+                pool: key.clone(),
+                fee_zto,
+                fee_otz,
+                since_block: block_number,
+            },
+            history: VecDeque::new(),
+        });
+        entry.current.fee_zto = fee_zto;
+        entry.current.fee_otz = fee_otz;
+        entry.current.since_block = block_number;
+        if entry.history.len() == FEE_HISTORY_LEN {
+            entry.history.pop_front();
+        }
+        entry.history.push_back(change.clone());
+        drop(pools);
+
+        self.version.fetch_add(1, Ordering::Relaxed);
+        if let (Some(old_fee_zto), Some(old_fee_otz)) = (change.old_fee_zto, change.old_fee_otz) {
+            FEE_CHANGES.with_label_values(&[dex.name()]).inc();
+            info!(
+                target: FEE_CHANGE_TARGET,
+                dex = dex.name(),
+                pair = pair_name,
+                pool = %key,
+                old_fee_zto,
+                old_fee_otz,
+                new_fee_zto = fee_zto,
+                new_fee_otz = fee_otz,
+                block = block_number,
+                source,
+                "💱 {} {} {} fee {} -> {} at #{} ({})",
+                dex.name(),
+                pair_name,
+                key,
+                fee_label(old_fee_zto, old_fee_otz),
+                fee_label(fee_zto, fee_otz),
+                block_number,
+                source
+            );
+        }
+        // Nobody listening is fine
+        let _ = self.changes.send(change.clone());
+        Some(change)
+    }
+
+    pub fn current(&self, pool_address: &str) -> Option<PoolFee> {
+        read_lock(&self.pools)
+            .get(&pool_address.to_lowercase())
+            .map(|entry| entry.current.clone())
+    }
+
+    // Every pool's current fee, by pair and then pool address
+    pub fn all(&self) -> Vec<PoolFee> {
+        let mut fees: Vec<PoolFee> = read_lock(&self.pools).values().map(|entry| entry.current.clone()).collect();
+        fees.sort_by(|a, b| a.pair_name.cmp(&b.pair_name).then_with(|| a.pool.cmp(&b.pool)));
+        fees
+    }
+
+    // Oldest change first, starting with the fee the pool was first seen with
+    pub fn history(&self, pool_address: &str) -> Vec<FeeChange> {
+        read_lock(&self.pools)
+            .get(&pool_address.to_lowercase())
+            .map(|entry| entry.history.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    // Every change from now on. A receiver that falls more than
+    // FEE_CHANGE_CHANNEL_CAPACITY changes behind skips the oldest.
+    pub fn subscribe(&self) -> broadcast::Receiver<FeeChange> {
+        self.changes.subscribe()
+    }
+
+    // Writes the current fees in fee.log's format, with the token1 -> token0 fee as an extra
+    // last column, to a temporary file and renames it over the snapshot, so readers never see
+    // a half-written file
+    pub fn write_snapshot(&self, path: &str) -> std::io::Result<()> {
+        let fees = self.all();
+        let tmp_path = format!("{}.tmp", path);
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        for fee in &fees {
+            writeln!(
+                writer,
+                "{},{},{},{}%,{}%",
+                fee_log_label(fee.dex),
+                fee.pair_name,
+                fee.pool,
+                fee.fee_zto as f64 / 10000.0,
+                fee.fee_otz as f64 / 10000.0
+            )?;
+        }
+        writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        std::fs::rename(&tmp_path, path)
+    }
+
+    fn version(&self) -> u64 {
+        self.version.load(Ordering::Relaxed)
+    }
+}
+
+// "300", or "300/350" when the directions differ
+fn fee_label(fee_zto: u32, fee_otz: u32) -> String {
+    if fee_zto == fee_otz { fee_zto.to_string() } else { format!("{}/{}", fee_zto, fee_otz) }
+}
+
+// Label fee.log has always used for the DEX
+fn fee_log_label(dex: Dex) -> &'static str {
+    match dex {
+        Dex::Uniswap => "Uniswap",
+        Dex::PancakeSwap => "Pancake",
+        Dex::Camelot => "Camelot",
+    }
+}
+
+// Writes FEE_SNAPSHOT_FILE every FEE_SNAPSHOT_INTERVAL when a fee changed since the last
+// write. The file I/O runs on the blocking pool, away from the monitors.
+pub async fn handle_fee_snapshots(fees: Arc<FeeRegistry>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut interval = tokio::time::interval(FEE_SNAPSHOT_INTERVAL);
+    let mut written = 0;
+
+    loop {
+        interval.tick().await;
+        let version = fees.version();
+        if version == written {
+            continue;
+        }
+        let registry = fees.clone();
+        match tokio::task::spawn_blocking(move || registry.write_snapshot(FEE_SNAPSHOT_FILE)).await? {
+            Ok(()) => written = version,
+            // Tried again on the next tick
+            Err(e) => warn!("Failed to write fee snapshot {}: {}", FEE_SNAPSHOT_FILE, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FeeRegistry;
+    use crate::exchange::model::Dex;
+
+    #[test]
+    fn either_direction_changing_is_a_change() {
+        let fees = FeeRegistry::new();
+        let mut changes = fees.subscribe();
+        let pool = "0xE51635AE8136ABAC44906A8F230C2D235E9C195F";
+
+        let first = fees.observe(Dex::Camelot, "ARB/WETH", pool, (500, 600), 1001, "state").unwrap();
+        assert_eq!((first.old_fee_zto, first.old_fee_otz), (None, None));
+        assert!(fees.observe(Dex::Camelot, "ARB/WETH", pool, (500, 600), 1002, "state").is_none());
+
+        // Only token1 -> token0 moved
+        let change = fees.observe(Dex::Camelot, "ARB/WETH", pool, (500, 450), 1003, "event").unwrap();
+        assert_eq!((change.old_fee_zto, change.old_fee_otz), (Some(500), Some(600)));
+        assert_eq!((change.new_fee_zto, change.new_fee_otz), (500, 450));
+        // An older block doesn't roll it back
+        assert!(fees.observe(Dex::Camelot, "ARB/WETH", pool, (500, 600), 1002, "state").is_none());
+
+        let current = fees.current(&pool.to_lowercase()).unwrap();
+        assert_eq!((current.fee_zto, current.fee_otz, current.since_block), (500, 450, 1003));
+        assert_eq!(fees.history(pool).len(), 2);
+        assert_eq!(changes.try_recv().unwrap().block_number, 1001);
+        assert_eq!(changes.try_recv().unwrap().new_fee_otz, 450);
+    }
+
+    #[test]
+    fn snapshot_has_both_directions() {
+        let fees = FeeRegistry::new();
+        fees.observe(Dex::Camelot, "ARB/WETH", "0xE51635AE8136ABAC44906A8F230C2D235E9C195F", (300, 350), 1001, "state");
+        fees.observe(Dex::Uniswap, "ARB/WETH", "0xC6F780497A95E246EB9449F5E4770916DCD6396A", (500, 500), 1001, "state");
+
+        let path = std::env::temp_dir().join(format!("scanner_fee_{}.log", std::process::id()));
+        let path = path.to_str().unwrap();
+        fees.write_snapshot(path).unwrap();
+        let snapshot = std::fs::read_to_string(path).unwrap();
+        let _ = std::fs::remove_file(path);
+
+        assert_eq!(
+            snapshot,
+            "Uniswap,ARB/WETH,0xc6f780497a95e246eb9449f5e4770916dcd6396a,0.05%,0.05%\n\
+             Camelot,ARB/WETH,0xe51635ae8136abac44906a8f230c2d235e9c195f,0.03%,0.035%\n"
+        );
+    }
+}
@@ -0,0 +1,338 @@
+pub mod report;
+
+use rusqlite::{Connection, params};
+use serde::Serialize;
+use std::sync::mpsc::{self, RecvTimeoutError, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{error, info};
+
+use crate::config::{HISTORY_BATCH_SIZE, HISTORY_FLUSH_INTERVAL, HISTORY_QUEUE_LEN};
+use crate::error::lock;
+use crate::metrics::HISTORY_ROWS;
+use crate::settings::settings;
+
+type SendError = Box<dyn std::error::Error + Send + Sync>;
+
+// Schema changes in order; a database at user_version N has had the first N applied.
+// Released migrations are never edited, only appended to.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE pair_blocks (
+        id INTEGER PRIMARY KEY,
+        block_number INTEGER NOT NULL,
+        timestamp_ms INTEGER NOT NULL,
+        monitor TEXT NOT NULL,
+        pair TEXT NOT NULL,
+        dex_a TEXT NOT NULL,
+        pool_a TEXT NOT NULL,
+        price_a REAL NOT NULL,
+        dex_b TEXT NOT NULL,
+        pool_b TEXT NOT NULL,
+        price_b REAL NOT NULL,
+        spread REAL NOT NULL,
+        min_liquidity_usd REAL NOT NULL,
+        depth_usd REAL NOT NULL,
+        tier INTEGER,
+        best_amount_usd REAL,
+        best_profit_usd REAL,
+        outcome TEXT NOT NULL,
+        selected INTEGER NOT NULL,
+        published INTEGER NOT NULL DEFAULT 0,
+        suspect_reason TEXT
+    );
+    CREATE INDEX pair_blocks_pair_block ON pair_blocks (pair, block_number);
+    CREATE INDEX pair_blocks_block ON pair_blocks (block_number);
+    CREATE TABLE simulations (
+        pair_block_id INTEGER NOT NULL REFERENCES pair_blocks (id),
+        amount_usd REAL NOT NULL,
+        amount_in REAL NOT NULL,
+        amount_out REAL NOT NULL,
+        profit_usd REAL NOT NULL
+    );
+    CREATE INDEX simulations_pair_block ON simulations (pair_block_id);",
+];
+
+// How a pair's evaluation on a block ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Outcome {
+    // Too shallow for any tier
+    NoTier,
+    // The pair's tier is disabled, not simulated
+    TierDisabled,
+    // Spread under the tier's minimum, not simulated
+    BelowSpread,
+    // No simulated size made money
+    Unprofitable,
+    // Profitable, but the trade already waiting to be published was better
+    Outbid,
+    // Priced off a suspect block or token price, held back
+    Suspect,
+    // Stored as the best trade. The IPC publisher sends it on its next cycle unless a
+    // better trade replaces it first or publishing is held; the row is marked published
+    // once it is handed to the publisher.
+    Selected,
+}
+
+impl Outcome {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Outcome::NoTier => "no_tier",
+            Outcome::TierDisabled => "tier_disabled",
+            Outcome::BelowSpread => "below_spread",
+            Outcome::Unprofitable => "unprofitable",
+            Outcome::Outbid => "outbid",
+            Outcome::Suspect => "suspect",
+            Outcome::Selected => "selected",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulationRecord {
+    pub amount_usd: f64,
+    // In the pair's base token
+    pub amount_in: f64,
+    pub amount_out: f64,
+    pub profit_usd: f64,
+}
+
+// One pool pair on one block
+#[derive(Debug, Clone, Serialize)]
+pub struct PairBlockRecord {
+    pub block_number: u64,
+    pub timestamp_ms: u64,
+    pub monitor: String,
+    pub pair_name: String,
+    pub dex_a: &'static str,
+    pub pool_a: String,
+    // USD prices
+    pub price_a: f64,
+    pub dex_b: &'static str,
+    pub pool_b: String,
+    pub price_b: f64,
+    // Fraction, 0.01 is 1%
+    pub spread: f64,
+    pub min_liquidity_usd: f64,
+    pub depth_usd: f64,
+    pub tier: Option<u8>,
+    pub simulations: Vec<SimulationRecord>,
+    pub outcome: Outcome,
+    pub suspect_reason: Option<String>,
+}
+
+impl PairBlockRecord {
+    pub fn key(&self) -> PairBlockKey {
+        PairBlockKey {
+            block_number: self.block_number,
+            pair_name: self.pair_name.clone(),
+            pool_a: self.pool_a.clone(),
+            pool_b: self.pool_b.clone(),
+        }
+    }
+
+    pub(crate) fn best(&self) -> Option<&SimulationRecord> {
+        self.simulations.iter().max_by(|a, b| a.profit_usd.total_cmp(&b.profit_usd))
+    }
+}
+
+// Opens the database and brings its schema up to date
+pub fn open(path: &str) -> Result<Connection, SendError> {
+    let mut conn = Connection::open(path)?;
+    conn.busy_timeout(Duration::from_secs(5))?;
+    // Reports can read while the scanner writes
+    conn.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    migrate(&mut conn)?;
+    Ok(conn)
+}
+
+fn migrate(conn: &mut Connection) -> Result<(), SendError> {
+    let tx = conn.transaction()?;
+    let version: usize = tx.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if version > MIGRATIONS.len() {
+        return Err(format!(
+            "history database is at schema version {}, newer than this scanner's {}",
+            version,
+            MIGRATIONS.len()
+        )
+        .into());
+    }
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", index + 1)?;
+        info!("🗄️ History database migrated to schema version {}", index + 1);
+    }
+    Ok(tx.commit()?)
+}
+
+// The block and pool pair a history row is about
+#[derive(Debug, Clone, PartialEq)]
+pub struct PairBlockKey {
+    pub block_number: u64,
+    pub pair_name: String,
+    pub pool_a: String,
+    pub pool_b: String,
+}
+
+enum HistoryWrite {
+    Record(PairBlockRecord),
+    Published(PairBlockKey),
+    // Writes what is batched and stops the writer thread
+    Close,
+}
+
+// Writes the batch in order, so a trade published right after it was selected finds its row
+fn write_batch(conn: &mut Connection, batch: &[HistoryWrite]) -> rusqlite::Result<()> {
+    let tx = conn.transaction()?;
+    {
+        let mut insert_pair = tx.prepare_cached(
+            "INSERT INTO pair_blocks (block_number, timestamp_ms, monitor, pair, dex_a, pool_a, price_a, dex_b, pool_b,
+                price_b, spread, min_liquidity_usd, depth_usd, tier, best_amount_usd, best_profit_usd, outcome, selected,
+                suspect_reason)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
+        )?;
+        let mut insert_simulation = tx.prepare_cached(
+            "INSERT INTO simulations (pair_block_id, amount_usd, amount_in, amount_out, profit_usd) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        let mut mark_published = tx.prepare_cached(
+            "UPDATE pair_blocks SET published = 1
+             WHERE block_number = ?1 AND pair = ?2 AND pool_a = ?3 AND pool_b = ?4 AND selected",
+        )?;
+        for write in batch {
+            let record = match write {
+                HistoryWrite::Record(record) => record,
+                HistoryWrite::Published(key) => {
+                    mark_published.execute(params![key.block_number as i64, key.pair_name, key.pool_a, key.pool_b])?;
+                    continue;
+                }
+                HistoryWrite::Close => continue,
+            };
+            let best = record.best();
+            insert_pair.execute(params![
+                record.block_number as i64,
+                record.timestamp_ms as i64,
+                record.monitor,
+                record.pair_name,
+                record.dex_a,
+                record.pool_a,
+                record.price_a,
+                record.dex_b,
+                record.pool_b,
+                record.price_b,
+                record.spread,
+                record.min_liquidity_usd,
+                record.depth_usd,
+                record.tier,
+                best.map(|b| b.amount_usd),
+                best.map(|b| b.profit_usd),
+                record.outcome.name(),
+                record.outcome == Outcome::Selected,
+                record.suspect_reason,
+            ])?;
+            let pair_block_id = tx.last_insert_rowid();
+            for simulation in &record.simulations {
+                insert_simulation.execute(params![
+                    pair_block_id,
+                    simulation.amount_usd,
+                    simulation.amount_in,
+                    simulation.amount_out,
+                    simulation.profit_usd,
+                ])?;
+            }
+        }
+    }
+    tx.commit()
+}
+
+// Stores every evaluated pair-block in SQLite, and marks selected rows published when the
+// IPC publisher sends them. Writes are queued without blocking and done by a dedicated
+// thread, one transaction per HISTORY_BATCH_SIZE writes or HISTORY_FLUSH_INTERVAL,
+// whichever comes first. A full queue drops writes rather than slowing the monitors down.
+#[derive(Clone)]
+pub struct HistoryWriter {
+    tx: mpsc::SyncSender<HistoryWrite>,
+    writer: Arc<Mutex<Option<thread::JoinHandle<()>>>>,
+}
+
+impl HistoryWriter {
+    // Enabled by [history] database or HISTORY_DB
+    pub fn from_settings() -> Result<Option<Self>, SendError> {
+        let path = settings().history.database.clone();
+        if path.is_empty() {
+            return Ok(None);
+        }
+        Self::new(&path).map(Some)
+    }
+
+    pub fn new(path: &str) -> Result<Self, SendError> {
+        let mut conn = open(path)?;
+        let (tx, rx) = mpsc::sync_channel::<HistoryWrite>(HISTORY_QUEUE_LEN);
+
+        info!("🗄️ Storing pair history in {}", path);
+
+        let writer = thread::spawn(move || {
+            let mut batch = Vec::with_capacity(HISTORY_BATCH_SIZE);
+            let mut closing = false;
+            while !closing {
+                match rx.recv() {
+                    Ok(HistoryWrite::Close) | Err(_) => break,
+                    Ok(write) => batch.push(write),
+                }
+                // Collect what else arrives before the flush deadline, up to a full batch
+                let deadline = Instant::now() + HISTORY_FLUSH_INTERVAL;
+                while batch.len() < HISTORY_BATCH_SIZE {
+                    match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                        Ok(HistoryWrite::Close) => {
+                            closing = true;
+                            break;
+                        }
+                        Ok(write) => batch.push(write),
+                        Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+                let records = batch.iter().filter(|write| matches!(write, HistoryWrite::Record(_))).count() as u64;
+                match write_batch(&mut conn, &batch) {
+                    Ok(()) => HISTORY_ROWS.with_label_values(&["written"]).inc_by(records),
+                    Err(e) => {
+                        HISTORY_ROWS.with_label_values(&["failed"]).inc_by(records);
+                        error!("❌ Failed to write {} history records: {}", records, e);
+                    }
+                }
+                batch.clear();
+            }
+        });
+
+        Ok(Self { tx, writer: Arc::new(Mutex::new(Some(writer))) })
+    }
+
+    // Writes the queued records and waits for the writer thread to stop. Records from
+    // handles still in use afterwards are dropped.
+    pub fn close(&self) {
+        let Some(writer) = lock(&self.writer).take() else {
+            return;
+        };
+        // Blocks while the queue is full, which the writer is draining
+        if self.tx.send(HistoryWrite::Close).is_ok() && writer.join().is_err() {
+            error!("❌ History writer thread panicked");
+        }
+    }
+
+    pub fn record(&self, record: PairBlockRecord) {
+        self.queue(HistoryWrite::Record(record));
+    }
+
+    // Marks the selected row of a pair-block published
+    pub fn published(&self, key: PairBlockKey) {
+        self.queue(HistoryWrite::Published(key));
+    }
+
+    fn queue(&self, write: HistoryWrite) {
+        match self.tx.try_send(write) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => HISTORY_ROWS.with_label_values(&["dropped"]).inc(),
+            // The writer thread only stops if the process is shutting down
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use rusqlite::{Connection, params};
+use serde::Serialize;
+
+use super::open;
+
+type SendError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryReport {
+    TopPairs,
+    Spreads,
+    Outcomes,
+    Selected,
+}
+
+impl HistoryReport {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "top-pairs" => Some(HistoryReport::TopPairs),
+            "spreads" => Some(HistoryReport::Spreads),
+            "outcomes" => Some(HistoryReport::Outcomes),
+            "selected" => Some(HistoryReport::Selected),
+            _ => None,
+        }
+    }
+}
+
+// Pairs by the sum of their best simulated profit on every block it was positive,
+// whether or not it was selected
+#[derive(Debug, Serialize)]
+pub struct PairProfit {
+    pub pair: String,
+    pub evaluations: u64,
+    pub profitable: u64,
+    pub selected: u64,
+    pub published: u64,
+    pub cumulative_profit_usd: f64,
+    pub best_profit_usd: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PoolPairSpread {
+    pub pair: String,
+    pub dex_a: String,
+    pub dex_b: String,
+    pub evaluations: u64,
+    pub avg_spread: f64,
+    pub max_spread: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OutcomeCount {
+    pub tier: Option<u8>,
+    pub outcome: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SelectedOpportunity {
+    pub block_number: u64,
+    pub timestamp_ms: u64,
+    pub pair: String,
+    pub dex_a: String,
+    pub dex_b: String,
+    pub spread: f64,
+    pub tier: Option<u8>,
+    pub amount_usd: f64,
+    pub profit_usd: f64,
+    // Handed to the IPC publisher, rather than replaced or held first
+    pub published: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum ReportRows {
+    TopPairs(Vec<PairProfit>),
+    Spreads(Vec<PoolPairSpread>),
+    Outcomes(Vec<OutcomeCount>),
+    Selected(Vec<SelectedOpportunity>),
+}
+
+pub fn run_report(path: &str, report: HistoryReport, limit: usize) -> Result<ReportRows, SendError> {
+    let conn = open(path)?;
+    let limit = limit as i64;
+    Ok(match report {
+        HistoryReport::TopPairs => ReportRows::TopPairs(top_pairs(&conn, limit)?),
+        HistoryReport::Spreads => ReportRows::Spreads(spreads(&conn, limit)?),
+        HistoryReport::Outcomes => ReportRows::Outcomes(outcomes(&conn)?),
+        HistoryReport::Selected => ReportRows::Selected(selected(&conn, limit)?),
+    })
+}
+
+fn top_pairs(conn: &Connection, limit: i64) -> rusqlite::Result<Vec<PairProfit>> {
+    let mut statement = conn.prepare(
+        "SELECT pair, COUNT(*), SUM(COALESCE(best_profit_usd, 0) > 0), SUM(selected), SUM(published),
+                COALESCE(SUM(MAX(best_profit_usd, 0)), 0), COALESCE(MAX(best_profit_usd), 0)
+         FROM pair_blocks
+         GROUP BY pair
+         ORDER BY 6 DESC
+         LIMIT ?1",
+    )?;
+    let rows = statement.query_map(params![limit], |row| {
+        Ok(PairProfit {
+            pair: row.get(0)?,
+            evaluations: row.get(1)?,
+            profitable: row.get(2)?,
+            selected: row.get(3)?,
+            published: row.get(4)?,
+            cumulative_profit_usd: row.get(5)?,
+            best_profit_usd: row.get(6)?,
+        })
+    })?;
+    rows.collect()
+}
+
+fn spreads(conn: &Connection, limit: i64) -> rusqlite::Result<Vec<PoolPairSpread>> {
+    let mut statement = conn.prepare(
+        "SELECT pair, dex_a, dex_b, COUNT(*), AVG(spread), MAX(spread)
+         FROM pair_blocks
+         GROUP BY pair, dex_a, dex_b
+         ORDER BY 5 DESC
+         LIMIT ?1",
+    )?;
+    let rows = statement.query_map(params![limit], |row| {
+        Ok(PoolPairSpread {
+            pair: row.get(0)?,
+            dex_a: row.get(1)?,
+            dex_b: row.get(2)?,
+            evaluations: row.get(3)?,
+            avg_spread: row.get(4)?,
+            max_spread: row.get(5)?,
+        })
+    })?;
+    rows.collect()
+}
+
+fn outcomes(conn: &Connection) -> rusqlite::Result<Vec<OutcomeCount>> {
+    let mut statement = conn.prepare(
+        "SELECT tier, outcome, COUNT(*) FROM pair_blocks GROUP BY tier, outcome ORDER BY tier, 3 DESC",
+    )?;
+    let rows = statement.query_map([], |row| {
+        Ok(OutcomeCount {
+            tier: row.get(0)?,
+            outcome: row.get(1)?,
+            count: row.get(2)?,
+        })
+    })?;
+    rows.collect()
+}
+
+fn selected(conn: &Connection, limit: i64) -> rusqlite::Result<Vec<SelectedOpportunity>> {
+    let mut statement = conn.prepare(
+        "SELECT block_number, timestamp_ms, pair, dex_a, dex_b, spread, tier, best_amount_usd, best_profit_usd, published
+         FROM pair_blocks
+         WHERE selected
+         ORDER BY id DESC
+         LIMIT ?1",
+    )?;
+    let rows = statement.query_map(params![limit], |row| {
+        Ok(SelectedOpportunity {
+            block_number: row.get(0)?,
+            timestamp_ms: row.get(1)?,
+            pair: row.get(2)?,
+            dex_a: row.get(3)?,
+            dex_b: row.get(4)?,
+            spread: row.get(5)?,
+            tier: row.get(6)?,
+            amount_usd: row.get(7)?,
+            profit_usd: row.get(8)?,
+            published: row.get(9)?,
+        })
+    })?;
+    rows.collect()
+}
+
+pub fn print_report(rows: &ReportRows) {
+    match rows {
+        ReportRows::TopPairs(pairs) => {
+            println!("🏆 Top pairs by cumulative theoretical profit:");
+            for pair in pairs {
+                println!(
+                    "   {} | evaluated: {} | profitable: {} | selected: {} | published: {} | cumulative: ${:.2} | best: ${:.4}",
+                    pair.pair,
+                    pair.evaluations,
+                    pair.profitable,
+                    pair.selected,
+                    pair.published,
+                    pair.cumulative_profit_usd,
+                    pair.best_profit_usd
+                );
+            }
+        }
+        ReportRows::Spreads(spreads) => {
+            println!("📏 Widest average spreads:");
+            for spread in spreads {
+                println!(
+                    "   {} {}-{} | evaluated: {} | avg: {:.4}% | max: {:.4}%",
+                    spread.pair,
+                    spread.dex_a,
+                    spread.dex_b,
+                    spread.evaluations,
+                    spread.avg_spread * 100.0,
+                    spread.max_spread * 100.0
+                );
+            }
+        }
+        ReportRows::Outcomes(outcomes) => {
+            println!("📋 Outcomes by tier:");
+            for outcome in outcomes {
+                let tier = outcome.tier.map_or("-".to_string(), |tier| tier.to_string());
+                println!("   TIER {} | {}: {}", tier, outcome.outcome, outcome.count);
+            }
+        }
+        ReportRows::Selected(opportunities) => {
+            println!("💹 Latest selected opportunities:");
+            for opportunity in opportunities {
+                println!(
+                    "   #{} {} {}-{} | Diff: {:.4}% | Size: ${:.2} | Profit: ${:.4}{}",
+                    opportunity.block_number,
+                    opportunity.pair,
+                    opportunity.dex_a,
+                    opportunity.dex_b,
+                    opportunity.spread * 100.0,
+                    opportunity.amount_usd,
+                    opportunity.profit_usd,
+                    if opportunity.published { " | published" } else { "" }
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::{HistoryWrite, Outcome, PairBlockRecord, SimulationRecord, write_batch};
+
+    fn record(pair_name: &str, block_number: u64, profit_usd: f64, outcome: Outcome) -> PairBlockRecord {
+        PairBlockRecord {
+            block_number,
+            timestamp_ms: 1_700_000_000_000 + block_number,
+            monitor: "camelot_pancakeswap".to_string(),
+            pair_name: pair_name.to_string(),
+            dex_a: "Camelot",
+            pool_a: format!("{}-camelot", pair_name),
+            price_a: 0.75,
+            dex_b: "PancakeSwap",
+            pool_b: format!("{}-pancakeswap", pair_name),
+            price_b: 0.765,
+            spread: 0.02,
+            min_liquidity_usd: 250_000.0,
+            depth_usd: 120_000.0,
+            tier: Some(2),
+            simulations: vec![SimulationRecord { amount_usd: 1000.0, amount_in: 0.33, amount_out: 0.335, profit_usd }],
+            outcome,
+            suspect_reason: None,
+        }
+    }
+
+    #[test]
+    fn top_pairs_counts_selected_and_published_rows() {
+        let path = std::env::temp_dir().join(format!("scanner_history_{}.db", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        let mut conn = open(path).unwrap();
+        let published = record("ARB/WETH", 101, 6.0, Outcome::Selected);
+        write_batch(
+            &mut conn,
+            &[
+                HistoryWrite::Record(record("ARB/WETH", 100, 4.0, Outcome::Selected)),
+                HistoryWrite::Record(published.clone()),
+                HistoryWrite::Record(record("ARB/WETH", 102, -1.0, Outcome::Unprofitable)),
+                HistoryWrite::Record(record("GMX/WETH", 100, 2.0, Outcome::Outbid)),
+                HistoryWrite::Published(published.key()),
+            ],
+        )
+        .unwrap();
+        drop(conn);
+
+        // Reopening an up-to-date database applies nothing
+        let conn = open(path).unwrap();
+        let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0)).unwrap();
+        assert_eq!(version, 1);
+
+        let pairs = top_pairs(&conn, 10).unwrap();
+        assert_eq!(pairs.iter().map(|pair| pair.pair.as_str()).collect::<Vec<_>>(), ["ARB/WETH", "GMX/WETH"]);
+        let arb = &pairs[0];
+        assert_eq!((arb.evaluations, arb.profitable, arb.selected, arb.published), (3, 2, 2, 1));
+        assert_eq!((arb.cumulative_profit_usd, arb.best_profit_usd), (10.0, 6.0));
+        let gmx = &pairs[1];
+        assert_eq!((gmx.evaluations, gmx.profitable, gmx.selected, gmx.published), (1, 1, 0, 0));
+
+        let rows = selected(&conn, 10).unwrap();
+        assert_eq!(rows.iter().map(|row| (row.block_number, row.published)).collect::<Vec<_>>(), [(101, true), (100, false)]);
+
+        drop(conn);
+        let _ = std::fs::remove_file(path);
+    }
+}
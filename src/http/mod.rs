@@ -0,0 +1,189 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tokio::net::TcpStream;
+
+use crate::config::{HTTP_MAX_HEAD_LEN, HTTP_READ_TIMEOUT};
+
+type SendError = Box<dyn std::error::Error + Send + Sync>;
+
+// The request line of a request whose head was read in full
+pub struct Request {
+    pub method: String,
+    // Without the query string
+    pub path: String,
+    // Everything read from the connection: the head and whatever came after it
+    pub bytes: Vec<u8>,
+}
+
+// Reads until the blank line that ends the request head, however many reads that takes.
+// A head longer than HTTP_MAX_HEAD_LEN is answered with a 431 and fails, and so is one
+// that isn't complete within HTTP_READ_TIMEOUT, with a 408, so idle clients can't hold
+// connections open.
+pub async fn read_request(stream: &mut TcpStream) -> Result<Request, SendError> {
+    read_request_within(stream, HTTP_READ_TIMEOUT).await
+}
+
+async fn read_request_within(stream: &mut TcpStream, timeout: Duration) -> Result<Request, SendError> {
+    match tokio::time::timeout(timeout, read_head(stream)).await {
+        Ok(result) => result,
+        Err(_) => {
+            let response = http_response("408 Request Timeout", "text/plain", b"request timed out\n");
+            stream.write_all(&response).await?;
+            Err(format!("no complete request head within {:?}", timeout).into())
+        }
+    }
+}
+
+async fn read_head(stream: &mut TcpStream) -> Result<Request, SendError> {
+    let mut bytes = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 1024];
+    loop {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Err("connection closed before the end of the request".into());
+        }
+        // The end may straddle two reads
+        let search_from = bytes.len().saturating_sub(3);
+        bytes.extend_from_slice(&chunk[..read]);
+        if bytes[search_from..].windows(4).any(|window| window == b"\r\n\r\n") {
+            break;
+        }
+        if bytes.len() > HTTP_MAX_HEAD_LEN {
+            let response = http_response("431 Request Header Fields Too Large", "text/plain", b"request too large\n");
+            stream.write_all(&response).await?;
+            return Err(format!("request head over {} bytes", HTTP_MAX_HEAD_LEN).into());
+        }
+    }
+
+    let head = String::from_utf8_lossy(&bytes);
+    let mut parts = head.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().split('?').next().unwrap_or_default().to_string();
+    Ok(Request { method, path, bytes })
+}
+
+// Browser dashboards on another origin may read everything, it is all read-only
+pub fn http_response(status: &str, content_type: &str, body: &[u8]) -> Vec<u8> {
+    let mut response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )
+    .into_bytes();
+    response.extend_from_slice(body);
+    response
+}
+
+// A connection whose request was already read, given back from the start so a WebSocket
+// handshake can read it again
+pub struct Replayed {
+    read: Vec<u8>,
+    offset: usize,
+    stream: TcpStream,
+}
+
+impl Replayed {
+    pub fn new(request: Request, stream: TcpStream) -> Self {
+        Self { read: request.bytes, offset: 0, stream }
+    }
+}
+
+impl AsyncRead for Replayed {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        if self.offset < self.read.len() {
+            let end = self.read.len().min(self.offset + buf.remaining());
+            buf.put_slice(&self.read[self.offset..end]);
+            self.offset = end;
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Replayed {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Replayed, read_request, read_request_within};
+    use futures_util::StreamExt;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    #[tokio::test]
+    async fn request_head_split_across_writes() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(b"GET /fees/0xe516?pretty HTTP/1.1\r\nHost: loc").await.unwrap();
+            stream.flush().await.unwrap();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            stream.write_all(b"alhost\r\n\r").await.unwrap();
+            stream.flush().await.unwrap();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            stream.write_all(b"\n").await.unwrap();
+            stream
+        });
+
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let request = read_request(&mut stream).await.unwrap();
+        assert_eq!((request.method.as_str(), request.path.as_str()), ("GET", "/fees/0xe516"));
+        assert!(request.bytes.ends_with(b"Host: localhost\r\n\r\n"));
+        drop(client.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn idle_client_times_out_with_a_408() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(b"GET /health HTTP/1.1\r\n").await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        });
+
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let error = read_request_within(&mut stream, Duration::from_millis(100)).await.err().unwrap();
+        assert!(error.to_string().starts_with("no complete request head within"), "{}", error);
+        drop(stream);
+        assert!(client.await.unwrap().starts_with("HTTP/1.1 408 Request Timeout\r\n"));
+    }
+
+    // The head is read before routing, and the handshake still gets to see all of it
+    #[tokio::test]
+    async fn websocket_upgrade_after_reading_the_head() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let request = read_request(&mut stream).await.unwrap();
+            assert_eq!(request.path, "/ws");
+            let mut ws = tokio_tungstenite::accept_async(Replayed::new(request, stream)).await.unwrap();
+            ws.close(None).await.unwrap();
+        });
+
+        let (mut ws, response) = tokio_tungstenite::connect_async(format!("ws://{}/ws", addr)).await.unwrap();
+        assert_eq!(response.status(), 101);
+        while ws.next().await.is_some() {}
+        server.await.unwrap();
+    }
+}
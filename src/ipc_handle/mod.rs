@@ -1,14 +1,45 @@
+pub mod protocol;
+pub mod subscriber;
+
 use iceoryx2::prelude::*;
 use iceoryx2_bb_container::vec::FixedSizeVec;
+use serde_json::json;
 use std::sync::{Arc, RwLock, mpsc};
-use bincode::{Encode, Decode};
-use crate::config::IPC_CYCLE_TIME;
-use std::io;
+use tracing::{error, info};
+
+use crate::cli::{json_output, tui_output};
+use crate::config::MAX_ROUTE_HOPS;
+use crate::error::read_lock;
+use crate::history::{HistoryWriter, PairBlockKey};
+use crate::metrics::{IPC_PUBLISHED, IPC_PUBLISH_SECONDS};
+use crate::settings::settings;
+use ethers::prelude::U256;
+use protocol::{
+    ArbTran, IpcMessage, MultiLegOpportunity, OPPORTUNITY_SERVICE, Opportunity, POOL_SERVICE,
+    PoolAnnouncement, PoolIpcMessage, ROUTE_SERVICE, RouteIpcMessage, WethUsdReference, encode, hex_bytes,
+};
 use std::thread;
 
-#[derive(Clone)]
+// Target of the events logged when the published trade or route changes, so they can be
+// filtered on their own, e.g. RUST_LOG=info,opportunity=off
+pub const OPPORTUNITY_TARGET: &str = "opportunity";
+
+// WETH/USD reference a trade was priced with
+#[derive(Clone, Default, PartialEq)]
+pub struct PriceReference {
+    pub weth_usd: f64,
+    pub method: u8,
+    pub pool_count: u8,
+    pub block_number: u64,
+    pub updated_at_ms: u64,
+    // Chainlink ETH/USD it was checked against, 0 when the oracle check is off
+    pub oracle_usd: f64,
+}
+
+#[derive(Clone, Default, PartialEq)]
 pub struct BestTrade {
     pub profit_usdc: f64,
+    pub reference: PriceReference,
     pub buy_dex: [u8; 20],
     pub buy_token_in: [u8; 20],
     pub buy_token_out: [u8; 20],
@@ -19,76 +50,160 @@ pub struct BestTrade {
     pub sell_token_out: [u8; 20],
     pub sell_fee: u32,
     pub sell_amount: [u8; 32],
+    // Pair-block the trade was selected on, None until the first trade
+    pub pair_block: Option<PairBlockKey>,
 }
 
-pub struct StreamResults {
-    pub best_trade: Arc<RwLock<BestTrade>>,
+// One swap of a multi-hop route, in the same units as BestTrade
+#[derive(Clone, Default, PartialEq)]
+pub struct RouteLeg {
+    pub dex: [u8; 20],
+    pub token_in: [u8; 20],
+    pub token_out: [u8; 20],
+    pub fee: u32,
+    pub amount: [u8; 32],
 }
 
-#[derive(Encode, Decode, Debug, Clone, Default)]
-#[repr(C)]
-
-struct ArbTran {
-    dex: [u8; 20],
-    token_from: [u8; 20],
-    token_to: [u8; 20],
-    fee: u32,
-    amount: [u8; 32],
+// The route scanner's pick for the latest block, found on reference.block_number. It is
+// reset at the start of every block, so no legs means nothing to publish.
+#[derive(Clone, Default, PartialEq)]
+pub struct BestRoute {
+    pub profit_usdc: f64,
+    pub reference: PriceReference,
+    pub legs: Vec<RouteLeg>,
 }
 
-#[derive(Encode, Decode, Debug, Clone, Default)]
-#[repr(C)]
-struct Opportunity {
-    first_transaction: ArbTran,
-    second_transaction: ArbTran,
+// Pool added to the live pair set at runtime
+#[derive(Clone, Default)]
+pub struct NewPool {
+    pub dex: [u8; 20],
+    pub pool: [u8; 20],
+    pub token0: [u8; 20],
+    pub token1: [u8; 20],
+    pub fee: u32,
+    pub liquidity_usdc: f64,
 }
 
-// Message type for the channel
-const DATA_SIZE: usize = std::mem::size_of::<Opportunity>();
+pub struct StreamResults {
+    pub best_trade: Arc<RwLock<BestTrade>>,
+    pub best_route: Arc<RwLock<BestRoute>>,
+    // Why publishing is on hold, set while the latest block's prices disagree with Chainlink
+    pub oracle_hold: Arc<RwLock<Option<String>>>,
+}
 
-type IpcMessage = FixedSizeVec<u8, DATA_SIZE>;
 
 // Function to run the publisher in a dedicated thread
-fn run_publisher_thread(rx: mpsc::Receiver<IpcMessage>) -> Result<(), Box<dyn std::error::Error>> {
+fn run_publisher_thread<const N: usize>(
+    service_name: &str,
+    rx: mpsc::Receiver<FixedSizeVec<u8, N>>,
+) -> Result<(), Box<dyn std::error::Error>> {
     let node = NodeBuilder::new().create::<ipc::Service>()?;
 
 
     let service = node
-        .service_builder(&"arbiscan_bot".try_into()?)
-        .publish_subscribe::<FixedSizeVec<u8, DATA_SIZE>>()
+        .service_builder(&service_name.try_into()?)
+        .publish_subscribe::<FixedSizeVec<u8, N>>()
         .open_or_create()?;
 
     let publisher = service.publisher_builder().create()?;
 
+    let published = IPC_PUBLISHED.with_label_values(&[service_name]);
+    let publish_seconds = IPC_PUBLISH_SECONDS.with_label_values(&[service_name]);
     while let Ok(msg) = rx.recv() {
+        let timer = publish_seconds.start_timer();
         let sample = publisher.loan_uninit()?;
         let sample = sample.write_payload(msg);
         sample.send()?;
+        timer.observe_duration();
+        published.inc();
     }
 
     Ok(())
 }
 
-// Modified handle_ipc_stream to use channel
-pub async fn handle_ipc_stream(stream_results: Arc<StreamResults>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let (tx, rx) = mpsc::channel();
+// Publishes newly added pools on their own service, one message per pool
+#[derive(Clone)]
+pub struct PoolAnnouncer {
+    tx: mpsc::Sender<PoolIpcMessage>,
+}
 
-    println!("🚀 Starting IPC publisher thread");
+impl PoolAnnouncer {
+    pub fn start() -> Self {
+        let (tx, rx) = mpsc::channel::<PoolIpcMessage>();
+        thread::spawn(move || {
+            if let Err(e) = run_publisher_thread(POOL_SERVICE, rx) {
+                error!("❌ IPC pool publisher thread error: {}", e);
+            }
+        });
+        Self { tx }
+    }
+
+    pub fn announce(&self, pool: &NewPool) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let announcement = PoolAnnouncement {
+            dex: pool.dex,
+            pool: pool.pool,
+            token0: pool.token0,
+            token1: pool.token1,
+            fee: pool.fee,
+            liquidity_usdc: pool.liquidity_usdc as u64,
+        };
+
+        let msg: PoolIpcMessage = encode(&announcement)?;
+        self.tx.send(msg).map_err(|_| "IPC pool publisher thread terminated")?;
+        Ok(())
+    }
+}
+
+// Modified handle_ipc_stream to use channel. Each new trade's history row is marked
+// published once it is handed to the publisher thread.
+pub async fn handle_ipc_stream(
+    stream_results: Arc<StreamResults>,
+    history: Option<HistoryWriter>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let (tx, rx) = mpsc::channel::<IpcMessage>();
+    let (route_tx, route_rx) = mpsc::channel::<RouteIpcMessage>();
+
+    info!("🚀 Starting IPC publisher thread");
     
     // Spawn publisher thread
     let _publisher_thread = thread::spawn(move || {
-        if let Err(e) = run_publisher_thread(rx) {
-            eprintln!("❌ IPC publisher thread error: {}", e);
+        if let Err(e) = run_publisher_thread(OPPORTUNITY_SERVICE, rx) {
+            error!("❌ IPC publisher thread error: {}", e);
         }
     });
 
-    println!("📡 IPC Stream initialized and ready to transmit");
+    // Multi-hop routes go out on their own service so two-leg consumers keep working
+    let _route_publisher_thread = thread::spawn(move || {
+        if let Err(e) = run_publisher_thread(ROUTE_SERVICE, route_rx) {
+            error!("❌ IPC route publisher thread error: {}", e);
+        }
+    });
 
+    let cycle_time = std::time::Duration::from_millis(settings().ipc.cycle_ms);
+    info!("📡 IPC Stream initialized and ready to transmit");
+
+    let mut held = false;
+    let mut last_trade: Option<BestTrade> = None;
+    let mut last_route: Option<BestRoute> = None;
     loop {
+        // Nothing goes out while the latest block's prices are suspect
+        let hold = read_lock(&stream_results.oracle_hold).clone();
+        if let Some(reason) = hold {
+            if !held {
+                info!("⏸️ Publishing held: {}", reason);
+                held = true;
+            }
+            tokio::time::sleep(cycle_time).await;
+            continue;
+        }
+        if held {
+            info!("▶️ Publishing resumed");
+            held = false;
+        }
+
         let should_send = {
-            let best_trade = stream_results.best_trade.read()
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
-            
+            let best_trade = read_lock(&stream_results.best_trade);
+
             // Check if trade is non-initial state
             best_trade.profit_usdc > 0.0 || 
             best_trade.buy_fee > 0 ||
@@ -98,44 +213,104 @@ pub async fn handle_ipc_stream(stream_results: Arc<StreamResults>) -> Result<(),
         };
         // Create opportunity message inside a block to drop the read guard before await
         if should_send {
-            let msg = {
-                let best_trade = stream_results.best_trade.read()
-                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
-                
-                let mut msg = FixedSizeVec::<u8, DATA_SIZE>::new();
-                let mut slice = [0u8; DATA_SIZE];
-                
+            let (msg, new_trade) = {
+                let best_trade = read_lock(&stream_results.best_trade);
+
                 let opportunity = pack_trade_data(&best_trade);
-                bincode::encode_into_slice(&opportunity, &mut slice, bincode::config::standard())?;
-
-                println!("💹 Trade Opportunity Detected:");
-                println!("🔄 First Transaction:");
-                println!("   📍 DEX: 0x{}", hex::encode(&opportunity.first_transaction.dex));
-                println!("   💱 From: 0x{} -> To: 0x{}", 
-                    hex::encode(&opportunity.first_transaction.token_from),
-                    hex::encode(&opportunity.first_transaction.token_to));
-                println!("   💰 Amount: 0x{}", hex::encode(&opportunity.first_transaction.amount));
-                println!("   🏷️ Fee: {}", opportunity.first_transaction.fee);
-                
-                println!("🔄 Second Transaction:");
-                println!("   📍 DEX: 0x{}", hex::encode(&opportunity.second_transaction.dex));
-                println!("   💱 From: 0x{} -> To: 0x{}", 
-                    hex::encode(&opportunity.second_transaction.token_from),
-                    hex::encode(&opportunity.second_transaction.token_to));
-                println!("   💰 Amount: 0x{}", hex::encode(&opportunity.second_transaction.amount));
-                println!("   🏷️ Fee: {}", opportunity.second_transaction.fee);
-                println!("📊 Profit (USDC): {}", best_trade.profit_usdc);
-                
-                msg.extend_from_slice(&slice);
-                msg
+                let msg: IpcMessage = encode(&opportunity)?;
+
+                // Sent every cycle, reported once per new trade
+                let new_trade = last_trade.as_ref() != Some(&*best_trade);
+                if new_trade {
+                    report_trade(&best_trade, &opportunity);
+                    last_trade = Some(best_trade.clone());
+                }
+                (msg, new_trade)
             };
 
             // Send message through channel
             if tx.send(msg).is_err() {
                 return Err("IPC publisher thread terminated".into());
             }
+            let sent = if new_trade { last_trade.as_ref().and_then(|trade| trade.pair_block.clone()) } else { None };
+            if let (Some(history), Some(key)) = (&history, sent) {
+                history.published(key);
+            }
+        }
+
+        let route_msg = {
+            let best_route = read_lock(&stream_results.best_route);
+
+            if best_route.legs.is_empty() {
+                None
+            } else {
+                let opportunity = pack_route_data(&best_route);
+                let msg: RouteIpcMessage = encode(&opportunity)?;
+
+                if last_route.as_ref() != Some(&*best_route) {
+                    report_route(&best_route, &opportunity);
+                    last_route = Some(best_route.clone());
+                }
+                Some(msg)
+            }
+        };
+
+        if let Some(msg) = route_msg {
+            route_tx.send(msg).map_err(|_| "IPC route publisher thread terminated")?;
         }
-        tokio::time::sleep(IPC_CYCLE_TIME).await;
+        tokio::time::sleep(cycle_time).await;
+    }
+}
+
+// Logs a new best trade, and prints it as a JSON line with --json
+fn report_trade(trade: &BestTrade, opportunity: &Opportunity) {
+    info!(
+        target: OPPORTUNITY_TARGET,
+        profit_usdc = trade.profit_usdc,
+        buy_dex = %hex_bytes(&trade.buy_dex),
+        buy_token_in = %hex_bytes(&trade.buy_token_in),
+        buy_token_out = %hex_bytes(&trade.buy_token_out),
+        buy_fee = trade.buy_fee,
+        buy_amount = %U256::from_big_endian(&trade.buy_amount),
+        sell_dex = %hex_bytes(&trade.sell_dex),
+        sell_token_out = %hex_bytes(&trade.sell_token_out),
+        sell_fee = trade.sell_fee,
+        sell_amount = %U256::from_big_endian(&trade.sell_amount),
+        weth_usd = trade.reference.weth_usd,
+        reference_block = trade.reference.block_number,
+        "💹 Publishing trade opportunity, profit ${:.4}",
+        trade.profit_usdc
+    );
+    if json_output() && !tui_output() {
+        let mut line = opportunity.to_json();
+        line["type"] = json!("opportunity");
+        line["profit_usdc"] = json!(trade.profit_usdc);
+        println!("{}", line);
+    }
+}
+
+fn report_route(route: &BestRoute, opportunity: &MultiLegOpportunity) {
+    let path = route
+        .legs
+        .iter()
+        .map(|leg| format!("{} {} -> {}", hex_bytes(&leg.dex), hex_bytes(&leg.token_in), hex_bytes(&leg.token_out)))
+        .collect::<Vec<_>>()
+        .join(" | ");
+    info!(
+        target: OPPORTUNITY_TARGET,
+        profit_usdc = route.profit_usdc,
+        legs = route.legs.len(),
+        path = %path,
+        weth_usd = route.reference.weth_usd,
+        reference_block = route.reference.block_number,
+        "🔺 Publishing route opportunity, profit ${:.4}",
+        route.profit_usdc
+    );
+    if json_output() && !tui_output() {
+        let mut line = opportunity.to_json();
+        line["type"] = json!("route");
+        line["profit_usdc"] = json!(route.profit_usdc);
+        println!("{}", line);
     }
 }
 
@@ -155,6 +330,38 @@ fn pack_trade_data(trade: &BestTrade) -> Opportunity {
             token_to: trade.sell_token_out,
             fee: trade.sell_fee,
             amount: trade.sell_amount,
-        }
+        },
+        reference: pack_reference(&trade.reference),
+    }
+}
+
+fn pack_reference(reference: &PriceReference) -> WethUsdReference {
+    let now_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    WethUsdReference {
+        weth_usd: reference.weth_usd,
+        method: reference.method,
+        pool_count: reference.pool_count,
+        block_number: reference.block_number,
+        age_ms: now_ms.saturating_sub(reference.updated_at_ms),
+        oracle_usd: reference.oracle_usd,
+    }
+}
+
+fn pack_route_data(route: &BestRoute) -> MultiLegOpportunity {
+    let mut opportunity = MultiLegOpportunity::default();
+    for (slot, leg) in opportunity.legs.iter_mut().zip(&route.legs) {
+        *slot = ArbTran {
+            dex: leg.dex,
+            token_from: leg.token_in,
+            token_to: leg.token_out,
+            fee: leg.fee,
+            amount: leg.amount,
+        };
     }
+    opportunity.leg_count = route.legs.len().min(MAX_ROUTE_HOPS) as u8;
+    opportunity.reference = pack_reference(&route.reference);
+    opportunity
 }
\ No newline at end of file
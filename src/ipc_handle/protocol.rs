@@ -0,0 +1,321 @@
+// Wire format of the scanner's iceoryx2 services. Each sample is a FixedSizeVec of the
+// message's size holding the bincode (standard config) encoding of one message.
+use bincode::{Decode, Encode};
+use ethers::prelude::U256;
+use iceoryx2_bb_container::vec::FixedSizeVec;
+use serde_json::{Value, json};
+
+use crate::config::MAX_ROUTE_HOPS;
+
+// iceoryx2 services the scanner publishes on
+pub const OPPORTUNITY_SERVICE: &str = "arbiscan_bot";
+pub const ROUTE_SERVICE: &str = "arbiscan_routes";
+pub const POOL_SERVICE: &str = "arbiscan_pools";
+
+#[derive(Encode, Decode, Debug, Clone, Default)]
+#[repr(C)]
+pub struct ArbTran {
+    pub dex: [u8; 20],
+    pub token_from: [u8; 20],
+    pub token_to: [u8; 20],
+    pub fee: u32,
+    pub amount: [u8; 32],
+}
+
+// method: 0 liquidity-weighted, 1 median; age_ms is measured when the message is sent
+#[derive(Encode, Decode, Debug, Clone, Default)]
+#[repr(C)]
+pub struct WethUsdReference {
+    pub weth_usd: f64,
+    pub method: u8,
+    pub pool_count: u8,
+    pub block_number: u64,
+    pub age_ms: u64,
+    pub oracle_usd: f64,
+}
+
+#[derive(Encode, Decode, Debug, Clone, Default)]
+#[repr(C)]
+pub struct Opportunity {
+    pub first_transaction: ArbTran,
+    pub second_transaction: ArbTran,
+    pub reference: WethUsdReference,
+}
+
+// Multi-hop route; only the first leg_count legs are used
+#[derive(Encode, Decode, Debug, Clone, Default)]
+#[repr(C)]
+pub struct MultiLegOpportunity {
+    pub leg_count: u8,
+    pub legs: [ArbTran; MAX_ROUTE_HOPS],
+    pub reference: WethUsdReference,
+}
+
+#[derive(Encode, Decode, Debug, Clone, Default)]
+#[repr(C)]
+pub struct PoolAnnouncement {
+    pub dex: [u8; 20],
+    pub pool: [u8; 20],
+    pub token0: [u8; 20],
+    pub token1: [u8; 20],
+    pub fee: u32,
+    pub liquidity_usdc: u64,
+}
+
+pub fn hex_bytes(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+impl ArbTran {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "dex": hex_bytes(&self.dex),
+            "token_from": hex_bytes(&self.token_from),
+            "token_to": hex_bytes(&self.token_to),
+            "fee": self.fee,
+            "amount": U256::from_big_endian(&self.amount).to_string(),
+        })
+    }
+}
+
+impl WethUsdReference {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "weth_usd": self.weth_usd,
+            "method": self.method,
+            "pool_count": self.pool_count,
+            "block_number": self.block_number,
+            "age_ms": self.age_ms,
+            "oracle_usd": self.oracle_usd,
+        })
+    }
+
+    pub fn print(&self) {
+        println!("💵 WETH/USD: {:.2} from #{} ({} ms old)", self.weth_usd, self.block_number, self.age_ms);
+    }
+}
+
+impl Opportunity {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "first_transaction": self.first_transaction.to_json(),
+            "second_transaction": self.second_transaction.to_json(),
+            "reference": self.reference.to_json(),
+        })
+    }
+
+    pub fn print(&self) {
+        for (label, transaction) in [("First", &self.first_transaction), ("Second", &self.second_transaction)] {
+            println!("🔄 {} Transaction:", label);
+            println!("   📍 DEX: {}", hex_bytes(&transaction.dex));
+            println!("   💱 From: {} -> To: {}", hex_bytes(&transaction.token_from), hex_bytes(&transaction.token_to));
+            println!("   💰 Amount: {}", hex_bytes(&transaction.amount));
+            println!("   🏷️ Fee: {}", transaction.fee);
+        }
+    }
+}
+
+impl MultiLegOpportunity {
+    pub fn legs(&self) -> &[ArbTran] {
+        &self.legs[..(self.leg_count as usize).min(MAX_ROUTE_HOPS)]
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "legs": self.legs().iter().map(ArbTran::to_json).collect::<Vec<_>>(),
+            "reference": self.reference.to_json(),
+        })
+    }
+
+    pub fn print(&self) {
+        for (i, leg) in self.legs().iter().enumerate() {
+            println!("   {}. 📍 DEX: {} | 💱 {} -> {} | 💰 {} | 🏷️ Fee: {}",
+                i + 1,
+                hex_bytes(&leg.dex),
+                hex_bytes(&leg.token_from),
+                hex_bytes(&leg.token_to),
+                hex_bytes(&leg.amount),
+                leg.fee);
+        }
+    }
+}
+
+impl PoolAnnouncement {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "dex": hex_bytes(&self.dex),
+            "pool": hex_bytes(&self.pool),
+            "token0": hex_bytes(&self.token0),
+            "token1": hex_bytes(&self.token1),
+            "fee": self.fee,
+            "liquidity_usdc": self.liquidity_usdc,
+        })
+    }
+
+    pub fn print(&self) {
+        println!("🆕 Pool {} on DEX {} | {} / {} | 🏷️ Fee: {} | 💧 ${}",
+            hex_bytes(&self.pool),
+            hex_bytes(&self.dex),
+            hex_bytes(&self.token0),
+            hex_bytes(&self.token1),
+            self.fee,
+            self.liquidity_usdc);
+    }
+}
+
+// Message type for the channel
+pub const DATA_SIZE: usize = std::mem::size_of::<Opportunity>();
+pub const ROUTE_DATA_SIZE: usize = std::mem::size_of::<MultiLegOpportunity>();
+
+pub type IpcMessage = FixedSizeVec<u8, DATA_SIZE>;
+pub type RouteIpcMessage = FixedSizeVec<u8, ROUTE_DATA_SIZE>;
+
+pub const POOL_DATA_SIZE: usize = std::mem::size_of::<PoolAnnouncement>();
+pub type PoolIpcMessage = FixedSizeVec<u8, POOL_DATA_SIZE>;
+
+// Encodes a message into a sample payload of N bytes
+pub fn encode<T: Encode, const N: usize>(message: &T) -> Result<FixedSizeVec<u8, N>, bincode::error::EncodeError> {
+    let mut slice = [0u8; N];
+    bincode::encode_into_slice(message, &mut slice, bincode::config::standard())?;
+    let mut payload = FixedSizeVec::<u8, N>::new();
+    payload.extend_from_slice(&slice);
+    Ok(payload)
+}
+
+pub fn decode<T: Decode<()>>(payload: &[u8]) -> Result<T, bincode::error::DecodeError> {
+    bincode::decode_from_slice(payload, bincode::config::standard()).map(|(message, _)| message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(seed: u8, fee: u32) -> ArbTran {
+        let mut amount = [0u8; 32];
+        amount[31] = seed;
+        ArbTran { dex: [seed; 20], token_from: [seed + 1; 20], token_to: [seed + 2; 20], fee, amount }
+    }
+
+    fn reference() -> WethUsdReference {
+        WethUsdReference {
+            weth_usd: 3000.5,
+            method: 1,
+            pool_count: 4,
+            block_number: 250_000_000,
+            age_ms: 120,
+            oracle_usd: 3001.25,
+        }
+    }
+
+    // The bytes other processes expect for transaction(seed, _) with the fee's varint
+    fn transaction_bytes(seed: u8, fee: &[u8]) -> Vec<u8> {
+        let mut bytes = [[seed; 20], [seed + 1; 20], [seed + 2; 20]].concat();
+        bytes.extend_from_slice(fee);
+        bytes.extend_from_slice(&[0; 31]);
+        bytes.push(seed);
+        bytes
+    }
+
+    fn reference_bytes() -> Vec<u8> {
+        let mut bytes = 3000.5f64.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&[1, 4]);
+        // Block 250_000_000 is a u32 varint, the age a single byte
+        bytes.extend_from_slice(&[252, 0x80, 0xB2, 0xE6, 0x0E, 120]);
+        bytes.extend_from_slice(&3001.25f64.to_le_bytes());
+        bytes
+    }
+
+    // The payload is the expected bytes, zero-padded to N
+    fn assert_payload<const N: usize>(payload: &FixedSizeVec<u8, N>, expected: &[u8]) {
+        assert_eq!(payload.len(), N);
+        assert_eq!(&payload[..expected.len()], expected);
+        assert!(payload[expected.len()..].iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn payload_sizes_are_pinned() {
+        // Subscribers in other processes open the services with these sizes
+        assert_eq!((DATA_SIZE, ROUTE_DATA_SIZE, POOL_DATA_SIZE), (232, 336, 96));
+
+        // The largest varints still fit
+        let transaction = ArbTran { fee: u32::MAX, ..transaction(1, 0) };
+        let reference = WethUsdReference { block_number: u64::MAX, age_ms: u64::MAX, ..reference() };
+        let opportunity = Opportunity {
+            first_transaction: transaction.clone(),
+            second_transaction: transaction.clone(),
+            reference: reference.clone(),
+        };
+        encode::<_, DATA_SIZE>(&opportunity).unwrap();
+        let route = MultiLegOpportunity {
+            leg_count: MAX_ROUTE_HOPS as u8,
+            legs: std::array::from_fn(|_| transaction.clone()),
+            reference,
+        };
+        encode::<_, ROUTE_DATA_SIZE>(&route).unwrap();
+        let pool = PoolAnnouncement { fee: u32::MAX, liquidity_usdc: u64::MAX, ..PoolAnnouncement::default() };
+        encode::<_, POOL_DATA_SIZE>(&pool).unwrap();
+    }
+
+    #[test]
+    fn opportunity_round_trip() {
+        let opportunity = Opportunity {
+            first_transaction: transaction(1, 500),
+            second_transaction: transaction(4, 3000),
+            reference: reference(),
+        };
+        let payload = encode::<_, DATA_SIZE>(&opportunity).unwrap();
+        let expected = [
+            transaction_bytes(1, &[251, 0xF4, 0x01]),
+            transaction_bytes(4, &[251, 0xB8, 0x0B]),
+            reference_bytes(),
+        ]
+        .concat();
+        assert_payload(&payload, &expected);
+
+        let decoded: Opportunity = decode(&payload).unwrap();
+        assert_eq!(decoded.to_json(), opportunity.to_json());
+    }
+
+    #[test]
+    fn multi_leg_opportunity_round_trip() {
+        let route = MultiLegOpportunity {
+            leg_count: 2,
+            legs: [transaction(1, 500), transaction(4, 100), ArbTran::default()],
+            reference: reference(),
+        };
+        let payload = encode::<_, ROUTE_DATA_SIZE>(&route).unwrap();
+        // Unused legs are still sent, as zeros
+        let expected = [
+            vec![2],
+            transaction_bytes(1, &[251, 0xF4, 0x01]),
+            transaction_bytes(4, &[100]),
+            vec![0; 93],
+            reference_bytes(),
+        ]
+        .concat();
+        assert_payload(&payload, &expected);
+
+        let decoded: MultiLegOpportunity = decode(&payload).unwrap();
+        assert_eq!(decoded.legs().len(), 2);
+        assert_eq!(decoded.to_json(), route.to_json());
+    }
+
+    #[test]
+    fn pool_announcement_round_trip() {
+        let pool = PoolAnnouncement {
+            dex: [1; 20],
+            pool: [2; 20],
+            token0: [3; 20],
+            token1: [4; 20],
+            fee: 500,
+            liquidity_usdc: 250_000,
+        };
+        let payload = encode::<_, POOL_DATA_SIZE>(&pool).unwrap();
+        let mut expected = [[1u8; 20], [2; 20], [3; 20], [4; 20]].concat();
+        expected.extend_from_slice(&[251, 0xF4, 0x01, 252, 0x90, 0xD0, 0x03, 0x00]);
+        assert_payload(&payload, &expected);
+
+        let decoded: PoolAnnouncement = decode(&payload).unwrap();
+        assert_eq!(decoded.to_json(), pool.to_json());
+    }
+}
@@ -0,0 +1,94 @@
+use iceoryx2::prelude::*;
+use iceoryx2_bb_container::vec::FixedSizeVec;
+use std::time::Duration;
+
+use super::protocol::{
+    DATA_SIZE, MultiLegOpportunity, OPPORTUNITY_SERVICE, Opportunity, POOL_DATA_SIZE, POOL_SERVICE,
+    PoolAnnouncement, ROUTE_DATA_SIZE, ROUTE_SERVICE, decode,
+};
+
+// How long to wait before polling again when no message is queued
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+// One of the services the scanner publishes on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpcStream {
+    Opportunities,
+    Routes,
+    Pools,
+}
+
+impl IpcStream {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "opportunities" => Some(IpcStream::Opportunities),
+            "routes" => Some(IpcStream::Routes),
+            "pools" => Some(IpcStream::Pools),
+            _ => None,
+        }
+    }
+
+    pub fn service(&self) -> &'static str {
+        match self {
+            IpcStream::Opportunities => OPPORTUNITY_SERVICE,
+            IpcStream::Routes => ROUTE_SERVICE,
+            IpcStream::Pools => POOL_SERVICE,
+        }
+    }
+}
+
+// Prints every message published on the stream, one JSON object per line with json
+pub fn run_subscriber(stream: IpcStream, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    match stream {
+        IpcStream::Opportunities => subscribe::<Opportunity, DATA_SIZE>(stream, |opportunity| {
+            if json {
+                println!("{}", opportunity.to_json());
+                return;
+            }
+            println!("💹 Trade Opportunity Received:");
+            opportunity.print();
+            opportunity.reference.print();
+        }),
+        IpcStream::Routes => subscribe::<MultiLegOpportunity, ROUTE_DATA_SIZE>(stream, |route| {
+            if json {
+                println!("{}", route.to_json());
+                return;
+            }
+            println!("🔺 Route Opportunity Received ({} legs):", route.leg_count);
+            route.print();
+            route.reference.print();
+        }),
+        IpcStream::Pools => subscribe::<PoolAnnouncement, POOL_DATA_SIZE>(stream, |pool| {
+            if json {
+                println!("{}", pool.to_json());
+                return;
+            }
+            pool.print();
+        }),
+    }
+}
+
+fn subscribe<T: bincode::Decode<()>, const N: usize>(
+    stream: IpcStream,
+    handle: impl Fn(T),
+) -> Result<(), Box<dyn std::error::Error>> {
+    let node = NodeBuilder::new().create::<ipc::Service>()?;
+    let service = node
+        .service_builder(&stream.service().try_into()?)
+        .publish_subscribe::<FixedSizeVec<u8, N>>()
+        .open_or_create()?;
+    let subscriber = service.subscriber_builder().create()?;
+
+    eprintln!("📡 Subscribed to {}", stream.service());
+
+    loop {
+        let Some(sample) = subscriber.receive()? else {
+            std::thread::sleep(POLL_INTERVAL);
+            continue;
+        };
+        match decode::<T>(sample.payload()) {
+            Ok(message) => handle(message),
+            Err(e) => eprintln!("Failed to decode message from {}: {}", stream.service(), e),
+        }
+    }
+}
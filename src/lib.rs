@@ -0,0 +1,38 @@
+// Scanner library: exchange adapters, simulators, the IPC and shared memory formats and
+// config, shared by the Scanner_Bot binary, the helper binaries and other services
+pub mod config;
+pub mod exchange;
+pub mod websocket;
+pub mod shm;
+pub mod trade;
+pub mod utils;
+pub mod ipc_handle;
+pub mod pool_state;
+pub mod quote_service;
+pub mod recorder;
+pub mod backtest;
+pub mod rpc_replay;
+// Scripted node for end-to-end runs, left out of release builds
+#[cfg(feature = "mock-node")]
+pub mod mock_node;
+pub mod routes;
+pub mod discover;
+pub mod pricing;
+pub mod reference_price;
+pub mod oracle;
+pub mod camelot_fees;
+pub mod depth;
+pub mod tiers;
+pub mod settings;
+pub mod cli;
+pub mod error;
+pub mod failures;
+pub mod metrics;
+pub mod http;
+pub mod logging;
+pub mod fees;
+pub mod history;
+pub mod export;
+pub mod live;
+pub mod api;
+pub mod tui;
@@ -0,0 +1,200 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+
+use crate::config::{LIVE_EVENT_CHANNEL_CAPACITY, LIVE_OPPORTUNITIES_LEN};
+use crate::error::{lock, read_lock, write_lock};
+use crate::history::{Outcome, PairBlockRecord};
+
+// Latest evaluation of one pool pair
+#[derive(Debug, Clone, Serialize)]
+pub struct PairSpread {
+    pub block_number: u64,
+    pub timestamp_ms: u64,
+    pub monitor: String,
+    pub pair: String,
+    pub dex_a: &'static str,
+    pub pool_a: String,
+    pub price_a: f64,
+    pub dex_b: &'static str,
+    pub pool_b: String,
+    pub price_b: f64,
+    // Fraction, 0.01 is 1%
+    pub spread: f64,
+    pub min_liquidity_usd: f64,
+    pub depth_usd: f64,
+    pub tier: Option<u8>,
+    pub best_amount_usd: Option<f64>,
+    pub best_profit_usd: Option<f64>,
+    pub outcome: &'static str,
+    pub suspect_reason: Option<String>,
+}
+
+impl From<&PairBlockRecord> for PairSpread {
+    fn from(record: &PairBlockRecord) -> Self {
+        let best = record.best();
+        Self {
+            block_number: record.block_number,
+            timestamp_ms: record.timestamp_ms,
+            monitor: record.monitor.clone(),
+            pair: record.pair_name.clone(),
+            dex_a: record.dex_a,
+            pool_a: record.pool_a.clone(),
+            price_a: record.price_a,
+            dex_b: record.dex_b,
+            pool_b: record.pool_b.clone(),
+            price_b: record.price_b,
+            spread: record.spread,
+            min_liquidity_usd: record.min_liquidity_usd,
+            depth_usd: record.depth_usd,
+            tier: record.tier,
+            best_amount_usd: best.map(|b| b.amount_usd),
+            best_profit_usd: best.map(|b| b.profit_usd),
+            outcome: record.outcome.name(),
+            suspect_reason: record.suspect_reason.clone(),
+        }
+    }
+}
+
+// How a monitor's latest block went
+#[derive(Debug, Clone, Serialize)]
+pub struct MonitorStatus {
+    pub monitor: String,
+    pub block_number: u64,
+    // When the block's scan finished, and how long it took
+    pub finished_ms: u64,
+    pub duration_ms: u64,
+    pub pairs: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveEvent {
+    // Every pool pair a monitor evaluated on a block, sent when the block is done
+    Spreads { monitor: String, block_number: u64, spreads: Vec<PairSpread> },
+    // A profitable pair as soon as it is found, whether it was selected, outbid or suspect
+    Opportunity(Box<PairSpread>),
+}
+
+// What the monitors saw last, for the API and anything else that wants a live view: the
+// latest spread of every pool pair, the last LIVE_OPPORTUNITIES_LEN profitable ones and
+// each monitor's last block. Changes are also sent to subscribers as they happen. Pairs
+// paused here are skipped by the pair monitors until resumed.
+pub struct LiveBoard {
+    // Keyed by "pool_a-pool_b"
+    spreads: RwLock<HashMap<String, PairSpread>>,
+    // Newest last
+    opportunities: RwLock<VecDeque<PairSpread>>,
+    monitors: RwLock<HashMap<String, MonitorStatus>>,
+    // Spreads of the block each monitor is on, sent together when it ends
+    pending: Mutex<HashMap<String, Vec<PairSpread>>>,
+    events: broadcast::Sender<LiveEvent>,
+    // Pair names
+    paused: RwLock<HashSet<String>>,
+}
+
+impl Default for LiveBoard {
+    fn default() -> Self {
+        Self {
+            spreads: RwLock::new(HashMap::new()),
+            opportunities: RwLock::new(VecDeque::new()),
+            monitors: RwLock::new(HashMap::new()),
+            pending: Mutex::new(HashMap::new()),
+            events: broadcast::channel(LIVE_EVENT_CHANNEL_CAPACITY).0,
+            paused: RwLock::new(HashSet::new()),
+        }
+    }
+}
+
+impl LiveBoard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, record: &PairBlockRecord) {
+        let spread = PairSpread::from(record);
+        if matches!(record.outcome, Outcome::Selected | Outcome::Outbid | Outcome::Suspect) {
+            let mut opportunities = write_lock(&self.opportunities);
+            if opportunities.len() == LIVE_OPPORTUNITIES_LEN {
+                opportunities.pop_front();
+            }
+            opportunities.push_back(spread.clone());
+            drop(opportunities);
+            // Nobody listening is fine
+            let _ = self.events.send(LiveEvent::Opportunity(Box::new(spread.clone())));
+        }
+        lock(&self.pending).entry(spread.monitor.clone()).or_default().push(spread.clone());
+        write_lock(&self.spreads).insert(format!("{}-{}", spread.pool_a, spread.pool_b), spread);
+    }
+
+    // Called once a monitor has been through every pair of the block
+    pub fn end_block(&self, monitor: &str, block_number: u64, duration: Duration) {
+        let spreads = lock(&self.pending).remove(monitor).unwrap_or_default();
+        let status = MonitorStatus {
+            monitor: monitor.to_string(),
+            block_number,
+            finished_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            duration_ms: duration.as_millis() as u64,
+            pairs: spreads.len(),
+        };
+        write_lock(&self.monitors).insert(monitor.to_string(), status);
+        let _ = self.events.send(LiveEvent::Spreads { monitor: monitor.to_string(), block_number, spreads });
+    }
+
+    // By pair, then pool pairing
+    pub fn spreads(&self) -> Vec<PairSpread> {
+        let mut spreads: Vec<PairSpread> = read_lock(&self.spreads).values().cloned().collect();
+        spreads.sort_by(|a, b| {
+            a.pair
+                .cmp(&b.pair)
+                .then_with(|| a.pool_a.cmp(&b.pool_a))
+                .then_with(|| a.pool_b.cmp(&b.pool_b))
+        });
+        spreads
+    }
+
+    // Newest first
+    pub fn opportunities(&self) -> Vec<PairSpread> {
+        read_lock(&self.opportunities).iter().rev().cloned().collect()
+    }
+
+    pub fn monitors(&self) -> Vec<MonitorStatus> {
+        let mut monitors: Vec<MonitorStatus> = read_lock(&self.monitors).values().cloned().collect();
+        monitors.sort_by(|a, b| a.monitor.cmp(&b.monitor));
+        monitors
+    }
+
+    pub fn is_paused(&self, pair_name: &str) -> bool {
+        read_lock(&self.paused).contains(pair_name)
+    }
+
+    // Returns whether the pair is paused now
+    pub fn toggle_pause(&self, pair_name: &str) -> bool {
+        let mut paused = write_lock(&self.paused);
+        if paused.remove(pair_name) {
+            false
+        } else {
+            paused.insert(pair_name.to_string());
+            true
+        }
+    }
+
+    pub fn resume_all(&self) {
+        write_lock(&self.paused).clear();
+    }
+
+    pub fn paused_count(&self) -> usize {
+        read_lock(&self.paused).len()
+    }
+
+    // Every event from now on. A receiver that falls more than
+    // LIVE_EVENT_CHANNEL_CAPACITY events behind skips the oldest.
+    pub fn subscribe(&self) -> broadcast::Receiver<LiveEvent> {
+        self.events.subscribe()
+    }
+}
@@ -0,0 +1,65 @@
+use std::path::Path;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{EnvFilter, fmt};
+
+use crate::settings::LoggingSettings;
+
+// Targets of the records behind the price_liquidity and simulation switches, so they can
+// be filtered on their own, e.g. RUST_LOG=info,simulation=off
+pub const PRICE_LIQUIDITY_TARGET: &str = "price_liquidity";
+pub const SIMULATION_TARGET: &str = "simulation";
+
+pub fn rotation(name: &str) -> Option<Rotation> {
+    match name {
+        "minutely" => Some(Rotation::MINUTELY),
+        "hourly" => Some(Rotation::HOURLY),
+        "daily" => Some(Rotation::DAILY),
+        "never" => Some(Rotation::NEVER),
+        _ => None,
+    }
+}
+
+// Installs the global subscriber. The console gets human-readable lines on stderr so
+// `--json` output on stdout stays parseable; the file gets JSON lines, one per event plus
+// one per closed span with its busy and idle time, which is where the block, pair, fetch
+// and quote timings end up. RUST_LOG overrides the configured level. Keep the returned
+// guard until exit, it flushes the file writer when dropped.
+pub fn init(logging: &LoggingSettings) -> Result<Option<WorkerGuard>, Box<dyn std::error::Error>> {
+    let filter = match EnvFilter::try_from_default_env() {
+        Ok(filter) => filter,
+        Err(_) => EnvFilter::try_new(&logging.level)?,
+    };
+    let console = logging
+        .console
+        .then(|| fmt::layer().with_writer(std::io::stderr).with_target(false));
+
+    let (file, guard) = if logging.file.is_empty() {
+        (None, None)
+    } else {
+        let path = Path::new(&logging.file);
+        let directory = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let prefix = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| format!("logging.file: '{}' has no file name", logging.file))?;
+        let rotation = rotation(&logging.rotation).ok_or_else(|| format!("logging.rotation: unknown '{}'", logging.rotation))?;
+        let appender = RollingFileAppender::builder()
+            .rotation(rotation)
+            .filename_prefix(prefix)
+            .build(directory)?;
+        let (writer, guard) = tracing_appender::non_blocking(appender);
+        let layer = fmt::layer()
+            .json()
+            .with_span_events(FmtSpan::CLOSE)
+            .with_current_span(true)
+            .with_span_list(true)
+            .with_writer(writer);
+        (Some(layer), Some(guard))
+    };
+
+    tracing_subscriber::registry().with(filter).with(console).with(file).try_init()?;
+    Ok(guard)
+}
@@ -1,32 +1,215 @@
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
 use dotenv::dotenv;
+use ethers::providers::Provider;
+use tokio::sync::oneshot;
+use tracing::info;
 
-mod config;
-mod exchange;
-mod websocket;
-mod shm;
-mod trade;
-mod utils;
-mod ipc_handle;
-use websocket::create_ws_provider;
-use websocket::stream_handler::handle_dex_streams;
-use shm::SharedMemoryManager;
-
-async fn init() -> Result<(), Box<dyn std::error::Error>> {
-    dotenv().ok();
+use scanner_bot::{backtest, cli, config, discover, export, history, logging, settings, tiers, tui, websocket};
+#[cfg(feature = "mock-node")]
+use scanner_bot::mock_node;
+use scanner_bot::cli::{Cli, Command, print_json};
+use scanner_bot::ipc_handle::subscriber::run_subscriber;
+use scanner_bot::live::LiveBoard;
+use scanner_bot::metrics::rpc::MeteredClient;
+use scanner_bot::rpc_replay::{FixtureWriter, ReplayClient};
+use scanner_bot::shm::SharedMemoryManager;
+use scanner_bot::websocket::{create_recording_ws_provider, create_ws_provider};
+use scanner_bot::websocket::block_source::BlockSource;
+use scanner_bot::websocket::stream_handler::{PairCategories, handle_dex_streams};
+
+async fn scan(config_path: Option<String>, dashboard: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(config_path) = config_path {
+        info!("⚙️ Loaded config from {}", config_path);
+        settings::watch(config_path);
+    }
+    tiers::log_tier_table(&tiers::tier_table());
     // Initialize shared memory (10MB should be enough for pool data)
-    let ws_provider = create_ws_provider().await?;
     let (categories, total_pools) = websocket::load_pair_categories()?;
-    let shm_manager = SharedMemoryManager::new("/tmp/pool_data.shm", total_pools)?;
+    let shm_manager = SharedMemoryManager::new(config::SHM_PATH, total_pools)?;
     let shm_manager = Arc::new(RwLock::new(shm_manager));
-    handle_dex_streams(ws_provider, categories, shm_manager).await?;
+
+    if !dashboard {
+        return scan_blocks(categories, shm_manager, None, None).await;
+    }
+    // The dashboard ends the scan when it is closed. The terminal is in raw mode, so ctrl-c
+    // only reaches the dashboard; the scan is asked to stop and awaited so it still closes
+    // the history and export writers. A scan that fails closes the dashboard first so the
+    // error lands on a restored terminal.
+    let board = Arc::new(LiveBoard::new());
+    let stop = Arc::new(AtomicBool::new(false));
+    let mut dashboard = {
+        let board = board.clone();
+        let stop = stop.clone();
+        tokio::task::spawn_blocking(move || tui::run(board, stop))
+    };
+    let (shutdown, shutdown_requested) = oneshot::channel();
+    let scan = scan_blocks(categories, shm_manager, Some(board), Some(shutdown_requested));
+    tokio::pin!(scan);
+    tokio::select! {
+        closed = &mut dashboard => {
+            let _ = shutdown.send(());
+            let result = scan.await;
+            closed??;
+            result
+        }
+        result = &mut scan => {
+            stop.store(true, Ordering::Relaxed);
+            dashboard.await??;
+            result
+        }
+    }
+}
+
+async fn scan_blocks(
+    categories: PairCategories,
+    shm_manager: Arc<RwLock<SharedMemoryManager>>,
+    live: Option<Arc<LiveBoard>>,
+    shutdown: Option<oneshot::Receiver<()>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // RPC_REPLAY_FILE runs the scanner against a fixture instead of a node,
+    // RPC_RECORD_FILE records a live session into one
+    if let Ok(replay_path) = std::env::var("RPC_REPLAY_FILE") {
+        let replay = ReplayClient::load(&replay_path)?;
+        let block_source = BlockSource::Replay(replay.block_numbers().to_vec());
+        let provider = Arc::new(Provider::new(MeteredClient::new(replay, "replay")));
+        handle_dex_streams(provider, block_source, categories, shm_manager, live, shutdown).await?;
+    } else if let Ok(record_path) = std::env::var("RPC_RECORD_FILE") {
+        let fixture = FixtureWriter::new(&record_path)?;
+        let ws_provider = create_recording_ws_provider(fixture.clone()).await?;
+        let block_source = BlockSource::live(Some(fixture)).await?;
+        handle_dex_streams(ws_provider, block_source, categories, shm_manager, live, shutdown).await?;
+    } else {
+        let ws_provider = create_ws_provider().await?;
+        let block_source = BlockSource::live(None).await?;
+        handle_dex_streams(ws_provider, block_source, categories, shm_manager, live, shutdown).await?;
+    }
     Ok(())
 }
 
+#[cfg(feature = "mock-node")]
+async fn run_mock_node(state_path: &str, block_interval_ms: u64, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let state = mock_node::MockChainState::load(state_path)?;
+    let bind_addr = std::env::var("MOCK_NODE_ADDR").unwrap_or_else(|_| "127.0.0.1:8548".to_string());
+    let node = mock_node::MockNode::start_on(&bind_addr, state, 1).await?;
+    if json {
+        println!("{}", serde_json::json!({ "event": "listening", "ws_url": node.ws_url() }));
+    } else {
+        println!("Set WS_RPC_URL={} to scan against it", node.ws_url());
+    }
+
+    let mut interval = tokio::time::interval(std::time::Duration::from_millis(block_interval_ms));
+    loop {
+        interval.tick().await;
+        node.mine_block();
+        if json {
+            println!("{}", serde_json::json!({ "event": "block", "block_number": node.block_number() }));
+        }
+    }
+}
+
+#[cfg(not(feature = "mock-node"))]
+async fn run_mock_node(_state_path: &str, _block_interval_ms: u64, _json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    Err("this build has no mock node; rebuild with --features mock-node".into())
+}
+
+async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
+    // The mock node serves its own state file and needs no settings
+    if let Command::MockNode { state, block_interval_ms } = &cli.command {
+        // Its own messages go to the console only
+        let _log_guard = logging::init(&settings::LoggingSettings { file: String::new(), ..Default::default() })?;
+        return run_mock_node(state, *block_interval_ms, cli.json).await;
+    }
+    let config_path = settings::init(cli.config.as_deref())?;
+    // Flushes the JSON log file when dropped at the end of the command. The dashboard
+    // owns the terminal, so only the log file gets lines then.
+    let mut logging_settings = settings::settings().logging.clone();
+    logging_settings.console &= !cli.tui;
+    let _log_guard = logging::init(&logging_settings)?;
+
+    match cli.command {
+        Command::Scan => scan(config_path, cli.tui).await,
+        Command::Discover { token_list, output } => {
+            let provider = create_ws_provider().await?;
+            let summary = discover::run_discover(provider, token_list.as_deref(), &output).await?;
+            if cli.json {
+                return print_json(&summary);
+            }
+            summary.print();
+            Ok(())
+        }
+        Command::Quote { pair, amount_usd } => {
+            let provider = create_ws_provider().await?;
+            let quote = cli::quote::quote_pair(&pair, amount_usd, provider).await?;
+            if cli.json {
+                return print_json(&quote);
+            }
+            cli::quote::print_quote(&quote);
+            Ok(())
+        }
+        Command::InspectPool { pool, dex } => {
+            let provider = create_ws_provider().await?;
+            let inspection = cli::inspect::inspect_pool(&pool, dex.as_deref(), provider).await?;
+            if cli.json {
+                return print_json(&inspection);
+            }
+            cli::inspect::print_inspection(&inspection);
+            Ok(())
+        }
+        Command::Replay { recording, report } => {
+            let backtest = backtest::run_backtest(&recording, &report)?;
+            if cli.json {
+                return print_json(&backtest);
+            }
+            backtest::print_report(&backtest);
+            println!("Full report written to {}", report);
+            Ok(())
+        }
+        Command::Subscribe { stream } => run_subscriber(stream, cli.json),
+        Command::History { report, limit } => {
+            let database = settings::settings().history.database.clone();
+            if database.is_empty() {
+                return Err("No history database; set [history] database or HISTORY_DB".into());
+            }
+            let rows = history::report::run_report(&database, report, limit).map_err(|e| e.to_string())?;
+            if cli.json {
+                return print_json(&rows);
+            }
+            history::report::print_report(&rows);
+            Ok(())
+        }
+        Command::ReadExport { file } => {
+            let summary = export::summarize_export(&file).map_err(|e| e.to_string())?;
+            if cli.json {
+                return print_json(&summary);
+            }
+            export::print_export_summary(&summary);
+            Ok(())
+        }
+        Command::MockNode { .. } | Command::Help => Ok(()),
+    }
+}
+
 #[tokio::main]
 async fn main() {
-    if let Err(e) = init().await {
-        eprintln!("Error during initialization: {}", e);
+    dotenv().ok();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let cli = match Cli::parse(&args) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if cli.command == Command::Help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+    cli::set_json_output(cli.json);
+    cli::set_tui_output(cli.tui);
+
+    if let Err(e) = run(cli).await {
+        eprintln!("Error: {}", e);
         std::process::exit(1);
     }
-}
\ No newline at end of file
+}
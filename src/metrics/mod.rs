@@ -0,0 +1,291 @@
+pub mod rpc;
+
+use lazy_static::lazy_static;
+use prometheus::{
+    Encoder, Gauge, GaugeVec, HistogramVec, IntCounterVec, IntGauge, TextEncoder, register_gauge,
+    register_gauge_vec, register_histogram_vec, register_int_counter_vec, register_int_gauge,
+};
+use prometheus::core::Collector;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tracing::{info, warn};
+
+use crate::config::ACCEPT_RETRY_DELAY;
+use crate::error::lock;
+use crate::http::{http_response, read_request};
+
+// Block processing and RPC latencies run from a few ms to several seconds on a busy node
+const LATENCY_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+// Handing a sample to iceoryx2 takes microseconds
+const IPC_BUCKETS: &[f64] = &[0.000_01, 0.000_05, 0.000_1, 0.000_5, 0.001, 0.005, 0.01, 0.05];
+
+lazy_static! {
+    pub static ref BLOCK_SECONDS: HistogramVec = register_histogram_vec!(
+        "scanner_block_processing_seconds",
+        "Time from a new block to the end of its scan, by monitor or route scan",
+        &["task"],
+        LATENCY_BUCKETS.to_vec()
+    )
+    .unwrap();
+    pub static ref RPC_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "scanner_rpc_requests_total",
+        "JSON-RPC requests sent, by method and endpoint host",
+        &["method", "endpoint"]
+    )
+    .unwrap();
+    pub static ref RPC_ERRORS: IntCounterVec = register_int_counter_vec!(
+        "scanner_rpc_errors_total",
+        "JSON-RPC requests that failed or reverted, by method and endpoint host",
+        &["method", "endpoint"]
+    )
+    .unwrap();
+    pub static ref RPC_SECONDS: HistogramVec = register_histogram_vec!(
+        "scanner_rpc_request_seconds",
+        "JSON-RPC round trip time, by method and endpoint host",
+        &["method", "endpoint"],
+        LATENCY_BUCKETS.to_vec()
+    )
+    .unwrap();
+    pub static ref POOL_FETCH_SECONDS: HistogramVec = register_histogram_vec!(
+        "scanner_pool_fetch_seconds",
+        "Time to fetch one pool's state, by DEX",
+        &["dex"],
+        LATENCY_BUCKETS.to_vec()
+    )
+    .unwrap();
+    pub static ref POOL_FAILURES: IntCounterVec = register_int_counter_vec!(
+        "scanner_pool_failures_total",
+        "Pool fetches and quotes that failed, by DEX and error kind",
+        &["dex", "kind"]
+    )
+    .unwrap();
+    pub static ref QUARANTINED_POOLS: IntGauge = register_int_gauge!(
+        "scanner_quarantined_pools",
+        "Pools currently skipped after failing repeatedly"
+    )
+    .unwrap();
+    pub static ref PAIRS_EVALUATED: IntCounterVec = register_int_counter_vec!(
+        "scanner_pairs_evaluated_total",
+        "Pool pairs fetched and compared, by monitor",
+        &["monitor"]
+    )
+    .unwrap();
+    pub static ref PAIRS_SKIPPED: IntCounterVec = register_int_counter_vec!(
+        "scanner_pairs_skipped_total",
+        "Pool pairs not simulated, by monitor and reason (quarantined, paused, failed, no_tier, tier_disabled, below_spread)",
+        &["monitor", "reason"]
+    )
+    .unwrap();
+    pub static ref OPPORTUNITIES_FOUND: IntCounterVec = register_int_counter_vec!(
+        "scanner_opportunities_found_total",
+        "Profitable opportunities found, by kind (pair or route)",
+        &["kind"]
+    )
+    .unwrap();
+    pub static ref OPPORTUNITIES_SUSPECT: IntCounterVec = register_int_counter_vec!(
+        "scanner_opportunities_suspect_total",
+        "Opportunities withheld because their prices were suspect, by kind",
+        &["kind"]
+    )
+    .unwrap();
+    pub static ref IPC_PUBLISHED: IntCounterVec = register_int_counter_vec!(
+        "scanner_ipc_published_total",
+        "Messages published over iceoryx2, by service",
+        &["service"]
+    )
+    .unwrap();
+    pub static ref IPC_PUBLISH_SECONDS: HistogramVec = register_histogram_vec!(
+        "scanner_ipc_publish_seconds",
+        "Time to loan, write and send one iceoryx2 sample, by service",
+        &["service"],
+        IPC_BUCKETS.to_vec()
+    )
+    .unwrap();
+    pub static ref FEE_CHANGES: IntCounterVec = register_int_counter_vec!(
+        "scanner_fee_changes_total",
+        "Pool fee changes seen, by DEX",
+        &["dex"]
+    )
+    .unwrap();
+    pub static ref HISTORY_ROWS: IntCounterVec = register_int_counter_vec!(
+        "scanner_history_records_total",
+        "Pair-block records for the history database, by result (written, failed, dropped)",
+        &["result"]
+    )
+    .unwrap();
+    pub static ref EXPORT_ROWS: IntCounterVec = register_int_counter_vec!(
+        "scanner_export_rows_total",
+        "Rows for the snapshot export files, by stream (pools, spreads) and result (written, failed, dropped)",
+        &["stream", "result"]
+    )
+    .unwrap();
+    pub static ref BEST_SPREAD: GaugeVec = register_gauge_vec!(
+        "scanner_best_spread_pct",
+        "Widest price difference between two pools of the pair on the latest block it was scanned, in percent",
+        &["pair"]
+    )
+    .unwrap();
+    pub static ref REFERENCE_WETH_USD: Gauge = register_gauge!(
+        "scanner_reference_weth_usd",
+        "WETH/USD reference price of the latest block"
+    )
+    .unwrap();
+    pub static ref REFERENCE_BLOCK: IntGauge = register_int_gauge!(
+        "scanner_reference_block",
+        "Block the WETH/USD reference price was taken from"
+    )
+    .unwrap();
+
+    // Widest spread seen per pair and the block it was seen on
+    static ref SPREADS: Mutex<HashMap<String, (u64, f64)>> = Mutex::new(HashMap::new());
+}
+
+// Several pool pairs of one pair report on the same block; the gauge keeps the widest
+pub fn observe_spread(pair_name: &str, block_number: u64, spread_pct: f64) {
+    let mut spreads = lock(&SPREADS);
+    let entry = spreads.entry(pair_name.to_string()).or_insert((block_number, spread_pct));
+    if block_number > entry.0 {
+        *entry = (block_number, spread_pct);
+    } else if block_number == entry.0 {
+        entry.1 = entry.1.max(spread_pct);
+    }
+    BEST_SPREAD.with_label_values(&[pair_name]).set(entry.1);
+}
+
+// RPC totals of one endpoint host since startup, over every method
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RpcStats {
+    pub endpoint: String,
+    pub requests: u64,
+    pub errors: u64,
+    pub avg_seconds: f64,
+}
+
+// Read back from the RPC metrics, by endpoint host
+pub fn rpc_stats() -> Vec<RpcStats> {
+    let mut stats: BTreeMap<String, (RpcStats, f64)> = BTreeMap::new();
+    let endpoint = |metric: &prometheus::proto::Metric| {
+        metric
+            .get_label()
+            .iter()
+            .find(|label| label.get_name() == "endpoint")
+            .map(|label| label.get_value().to_string())
+            .unwrap_or_default()
+    };
+    for family in RPC_REQUESTS.collect() {
+        for metric in family.get_metric() {
+            rpc_entry(&mut stats, endpoint(metric)).0.requests += metric.get_counter().get_value() as u64;
+        }
+    }
+    for family in RPC_ERRORS.collect() {
+        for metric in family.get_metric() {
+            rpc_entry(&mut stats, endpoint(metric)).0.errors += metric.get_counter().get_value() as u64;
+        }
+    }
+    for family in RPC_SECONDS.collect() {
+        for metric in family.get_metric() {
+            rpc_entry(&mut stats, endpoint(metric)).1 += metric.get_histogram().get_sample_sum();
+        }
+    }
+    stats
+        .into_values()
+        .map(|(mut stats, seconds)| {
+            if stats.requests > 0 {
+                stats.avg_seconds = seconds / stats.requests as f64;
+            }
+            stats
+        })
+        .collect()
+}
+
+fn rpc_entry(stats: &mut BTreeMap<String, (RpcStats, f64)>, endpoint: String) -> &mut (RpcStats, f64) {
+    stats
+        .entry(endpoint.clone())
+        .or_insert_with(|| (RpcStats { endpoint, ..RpcStats::default() }, 0.0))
+}
+
+pub fn render() -> Result<Vec<u8>, prometheus::Error> {
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&prometheus::gather(), &mut buffer)?;
+    Ok(buffer)
+}
+
+// Serves GET /metrics in the Prometheus text format. Anything else gets a 404.
+pub async fn serve_metrics(listen: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let listener = TcpListener::bind(listen).await?;
+    info!("📈 Metrics on http://{}/metrics", listener.local_addr()?);
+
+    loop {
+        let mut stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            // e.g. out of file descriptors; scrapes resume once the listener recovers
+            Err(e) => {
+                warn!("Metrics endpoint failed to accept a connection: {}", e);
+                tokio::time::sleep(ACCEPT_RETRY_DELAY).await;
+                continue;
+            }
+        };
+        tokio::spawn(async move {
+            let Ok(request) = read_request(&mut stream).await else {
+                return;
+            };
+            let response = if request.method == "GET" && request.path == "/metrics" {
+                match render() {
+                    Ok(body) => http_response("200 OK", "text/plain; version=0.0.4", &body),
+                    Err(e) => http_response("500 Internal Server Error", "text/plain", e.to_string().as_bytes()),
+                }
+            } else {
+                http_response("404 Not Found", "text/plain", b"not found\n")
+            };
+            if let Err(e) = stream.write_all(&response).await {
+                warn!("Failed to write metrics response: {}", e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The metrics are process-wide, so each test uses labels of its own
+
+    #[test]
+    fn widest_spread_of_a_block_is_kept() {
+        let pair = "SPREAD_TEST/WETH";
+        observe_spread(pair, 100, 0.8);
+        observe_spread(pair, 100, 1.2);
+        observe_spread(pair, 100, 0.5);
+        assert_eq!(BEST_SPREAD.with_label_values(&[pair]).get(), 1.2);
+
+        // A newer block replaces it even when narrower, and an older block is ignored
+        observe_spread(pair, 101, 0.3);
+        assert_eq!(BEST_SPREAD.with_label_values(&[pair]).get(), 0.3);
+        observe_spread(pair, 100, 2.0);
+        assert_eq!(BEST_SPREAD.with_label_values(&[pair]).get(), 0.3);
+    }
+
+    #[test]
+    fn rpc_stats_add_up_every_method_of_an_endpoint() {
+        let endpoint = "rpc-stats.test";
+        for (method, seconds, failed) in [("eth_call", 0.1, false), ("eth_call", 0.3, true), ("eth_blockNumber", 0.2, false)] {
+            RPC_REQUESTS.with_label_values(&[method, endpoint]).inc();
+            RPC_SECONDS.with_label_values(&[method, endpoint]).observe(seconds);
+            if failed {
+                RPC_ERRORS.with_label_values(&[method, endpoint]).inc();
+            }
+        }
+        RPC_REQUESTS.with_label_values(&["eth_call", "other-stats.test"]).inc();
+
+        let stats = rpc_stats();
+        let stats = stats.iter().find(|stats| stats.endpoint == endpoint).unwrap();
+        assert_eq!((stats.requests, stats.errors), (3, 1));
+        assert!((stats.avg_seconds - 0.2).abs() < 1e-9, "{}", stats.avg_seconds);
+
+        let other = rpc_stats().into_iter().find(|stats| stats.endpoint == "other-stats.test").unwrap();
+        assert_eq!((other.requests, other.errors, other.avg_seconds), (1, 0, 0.0));
+    }
+}
@@ -0,0 +1,571 @@
+use ethers::abi::{self, ParamType, Token};
+use ethers::types::{Address, I256, U256};
+use ethers::utils::id;
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{info, warn};
+
+// Scriptable stand-in for an Arbitrum node. It answers the eth_call selectors the
+// scanner uses (pool state, tick bitmaps, quoters, ERC20, multicall) from an in-memory chain state,
+// pushes newHeads when told to mine a block and can drop every open connection.
+// Point WS_RPC_URL at ws_url() and the scanner runs against it unchanged.
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MockDex {
+    Uniswap,
+    PancakeSwap,
+    Camelot,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct MockToken {
+    pub address: Address,
+    pub decimals: u8,
+    #[serde(default)]
+    pub symbol: String,
+    // holder -> raw balance
+    #[serde(default)]
+    pub balances: HashMap<Address, U256>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct MockPool {
+    pub address: Address,
+    pub dex: MockDex,
+    pub token0: Address,
+    pub token1: Address,
+    // Hundredths of a bip; for Camelot this is the current token0 -> token1 dynamic fee
+    pub fee: u32,
+    // Camelot token1 -> token0 fee, defaults to fee
+    #[serde(default)]
+    pub fee_otz: Option<u32>,
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+    pub liquidity: u128,
+    // Defaults to the DEX's spacing for the fee
+    #[serde(default)]
+    pub tick_spacing: Option<i32>,
+    // Initialized ticks; quotes still use the active liquidity only
+    #[serde(default)]
+    pub ticks: Vec<MockTick>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct MockTick {
+    pub tick: i32,
+    pub liquidity_net: i128,
+}
+
+impl MockPool {
+    fn fee_for(&self, zero_for_one: bool) -> u32 {
+        if zero_for_one { self.fee } else { self.fee_otz.unwrap_or(self.fee) }
+    }
+
+    fn tick_spacing(&self) -> i32 {
+        self.tick_spacing.unwrap_or(match (self.dex, self.fee) {
+            (MockDex::Camelot, _) => 60,
+            (_, 100) => 1,
+            (_, 500) => 10,
+            (_, 2500) => 50,
+            (_, 10000) => 200,
+            _ => 60,
+        })
+    }
+
+    // One bit per initialized tick divided by the spacing, 256 to a word
+    fn tick_bitmap_word(&self, word: i32) -> U256 {
+        let spacing = self.tick_spacing();
+        self.ticks
+            .iter()
+            .map(|tick| tick.tick.div_euclid(spacing))
+            .filter(|compressed| compressed >> 8 == word)
+            .fold(U256::zero(), |bitmap, compressed| bitmap | (U256::one() << (compressed & 0xff) as usize))
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct MockChainState {
+    #[serde(default = "default_chain_id")]
+    pub chain_id: u64,
+    #[serde(default)]
+    pub tokens: Vec<MockToken>,
+    #[serde(default)]
+    pub pools: Vec<MockPool>,
+}
+
+fn default_chain_id() -> u64 {
+    42161
+}
+
+impl MockChainState {
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = std::fs::File::open(path)?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+
+    pub fn pool_mut(&mut self, address: Address) -> Option<&mut MockPool> {
+        self.pools.iter_mut().find(|p| p.address == address)
+    }
+
+    pub fn token_mut(&mut self, address: Address) -> Option<&mut MockToken> {
+        self.tokens.iter_mut().find(|t| t.address == address)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum NodeEvent {
+    NewHead(u64),
+    DropConnections,
+}
+
+struct Shared {
+    state: RwLock<MockChainState>,
+    block_number: AtomicU64,
+}
+
+pub struct MockNode {
+    addr: SocketAddr,
+    shared: Arc<Shared>,
+    events: broadcast::Sender<NodeEvent>,
+}
+
+impl MockNode {
+    // Binds to an ephemeral port on localhost and starts accepting connections
+    pub async fn start(state: MockChainState, start_block: u64) -> std::io::Result<Self> {
+        Self::start_on("127.0.0.1:0", state, start_block).await
+    }
+
+    pub async fn start_on(bind_addr: &str, state: MockChainState, start_block: u64) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(bind_addr).await?;
+        let addr = listener.local_addr()?;
+        let shared = Arc::new(Shared {
+            state: RwLock::new(state),
+            block_number: AtomicU64::new(start_block),
+        });
+        let (events, _) = broadcast::channel(64);
+
+        let accept_shared = shared.clone();
+        let accept_events = events.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let shared = accept_shared.clone();
+                let events = accept_events.subscribe();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, shared, events).await {
+                        warn!("❌ Mock node connection error: {}", e);
+                    }
+                });
+            }
+        });
+
+        info!("🧪 Mock node listening on ws://{}", addr);
+
+        Ok(Self { addr, shared, events })
+    }
+
+    pub fn ws_url(&self) -> String {
+        format!("ws://{}", self.addr)
+    }
+
+    pub fn block_number(&self) -> u64 {
+        self.shared.block_number.load(Ordering::SeqCst)
+    }
+
+    // Applies a change to the chain state; later eth_calls see the new values
+    pub fn update_state<F: FnOnce(&mut MockChainState)>(&self, f: F) {
+        f(&mut self.shared.state.write().unwrap());
+    }
+
+    // Advances the chain by one block and pushes the header to every newHeads subscriber
+    pub fn mine_block(&self) -> u64 {
+        let number = self.shared.block_number.fetch_add(1, Ordering::SeqCst) + 1;
+        let _ = self.events.send(NodeEvent::NewHead(number));
+        number
+    }
+
+    // Closes every open connection, as a flaky provider would
+    pub fn drop_connections(&self) {
+        let _ = self.events.send(NodeEvent::DropConnections);
+    }
+}
+
+async fn handle_connection(
+    stream: TcpStream,
+    shared: Arc<Shared>,
+    mut events: broadcast::Receiver<NodeEvent>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let ws = tokio_tungstenite::accept_async(stream).await?;
+    let (mut sink, mut incoming) = ws.split();
+    let mut subscriptions: Vec<String> = Vec::new();
+
+    loop {
+        tokio::select! {
+            message = incoming.next() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Ping(payload))) => {
+                        sink.send(Message::Pong(payload)).await?;
+                        continue;
+                    }
+                    Some(Ok(Message::Close(_))) | None => return Ok(()),
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(Box::new(e)),
+                };
+
+                let request: Value = serde_json::from_str(&text)?;
+                let response = match request {
+                    Value::Array(batch) => Value::Array(
+                        batch.iter().map(|r| handle_request(r, &shared, &mut subscriptions)).collect(),
+                    ),
+                    request => handle_request(&request, &shared, &mut subscriptions),
+                };
+                sink.send(Message::Text(response.to_string())).await?;
+            }
+            event = events.recv() => {
+                match event {
+                    Ok(NodeEvent::NewHead(number)) => {
+                        for subscription in &subscriptions {
+                            let notification = json!({
+                                "jsonrpc": "2.0",
+                                "method": "eth_subscription",
+                                "params": { "subscription": subscription, "result": block_header(number) },
+                            });
+                            sink.send(Message::Text(notification.to_string())).await?;
+                        }
+                    }
+                    Ok(NodeEvent::DropConnections) | Err(broadcast::error::RecvError::Closed) => {
+                        let _ = sink.close().await;
+                        return Ok(());
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                }
+            }
+        }
+    }
+}
+
+fn handle_request(request: &Value, shared: &Shared, subscriptions: &mut Vec<String>) -> Value {
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let method = request.get("method").and_then(Value::as_str).unwrap_or_default();
+    let params = request.get("params").cloned().unwrap_or(Value::Array(Vec::new()));
+
+    let result: Result<Value, (i64, String)> = match method {
+        "eth_chainId" => Ok(json!(format!("0x{:x}", shared.state.read().unwrap().chain_id))),
+        "net_version" => Ok(json!(shared.state.read().unwrap().chain_id.to_string())),
+        "eth_blockNumber" => Ok(json!(format!("0x{:x}", shared.block_number.load(Ordering::SeqCst)))),
+        "eth_getBlockByNumber" => Ok(block_header(shared.block_number.load(Ordering::SeqCst))),
+        "eth_subscribe" => match params.get(0).and_then(Value::as_str) {
+            Some("newHeads") => {
+                let subscription = format!("0x{:x}", subscriptions.len() + 1);
+                subscriptions.push(subscription.clone());
+                Ok(json!(subscription))
+            }
+            other => Err((-32602, format!("Unsupported subscription {:?}", other))),
+        },
+        "eth_unsubscribe" => {
+            let subscription = params.get(0).and_then(Value::as_str).unwrap_or_default();
+            let before = subscriptions.len();
+            subscriptions.retain(|s| s != subscription);
+            Ok(json!(subscriptions.len() != before))
+        }
+        "eth_call" => eth_call(&params, &shared.state.read().unwrap()),
+        _ => Err((-32601, format!("Method {} not supported by mock node", method))),
+    };
+
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } }),
+    }
+}
+
+fn eth_call(params: &Value, state: &MockChainState) -> Result<Value, (i64, String)> {
+    let call = params.get(0).ok_or((-32602, "Missing call object".to_string()))?;
+    let to: Address = call
+        .get("to")
+        .and_then(Value::as_str)
+        .and_then(|to| to.parse().ok())
+        .ok_or((-32602, "Missing call target".to_string()))?;
+    let data = call
+        .get("data")
+        .or_else(|| call.get("input"))
+        .and_then(Value::as_str)
+        .and_then(|data| hex::decode(data.trim_start_matches("0x")).ok())
+        .ok_or((-32602, "Missing call data".to_string()))?;
+
+    // Reverts surface as JSON-RPC errors, like a real node
+    let output = dispatch_call(to, &data, state).map_err(|e| (3, format!("execution reverted: {}", e)))?;
+    Ok(json!(format!("0x{}", hex::encode(output))))
+}
+
+fn dispatch_call(to: Address, data: &[u8], state: &MockChainState) -> Result<Vec<u8>, String> {
+    if data.len() < 4 {
+        return Err("calldata shorter than a selector".to_string());
+    }
+    let (selector, args) = (&data[..4], &data[4..]);
+
+    // Multicall3, for clients that batch their pool reads
+    if selector == &id("aggregate3((address,bool,bytes)[])")[..] {
+        let calls = decode_args(&[ParamType::Array(Box::new(ParamType::Tuple(vec![
+            ParamType::Address,
+            ParamType::Bool,
+            ParamType::Bytes,
+        ])))], args)?;
+        let mut results = Vec::new();
+        for call in array_of(&calls[0]) {
+            let fields = tuple_of(&call);
+            let (target, allow_failure, call_data) = (address_of(&fields[0]), bool_of(&fields[1]), bytes_of(&fields[2]));
+            match dispatch_call(target, &call_data, state) {
+                Ok(output) => results.push(Token::Tuple(vec![Token::Bool(true), Token::Bytes(output)])),
+                Err(_) if allow_failure => results.push(Token::Tuple(vec![Token::Bool(false), Token::Bytes(Vec::new())])),
+                Err(e) => return Err(e),
+            }
+        }
+        return Ok(abi::encode(&[Token::Array(results)]));
+    }
+    if selector == &id("aggregate((address,bytes)[])")[..] {
+        let calls = decode_args(&[ParamType::Array(Box::new(ParamType::Tuple(vec![
+            ParamType::Address,
+            ParamType::Bytes,
+        ])))], args)?;
+        let mut results = Vec::new();
+        for call in array_of(&calls[0]) {
+            let fields = tuple_of(&call);
+            results.push(Token::Bytes(dispatch_call(address_of(&fields[0]), &bytes_of(&fields[1]), state)?));
+        }
+        return Ok(abi::encode(&[Token::Uint(U256::zero()), Token::Array(results)]));
+    }
+
+    // Quoters are matched by signature, whatever address they are called on
+    if selector == &id("quoteExactInputSingle(address,address,uint24,uint256,uint160)")[..] {
+        let args = decode_args(
+            &[ParamType::Address, ParamType::Address, ParamType::Uint(24), ParamType::Uint(256), ParamType::Uint(160)],
+            args,
+        )?;
+        let pool = find_pool(state, MockDex::Uniswap, address_of(&args[0]), address_of(&args[1]), Some(uint_of(&args[2])))?;
+        let amount_out = quote(pool, address_of(&args[0]), uint_of(&args[3]))?;
+        return Ok(abi::encode(&[Token::Uint(amount_out)]));
+    }
+    if selector == &id("quoteExactInputSingle((address,address,uint256,uint24,uint160))")[..] {
+        let args = decode_args(&[ParamType::Tuple(vec![
+            ParamType::Address,
+            ParamType::Address,
+            ParamType::Uint(256),
+            ParamType::Uint(24),
+            ParamType::Uint(160),
+        ])], args)?;
+        let fields = tuple_of(&args[0]);
+        let pool = find_pool(state, MockDex::PancakeSwap, address_of(&fields[0]), address_of(&fields[1]), Some(uint_of(&fields[3])))?;
+        let amount_out = quote(pool, address_of(&fields[0]), uint_of(&fields[2]))?;
+        return Ok(abi::encode(&[
+            Token::Uint(amount_out),
+            Token::Uint(pool.sqrt_price_x96),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::zero()),
+        ]));
+    }
+    if selector == &id("quoteExactInputSingle(address,address,uint256,uint160)")[..] {
+        let args = decode_args(
+            &[ParamType::Address, ParamType::Address, ParamType::Uint(256), ParamType::Uint(160)],
+            args,
+        )?;
+        let pool = find_pool(state, MockDex::Camelot, address_of(&args[0]), address_of(&args[1]), None)?;
+        let amount_out = quote(pool, address_of(&args[0]), uint_of(&args[2]))?;
+        let fee = pool.fee_for(address_of(&args[0]) == pool.token0);
+        return Ok(abi::encode(&[Token::Uint(amount_out), Token::Uint(U256::from(fee))]));
+    }
+
+    if let Some(pool) = state.pools.iter().find(|p| p.address == to) {
+        return pool_call(pool, selector, args);
+    }
+    if let Some(token) = state.tokens.iter().find(|t| t.address == to) {
+        return token_call(token, selector, args);
+    }
+
+    Err(format!("no contract at {:?}", to))
+}
+
+fn pool_call(pool: &MockPool, selector: &[u8], args: &[u8]) -> Result<Vec<u8>, String> {
+    let tick = Token::Int(I256::from(pool.tick).into_raw());
+    let bitmap_selector = if pool.dex == MockDex::Camelot { id("tickTable(int16)") } else { id("tickBitmap(int16)") };
+    let output = if selector == &id("token0()")[..] {
+        abi::encode(&[Token::Address(pool.token0)])
+    } else if selector == &id("token1()")[..] {
+        abi::encode(&[Token::Address(pool.token1)])
+    } else if selector == &id("fee()")[..] && pool.dex != MockDex::Camelot {
+        abi::encode(&[Token::Uint(U256::from(pool.fee))])
+    } else if selector == &id("liquidity()")[..] {
+        abi::encode(&[Token::Uint(U256::from(pool.liquidity))])
+    } else if selector == &id("slot0()")[..] && pool.dex != MockDex::Camelot {
+        abi::encode(&[
+            Token::Uint(pool.sqrt_price_x96),
+            tick,
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::one()),
+            Token::Uint(U256::one()),
+            Token::Uint(U256::zero()),
+            Token::Bool(true),
+        ])
+    } else if selector == &id("tickSpacing()")[..] {
+        abi::encode(&[Token::Int(I256::from(pool.tick_spacing()).into_raw())])
+    } else if selector == &bitmap_selector[..] {
+        let args = decode_args(&[ParamType::Int(16)], args)?;
+        let word = I256::from_raw(int_of(&args[0])).low_i32();
+        abi::encode(&[Token::Uint(pool.tick_bitmap_word(word))])
+    } else if selector == &id("ticks(int24)")[..] {
+        let args = decode_args(&[ParamType::Int(24)], args)?;
+        let index = I256::from_raw(int_of(&args[0])).low_i32();
+        let net = pool.ticks.iter().find(|tick| tick.tick == index).map_or(0, |tick| tick.liquidity_net);
+        abi::encode(&[
+            Token::Uint(U256::from(net.unsigned_abs())),
+            Token::Int(I256::from(net).into_raw()),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::zero()),
+            Token::Int(U256::zero()),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::zero()),
+            Token::Bool(net != 0),
+        ])
+    } else if selector == &id("globalState()")[..] && pool.dex == MockDex::Camelot {
+        abi::encode(&[
+            Token::Uint(pool.sqrt_price_x96),
+            tick,
+            Token::Uint(U256::from(pool.fee_for(true))),
+            Token::Uint(U256::from(pool.fee_for(false))),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::zero()),
+            Token::Bool(true),
+        ])
+    } else {
+        return Err(format!("unsupported pool selector 0x{}", hex::encode(selector)));
+    };
+    Ok(output)
+}
+
+fn token_call(token: &MockToken, selector: &[u8], args: &[u8]) -> Result<Vec<u8>, String> {
+    let output = if selector == &id("decimals()")[..] {
+        abi::encode(&[Token::Uint(U256::from(token.decimals))])
+    } else if selector == &id("symbol()")[..] || selector == &id("name()")[..] {
+        abi::encode(&[Token::String(token.symbol.clone())])
+    } else if selector == &id("balanceOf(address)")[..] {
+        let args = decode_args(&[ParamType::Address], args)?;
+        let balance = token.balances.get(&address_of(&args[0])).copied().unwrap_or_default();
+        abi::encode(&[Token::Uint(balance)])
+    } else {
+        return Err(format!("unsupported token selector 0x{}", hex::encode(selector)));
+    };
+    Ok(output)
+}
+
+fn find_pool(
+    state: &MockChainState,
+    dex: MockDex,
+    token_in: Address,
+    token_out: Address,
+    fee: Option<U256>,
+) -> Result<&MockPool, String> {
+    state
+        .pools
+        .iter()
+        .find(|p| {
+            p.dex == dex
+                && ((p.token0 == token_in && p.token1 == token_out) || (p.token0 == token_out && p.token1 == token_in))
+                && fee.is_none_or(|fee| fee == U256::from(p.fee))
+        })
+        .ok_or_else(|| format!("no {:?} pool for {:?}/{:?}", dex, token_in, token_out))
+}
+
+// Same single-range swap math as the local simulator, on raw amounts
+fn quote(pool: &MockPool, token_in: Address, amount_in: U256) -> Result<U256, String> {
+    let sqrt_price = pool.sqrt_price_x96.to_string().parse::<f64>().unwrap_or(0.0) / 2.0f64.powi(96);
+    let liquidity = pool.liquidity as f64;
+    if sqrt_price <= 0.0 || liquidity <= 0.0 {
+        return Err("pool has no active liquidity".to_string());
+    }
+
+    let fee = pool.fee_for(token_in == pool.token0);
+    let amount_in = amount_in.to_string().parse::<f64>().unwrap_or(0.0) * (1.0 - fee as f64 / 1_000_000.0);
+    let amount_out = if token_in == pool.token0 {
+        let sqrt_price_next = 1.0 / (1.0 / sqrt_price + amount_in / liquidity);
+        liquidity * (sqrt_price - sqrt_price_next)
+    } else {
+        let sqrt_price_next = sqrt_price + amount_in / liquidity;
+        liquidity * (1.0 / sqrt_price - 1.0 / sqrt_price_next)
+    };
+
+    Ok(U256::from(amount_out.max(0.0) as u128))
+}
+
+fn decode_args(types: &[ParamType], args: &[u8]) -> Result<Vec<Token>, String> {
+    abi::decode(types, args).map_err(|e| format!("bad calldata: {}", e))
+}
+
+fn address_of(token: &Token) -> Address {
+    token.clone().into_address().unwrap_or_default()
+}
+
+fn uint_of(token: &Token) -> U256 {
+    token.clone().into_uint().unwrap_or_default()
+}
+
+fn int_of(token: &Token) -> U256 {
+    token.clone().into_int().unwrap_or_default()
+}
+
+fn bool_of(token: &Token) -> bool {
+    token.clone().into_bool().unwrap_or_default()
+}
+
+fn bytes_of(token: &Token) -> Vec<u8> {
+    token.clone().into_bytes().unwrap_or_default()
+}
+
+fn array_of(token: &Token) -> Vec<Token> {
+    token.clone().into_array().unwrap_or_default()
+}
+
+fn tuple_of(token: &Token) -> Vec<Token> {
+    token.clone().into_tuple().unwrap_or_default()
+}
+
+// Minimal header carrying every field clients require to deserialize newHeads
+fn block_header(number: u64) -> Value {
+    let hash = |salt: u8| {
+        let mut bytes = [0u8; 32];
+        bytes[..8].copy_from_slice(&number.to_be_bytes());
+        bytes[31] = salt;
+        format!("0x{}", hex::encode(bytes))
+    };
+    let empty_root = "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421";
+    json!({
+        "number": format!("0x{:x}", number),
+        "hash": hash(1),
+        "parentHash": hash(0),
+        "sha3Uncles": "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347",
+        "miner": format!("{:?}", Address::zero()),
+        "stateRoot": hash(2),
+        "transactionsRoot": empty_root,
+        "receiptsRoot": empty_root,
+        "logsBloom": format!("0x{}", "0".repeat(512)),
+        "difficulty": "0x1",
+        "totalDifficulty": "0x0",
+        "gasLimit": "0x4000000000000",
+        "gasUsed": "0x0",
+        "timestamp": format!("0x{:x}", chrono::Utc::now().timestamp()),
+        "extraData": "0x",
+        "mixHash": hash(3),
+        "nonce": "0x0000000000000000",
+        "baseFeePerGas": "0x989680",
+        "transactions": [],
+        "uncles": [],
+    })
+}
@@ -0,0 +1,181 @@
+use ethers::prelude::Address as EthersAddress;
+use ethers::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::config::CHAINLINK_USD_FEEDS;
+use crate::error::to_u128;
+use crate::pricing::{base_symbol, token_address};
+use crate::settings::settings;
+
+type SendError = Box<dyn std::error::Error + Send + Sync>;
+
+abigen!(
+    ChainlinkAggregator,
+    r#"[
+        function decimals() external view returns (uint8)
+        function latestRoundData() external view returns (uint80 roundId, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound)
+    ]"#,
+);
+
+// Cross-checks DEX-derived USD prices against Chainlink. Enabled with oracle.enabled;
+// oracle.max_deviation is the allowed relative gap (0.02 = 2%) and oracle.max_age_secs the
+// oldest feed answer still trusted. Feeds that can't be read are skipped, not treated as suspect.
+pub struct ChainlinkOracle {
+    // Feed answers already read this block, by token
+    prices: Mutex<HashMap<EthersAddress, (u64, Option<f64>)>>,
+}
+
+impl ChainlinkOracle {
+    pub fn from_settings() -> Option<Self> {
+        let oracle = settings().oracle.clone();
+        if !oracle.enabled {
+            return None;
+        }
+        info!(
+            "🔮 Chainlink cross-check enabled (max deviation {:.2}%, max feed age {}s)",
+            oracle.max_deviation * 100.0,
+            oracle.max_age_secs
+        );
+
+        Some(Self { prices: Mutex::new(HashMap::new()) })
+    }
+
+    // Chainlink USD price of a token for this block, None when there's no usable feed
+    pub async fn usd_price<M: Middleware + 'static>(
+        &self,
+        token: EthersAddress,
+        block_number: u64,
+        provider: Arc<M>,
+    ) -> Option<f64> {
+        let mut prices = self.prices.lock().await;
+        if let Some((_, price)) = prices.get(&token).filter(|(block, _)| *block >= block_number) {
+            return *price;
+        }
+
+        let feed = feed_for(token)?;
+        let price = match read_feed(feed, settings().oracle.max_age_secs, provider).await {
+            Ok(price) => Some(price),
+            Err(e) => {
+                warn!("⚠️ Chainlink {} feed unavailable at #{}: {}", base_symbol(token), block_number, e);
+                None
+            }
+        };
+        prices.insert(token, (block_number, price));
+        price
+    }
+
+    // Reason the DEX price is suspect, if it's further from Chainlink than allowed
+    pub async fn check<M: Middleware + 'static>(
+        &self,
+        token: EthersAddress,
+        dex_usd: f64,
+        block_number: u64,
+        provider: Arc<M>,
+    ) -> Option<String> {
+        let oracle_usd = self.usd_price(token, block_number, provider).await?;
+        suspect_reason(token, dex_usd, oracle_usd, settings().oracle.max_deviation)
+    }
+}
+
+pub fn deviation(dex_usd: f64, oracle_usd: f64) -> f64 {
+    if oracle_usd <= 0.0 {
+        return 0.0;
+    }
+    (dex_usd - oracle_usd).abs() / oracle_usd
+}
+
+fn suspect_reason(token: EthersAddress, dex_usd: f64, oracle_usd: f64, max_deviation: f64) -> Option<String> {
+    let deviation = deviation(dex_usd, oracle_usd);
+    (deviation > max_deviation).then(|| {
+        format!(
+            "{}/USD {:.4} is {:.2}% off Chainlink {:.4}",
+            base_symbol(token),
+            dex_usd,
+            deviation * 100.0,
+            oracle_usd
+        )
+    })
+}
+
+fn feed_for(token: EthersAddress) -> Option<EthersAddress> {
+    CHAINLINK_USD_FEEDS
+        .iter()
+        .find(|(address, _)| token_address(address) == token)
+        .and_then(|(_, feed)| feed.parse().ok())
+}
+
+async fn read_feed<M: Middleware + 'static>(
+    feed: EthersAddress,
+    max_age_secs: u64,
+    provider: Arc<M>,
+) -> Result<f64, SendError> {
+    let aggregator = ChainlinkAggregator::new(feed, provider);
+    let decimals = aggregator.decimals().call().await?;
+    let (_, answer, _, updated_at, _) = aggregator.latest_round_data().call().await?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    feed_price(answer, updated_at, decimals, now, max_age_secs)
+}
+
+// USD price from a latestRoundData answer, unless it is stale, non-positive or out of range
+fn feed_price(answer: I256, updated_at: U256, decimals: u8, now: u64, max_age_secs: u64) -> Result<f64, SendError> {
+    let updated_at = u64::try_from(updated_at).map_err(|_| format!("updatedAt {} overflows u64", updated_at))?;
+    let age = now.saturating_sub(updated_at);
+    if age > max_age_secs {
+        return Err(format!("answer is {}s old", age).into());
+    }
+    if answer <= I256::zero() {
+        return Err("non-positive answer".into());
+    }
+
+    Ok(to_u128(answer.into_raw(), "answer")? as f64 / 10f64.powi(decimals as i32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{deviation, feed_price, suspect_reason};
+    use crate::config::WETH_ADDRESS;
+    use crate::pricing::token_address;
+    use ethers::prelude::{I256, U256};
+
+    const NOW: u64 = 1_700_000_000;
+
+    #[test]
+    fn fresh_answer_is_scaled_by_the_feed_decimals() {
+        let price = feed_price(I256::from(3_000_12345678i64), U256::from(NOW - 60), 8, NOW, 3600).unwrap();
+        assert!((price - 3000.12345678).abs() < 1e-9);
+    }
+
+    #[test]
+    fn stale_negative_and_overflowing_answers_are_errors() {
+        assert!(feed_price(I256::from(1), U256::from(NOW - 3601), 8, NOW, 3600).is_err());
+        assert!(feed_price(I256::zero(), U256::from(NOW), 8, NOW, 3600).is_err());
+        assert!(feed_price(I256::from(-1), U256::from(NOW), 8, NOW, 3600).is_err());
+        // Out of range values a broken feed could return are errors, not panics
+        assert!(feed_price(I256::from(1), U256::MAX, 8, NOW, 3600).is_err());
+        assert!(feed_price(I256::MAX, U256::from(NOW), 8, NOW, 3600).is_err());
+    }
+
+    #[test]
+    fn deviation_is_relative_to_the_oracle() {
+        assert!((deviation(3060.0, 3000.0) - 0.02).abs() < 1e-12);
+        assert!((deviation(2940.0, 3000.0) - 0.02).abs() < 1e-12);
+        // No oracle price, nothing to compare against
+        assert_eq!(deviation(3000.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn only_prices_past_the_max_deviation_are_suspect() {
+        let weth = token_address(WETH_ADDRESS);
+        assert_eq!(suspect_reason(weth, 3050.0, 3000.0, 0.02), None);
+        let reason = suspect_reason(weth, 3100.0, 3000.0, 0.02).unwrap();
+        assert_eq!(reason, "WETH/USD 3100.0000 is 3.33% off Chainlink 3000.0000");
+    }
+}
@@ -0,0 +1,524 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use ethers::prelude::{Address as EthersAddress, abigen};
+use ethers::providers::Middleware;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+use tracing::instrument;
+
+use crate::exchange::camelot::{CamelotPoolInfo, get_camelot_info};
+use crate::exchange::FetchDepth;
+use crate::exchange::model::Dex;
+use crate::exchange::pancakeswap::{PancakeSwapPoolInfo, get_pancakeswap_info};
+use crate::exchange::uniswap::{UniswapPoolInfo, get_uniswap_info};
+use crate::config::WETH_ADDRESS;
+use crate::depth::{DepthLadder, depth_ladder};
+use crate::error::{ScanResult, lock, parse_address, read_lock, write_lock};
+use crate::export::SnapshotExporter;
+use crate::export::schema::PoolRow;
+use crate::fees::FeeRegistry;
+use crate::metrics::{POOL_FAILURES, POOL_FETCH_SECONDS};
+use crate::recorder::{BlockRecorder, RecordedPool};
+use crate::trade::camelot::simulate_camelot_trade_with_slippage;
+use crate::trade::pancake::simulate_pancake_trade_with_slippage;
+use crate::trade::uniswap::simulate_uniswap_trade_with_slippage;
+
+// Latest fetched state of a single pool, tagged by the DEX it lives on
+#[derive(Debug, Clone)]
+pub enum PoolState {
+    Uniswap(UniswapPoolInfo),
+    PancakeSwap(PancakeSwapPoolInfo),
+    Camelot(CamelotPoolInfo),
+}
+
+impl PoolState {
+    // Full state, with the initialized ticks the depth ladder walks through
+    pub async fn fetch<M: Middleware + 'static>(
+        dex: Dex,
+        pool_address: &str,
+        base: EthersAddress,
+        provider: Arc<M>,
+    ) -> ScanResult<Self> {
+        Self::fetch_with(dex, pool_address, base, FetchDepth::WithTicks, provider).await
+    }
+
+    // Price, TVL and fees without the tick walk, for pools whose depth isn't used such as
+    // the reference pools. The depth ladder of such a state assumes constant liquidity.
+    pub async fn fetch_price<M: Middleware + 'static>(
+        dex: Dex,
+        pool_address: &str,
+        base: EthersAddress,
+        provider: Arc<M>,
+    ) -> ScanResult<Self> {
+        Self::fetch_with(dex, pool_address, base, FetchDepth::PriceOnly, provider).await
+    }
+
+    #[instrument(name = "fetch_pool", skip_all, fields(dex = dex.name(), pool = pool_address))]
+    async fn fetch_with<M: Middleware + 'static>(
+        dex: Dex,
+        pool_address: &str,
+        base: EthersAddress,
+        depth: FetchDepth,
+        provider: Arc<M>,
+    ) -> ScanResult<Self> {
+        let address = pool_address.to_string();
+        let _timer = POOL_FETCH_SECONDS.with_label_values(&[dex.name()]).start_timer();
+        let state = match dex {
+            Dex::Uniswap => get_uniswap_info(address, base, depth, provider).await.map(PoolState::Uniswap),
+            Dex::PancakeSwap => get_pancakeswap_info(address, base, depth, provider).await.map(PoolState::PancakeSwap),
+            Dex::Camelot => get_camelot_info(address, base, depth, provider).await.map(PoolState::Camelot),
+        };
+        if let Err(e) = &state {
+            POOL_FAILURES.with_label_values(&[dex.name(), e.kind().name()]).inc();
+        }
+        state
+    }
+
+    // Token a pool outside the pair file is quoted in: preferred when the pool holds it and
+    // token1 otherwise, since route legs such as ARB/USDC don't touch WETH
+    pub async fn quote_token<M: Middleware + 'static>(
+        pool_address: &str,
+        preferred: EthersAddress,
+        provider: Arc<M>,
+    ) -> ScanResult<EthersAddress> {
+        abigen!(
+            PoolTokens,
+            r#"[
+                function token0() external view returns (address)
+                function token1() external view returns (address)
+            ]"#,
+        );
+        let pool = PoolTokens::new(parse_address(pool_address)?, provider);
+        let token0 = pool.token_0().call().await?;
+        let token1 = pool.token_1().call().await?;
+        Ok(if token0 == preferred || token1 == preferred { preferred } else { token1 })
+    }
+
+    pub fn dex(&self) -> Dex {
+        match self {
+            PoolState::Uniswap(_) => Dex::Uniswap,
+            PoolState::PancakeSwap(_) => Dex::PancakeSwap,
+            PoolState::Camelot(_) => Dex::Camelot,
+        }
+    }
+
+    pub fn dex_name(&self) -> &'static str {
+        match self {
+            PoolState::Uniswap(_) => "Uniswap",
+            PoolState::PancakeSwap(_) => "PancakeSwap",
+            PoolState::Camelot(_) => "Camelot",
+        }
+    }
+
+    pub fn pair_address(&self) -> &str {
+        match self {
+            PoolState::Uniswap(p) => &p.pair_address,
+            PoolState::PancakeSwap(p) => &p.pair_address,
+            PoolState::Camelot(p) => &p.pair_address,
+        }
+    }
+
+    pub fn tokens(&self) -> (EthersAddress, EthersAddress) {
+        match self {
+            PoolState::Uniswap(p) => (p.token0, p.token1),
+            PoolState::PancakeSwap(p) => (p.token0, p.token1),
+            PoolState::Camelot(p) => (p.token0, p.token1),
+        }
+    }
+
+    pub fn base(&self) -> EthersAddress {
+        match self {
+            PoolState::Uniswap(p) => p.base,
+            PoolState::PancakeSwap(p) => p.base,
+            PoolState::Camelot(p) => p.base,
+        }
+    }
+
+    pub fn price(&self) -> f64 {
+        match self {
+            PoolState::Uniswap(p) => p.price,
+            PoolState::PancakeSwap(p) => p.price,
+            PoolState::Camelot(p) => p.price,
+        }
+    }
+
+    pub fn tick(&self) -> i32 {
+        match self {
+            PoolState::Uniswap(p) => p.tick,
+            PoolState::PancakeSwap(p) => p.tick,
+            PoolState::Camelot(p) => p.tick,
+        }
+    }
+
+    pub fn liquidity(&self) -> u128 {
+        match self {
+            PoolState::Uniswap(p) => p.liquidity,
+            PoolState::PancakeSwap(p) => p.liquidity,
+            PoolState::Camelot(p) => p.liquidity,
+        }
+    }
+
+    // Fee for token0 -> token1 swaps; the same both ways except on Camelot
+    pub fn fee(&self) -> u32 {
+        self.fee_for(true)
+    }
+
+    pub fn fee_for(&self, zero_for_one: bool) -> u32 {
+        match self {
+            PoolState::Uniswap(p) => p.fee,
+            PoolState::PancakeSwap(p) => p.fee,
+            PoolState::Camelot(p) if zero_for_one => p.fee_zto,
+            PoolState::Camelot(p) => p.fee_otz,
+        }
+    }
+
+    pub fn initialized_ticks(&self) -> &[(i32, i128)] {
+        match self {
+            PoolState::Uniswap(p) => &p.initialized_ticks,
+            PoolState::PancakeSwap(p) => &p.initialized_ticks,
+            PoolState::Camelot(p) => &p.initialized_ticks,
+        }
+    }
+
+    pub fn token_amounts_adjusted(&self) -> (f64, f64) {
+        match self {
+            PoolState::Uniswap(p) => (p.token0_amount_adjusted, p.token1_amount_adjusted),
+            PoolState::PancakeSwap(p) => (p.token0_amount_adjusted, p.token1_amount_adjusted),
+            PoolState::Camelot(p) => (p.token0_amount_adjusted, p.token1_amount_adjusted),
+        }
+    }
+
+    pub fn decimals(&self) -> (u8, u8) {
+        match self {
+            PoolState::Uniswap(p) => (p.token0_decimals, p.token1_decimals),
+            PoolState::PancakeSwap(p) => (p.token0_decimals, p.token1_decimals),
+            PoolState::Camelot(p) => (p.token0_decimals, p.token1_decimals),
+        }
+    }
+
+    // Price and liquidity in USDC, given the USD price of the base token
+    pub fn usdc_values(&self, base_usd: f64) -> (f64, f64) {
+        (self.price() * base_usd, self.liquidity() as f64 * base_usd)
+    }
+
+    // Plain-data copy of the state that can be written to disk and replayed offline
+    pub fn snapshot(&self, weth_usdc: f64, base_usd: f64) -> PoolSnapshot {
+        let (token0, token1, token0_decimals, token1_decimals, sqrt_price_x96, active_liquidity, tick, fee, price, liquidity) =
+            match self {
+                PoolState::Uniswap(p) => (p.token0, p.token1, p.token0_decimals, p.token1_decimals, p.sqrt_price_x96, p.active_liquidity, p.tick, p.fee, p.price, p.liquidity),
+                PoolState::PancakeSwap(p) => (p.token0, p.token1, p.token0_decimals, p.token1_decimals, p.sqrt_price_x96, p.active_liquidity, p.tick, p.fee, p.price, p.liquidity),
+                PoolState::Camelot(p) => (p.token0, p.token1, p.token0_decimals, p.token1_decimals, p.sqrt_price_x96, p.active_liquidity, p.tick, p.fee_zto, p.price, p.liquidity),
+            };
+
+        PoolSnapshot {
+            dex: self.dex_name().to_string(),
+            pool_address: self.pair_address().to_lowercase(),
+            token0: format!("0x{}", hex::encode(token0.as_bytes())),
+            token1: format!("0x{}", hex::encode(token1.as_bytes())),
+            token0_decimals,
+            token1_decimals,
+            sqrt_price_x96: sqrt_price_x96.to_string(),
+            active_liquidity,
+            tick,
+            initialized_ticks: self.initialized_ticks().to_vec(),
+            fee,
+            fee_otz: match self {
+                PoolState::Camelot(p) => Some(p.fee_otz),
+                _ => None,
+            },
+            price,
+            liquidity,
+            base_token: format!("0x{}", hex::encode(self.base().as_bytes())),
+            weth_usdc,
+            base_usd,
+        }
+    }
+
+    pub fn depth_ladder(&self, base_usd: f64) -> DepthLadder {
+        self.snapshot(0.0, base_usd).depth_ladder()
+    }
+
+    // Runs the DEX quoter for this pool; returns the output amount and the fee that was applied
+    #[instrument(name = "quote", skip_all, fields(dex = self.dex_name(), pool = self.pair_address(), direction, amount_in))]
+    pub async fn simulate<M: Middleware + 'static>(
+        &self,
+        amount_in: f64,
+        direction: &str,
+        provider: Arc<M>,
+    ) -> ScanResult<(f64, u32)> {
+        let quote = self.quote(amount_in, direction, provider).await;
+        if let Err(e) = &quote {
+            POOL_FAILURES.with_label_values(&[self.dex_name(), e.kind().name()]).inc();
+        }
+        quote
+    }
+
+    async fn quote<M: Middleware + 'static>(
+        &self,
+        amount_in: f64,
+        direction: &str,
+        provider: Arc<M>,
+    ) -> ScanResult<(f64, u32)> {
+        match self {
+            PoolState::Uniswap(p) => {
+                let amount_out = simulate_uniswap_trade_with_slippage(
+                    p.token0,
+                    p.token0_decimals,
+                    p.token1,
+                    p.token1_decimals,
+                    amount_in,
+                    p.fee,
+                    p.base,
+                    direction,
+                    provider,
+                )
+                .await?;
+                Ok((amount_out, p.fee))
+            }
+            PoolState::PancakeSwap(p) => {
+                let amount_out = simulate_pancake_trade_with_slippage(
+                    p.token0,
+                    p.token0_decimals,
+                    p.token1,
+                    p.token1_decimals,
+                    amount_in,
+                    p.fee,
+                    p.base,
+                    direction,
+                    provider,
+                )
+                .await?;
+                Ok((amount_out, p.fee))
+            }
+            PoolState::Camelot(p) => {
+                simulate_camelot_trade_with_slippage(
+                    p.token0,
+                    p.token0_decimals,
+                    p.token1,
+                    p.token1_decimals,
+                    amount_in,
+                    p.base,
+                    direction,
+                    provider,
+                )
+                .await
+            }
+        }
+    }
+}
+
+// Serializable pool state. Prices are base token per token and liquidity is TVL in the
+// base token, matching the exchange info structs; sqrt_price_x96 is kept as a decimal string
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolSnapshot {
+    pub dex: String,
+    pub pool_address: String,
+    pub token0: String,
+    pub token1: String,
+    pub token0_decimals: u8,
+    pub token1_decimals: u8,
+    pub sqrt_price_x96: String,
+    pub active_liquidity: u128,
+    pub tick: i32,
+    // Initialized ticks near the price and their liquidityNet; older recordings have none
+    // and their depth assumes constant liquidity
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub initialized_ticks: Vec<(i32, i128)>,
+    // token0 -> token1 fee; token1 -> token0 uses fee_otz where the DEX has directional fees
+    pub fee: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_otz: Option<u32>,
+    pub price: f64,
+    pub liquidity: u128,
+    // Recordings made before per-pair bases were all quoted in WETH
+    #[serde(default = "default_base_token")]
+    pub base_token: String,
+    pub weth_usdc: f64,
+    #[serde(default)]
+    pub base_usd: f64,
+}
+
+fn default_base_token() -> String {
+    format!("0x{}", WETH_ADDRESS.to_lowercase())
+}
+
+impl PoolSnapshot {
+    // USD price of the base token, which is WETH/USDC for older recordings
+    pub fn base_usd(&self) -> f64 {
+        if self.base_usd > 0.0 { self.base_usd } else { self.weth_usdc }
+    }
+
+    pub fn fee_for(&self, zero_for_one: bool) -> u32 {
+        if zero_for_one { self.fee } else { self.fee_otz.unwrap_or(self.fee) }
+    }
+
+    // USD price of either of the pool's tokens, through the base token
+    pub fn token_usd(&self, token: &str) -> f64 {
+        let base_usd = self.base_usd();
+        if token.eq_ignore_ascii_case(&self.base_token) { base_usd } else { self.price * base_usd }
+    }
+
+    // Depth around the current price, with the pool's tokens valued through the base token
+    pub fn depth_ladder(&self) -> DepthLadder {
+        let token_usd = (self.token_usd(&self.token0), self.token_usd(&self.token1));
+        depth_ladder(
+            self.tick,
+            self.active_liquidity,
+            &self.initialized_ticks,
+            (self.token0_decimals, self.token1_decimals),
+            token_usd,
+        )
+    }
+}
+
+// One pool's fetch for one block, run once however many pairings ask for it
+struct BlockFetch {
+    block_number: u64,
+    base: EthersAddress,
+    state: Arc<OnceCell<ScanResult<PoolState>>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CachedPool {
+    pub pair_name: String,
+    pub state: PoolState,
+    pub block_number: u64,
+    pub weth_usdc: f64,
+    pub base_usd: f64,
+}
+
+// Pool states the monitors fetched on the latest block, keyed by lowercase pool address.
+// When a recorder or exporter is attached every accepted state is also written to the
+// recording or the pool export. Every accepted state's fee goes to the fee registry.
+pub struct PoolCache {
+    pools: RwLock<HashMap<String, CachedPool>>,
+    // Each pool's fetch on the latest block it was asked for, by lowercase address
+    fetches: Mutex<HashMap<String, BlockFetch>>,
+    recorder: Option<BlockRecorder>,
+    exporter: Option<SnapshotExporter>,
+    fees: Arc<FeeRegistry>,
+}
+
+impl PoolCache {
+    pub fn new(recorder: Option<BlockRecorder>, exporter: Option<SnapshotExporter>) -> Self {
+        Self {
+            pools: RwLock::new(HashMap::new()),
+            fetches: Mutex::new(HashMap::new()),
+            recorder,
+            exporter,
+            fees: Arc::new(FeeRegistry::new()),
+        }
+    }
+
+    pub fn fees(&self) -> Arc<FeeRegistry> {
+        self.fees.clone()
+    }
+
+    // The pool's full state on a block. A pool is in as many pairings as it has partner
+    // pools, so the first pairing to ask fetches it and the others, including those that ask
+    // while that fetch runs, share the result, failures included. A monitor still working on
+    // an older block fetches for itself.
+    pub async fn fetch<M: Middleware + 'static>(
+        &self,
+        dex: Dex,
+        pool_address: &str,
+        base: EthersAddress,
+        block_number: u64,
+        provider: Arc<M>,
+    ) -> ScanResult<PoolState> {
+        let key = pool_address.to_lowercase();
+        let shared = {
+            let mut fetches = lock(&self.fetches);
+            match fetches.get(&key) {
+                Some(fetch) if fetch.block_number == block_number && fetch.base == base => Some(fetch.state.clone()),
+                Some(fetch) if fetch.block_number > block_number => None,
+                _ => {
+                    let state = Arc::new(OnceCell::new());
+                    fetches.insert(key, BlockFetch { block_number, base, state: state.clone() });
+                    Some(state)
+                }
+            }
+        };
+        match shared {
+            Some(state) => state.get_or_init(|| PoolState::fetch(dex, pool_address, base, provider)).await.clone(),
+            None => PoolState::fetch(dex, pool_address, base, provider).await,
+        }
+    }
+
+    pub fn update(&self, pool_address: &str, pair_name: &str, state: PoolState, block_number: u64, weth_usdc: f64, base_usd: f64) {
+        let mut pools = write_lock(&self.pools);
+        let key = pool_address.to_lowercase();
+        // Monitors share pools, so never let an older block overwrite a newer one
+        // and record each pool at most once per block
+        if pools.get(&key).is_some_and(|existing| existing.block_number >= block_number) {
+            return;
+        }
+
+        self.fees.observe(state.dex(), pair_name, &key, (state.fee_for(true), state.fee_for(false)), block_number, "state");
+        if self.recorder.is_some() || self.exporter.is_some() {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64;
+            let snapshot = state.snapshot(weth_usdc, base_usd);
+            if let Some(exporter) = &self.exporter {
+                exporter.pool(PoolRow {
+                    block_number,
+                    timestamp_ms: timestamp,
+                    pair_name: pair_name.to_string(),
+                    pool: snapshot.clone(),
+                });
+            }
+            if let Some(recorder) = &self.recorder {
+                recorder.record(RecordedPool {
+                    block_number,
+                    timestamp,
+                    pair_name: pair_name.to_string(),
+                    pool: snapshot,
+                });
+            }
+        }
+
+        pools.insert(key, CachedPool {
+            pair_name: pair_name.to_string(),
+            state,
+            block_number,
+            weth_usdc,
+            base_usd,
+        });
+    }
+
+    // Applies a Camelot Fee event to the cached state, unless a newer state already replaced it
+    pub fn update_camelot_fees(&self, pool_address: &str, block_number: u64, fee_zto: u32, fee_otz: u32) {
+        let mut pools = write_lock(&self.pools);
+        let key = pool_address.to_lowercase();
+        let Some(cached) = pools.get_mut(&key).filter(|cached| cached.block_number <= block_number) else {
+            return;
+        };
+        if let PoolState::Camelot(p) = &mut cached.state {
+            p.fee_zto = fee_zto;
+            p.fee_otz = fee_otz;
+            self.fees.observe(Dex::Camelot, &cached.pair_name, &key, (fee_zto, fee_otz), block_number, "event");
+        }
+    }
+
+    pub fn camelot_pools(&self) -> Vec<EthersAddress> {
+        read_lock(&self.pools)
+            .iter()
+            .filter(|(_, cached)| matches!(cached.state, PoolState::Camelot(_)))
+            .filter_map(|(address, _)| address.parse().ok())
+            .collect()
+    }
+
+    pub fn get(&self, pool_address: &str) -> Option<CachedPool> {
+        read_lock(&self.pools)
+            .get(&pool_address.to_lowercase())
+            .cloned()
+    }
+
+    pub fn all(&self) -> Vec<CachedPool> {
+        read_lock(&self.pools).values().cloned().collect()
+    }
+}
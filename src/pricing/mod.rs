@@ -0,0 +1,68 @@
+use ethers::prelude::Address as EthersAddress;
+use ethers::providers::Middleware;
+use std::sync::Arc;
+
+use crate::config::{BASE_TOKENS, USD_STABLECOINS, WBTC_ADDRESS, WBTC_WETH_REFERENCE_POOL, WETH_ADDRESS};
+use crate::error::{ScanError, ScanResult};
+use crate::exchange::FetchDepth;
+use crate::exchange::uniswap::get_uniswap_info;
+
+pub fn token_address(address: &str) -> EthersAddress {
+    format!("0x{}", address.trim_start_matches("0x"))
+        .parse()
+        .unwrap_or_default()
+}
+
+pub fn weth() -> EthersAddress {
+    token_address(WETH_ADDRESS)
+}
+
+// Resolves the "Base" of a pair in data.json, given as a symbol from BASE_TOKENS or an
+// address. Pairs without one are quoted in WETH.
+pub fn resolve_base(base: Option<&str>) -> Result<EthersAddress, Box<dyn std::error::Error>> {
+    let Some(base) = base else {
+        return Ok(weth());
+    };
+    if let Some((_, address)) = BASE_TOKENS.iter().find(|(symbol, _)| symbol.eq_ignore_ascii_case(base)) {
+        return Ok(token_address(address));
+    }
+    base.parse::<EthersAddress>()
+        .map_err(|_| format!("Unknown base token {}", base).into())
+}
+
+// Symbol for logs, falling back to the address for tokens outside BASE_TOKENS
+pub fn base_symbol(base: EthersAddress) -> String {
+    BASE_TOKENS
+        .iter()
+        .find(|(_, address)| token_address(address) == base)
+        .map(|(symbol, _)| symbol.to_string())
+        .unwrap_or_else(|| format!("{:?}", base))
+}
+
+// USD price of a base token that needs no call: stablecoins are $1 and WETH is the
+// block's reference price
+pub fn known_usd_price(token: EthersAddress, weth_usd: f64) -> Option<f64> {
+    if USD_STABLECOINS.iter().any(|stable| token_address(stable) == token) {
+        return Some(1.0);
+    }
+    (token == weth()).then_some(weth_usd)
+}
+
+// USD price of a base token. WBTC is priced through its WETH reference pool. Monitors go
+// through ReferencePriceService::base_usd, which fetches it once per block.
+pub async fn usd_price<M: Middleware + 'static>(
+    token: EthersAddress,
+    weth_usd: f64,
+    provider: Arc<M>,
+) -> ScanResult<f64> {
+    if let Some(price) = known_usd_price(token, weth_usd) {
+        return Ok(price);
+    }
+
+    if token == token_address(WBTC_ADDRESS) {
+        let reference = get_uniswap_info(WBTC_WETH_REFERENCE_POOL.to_lowercase(), weth(), FetchDepth::PriceOnly, provider).await?;
+        return Ok(reference.price * weth_usd);
+    }
+
+    Err(ScanError::Config(format!("No USD price source for token {:?}", token)))
+}
@@ -0,0 +1,304 @@
+use bincode::{Decode, Encode};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{UnixListener, UnixStream};
+use tracing::{error, info, warn};
+
+use crate::config::ACCEPT_RETRY_DELAY;
+use crate::pool_state::{CachedPool, PoolCache, PoolState};
+use crate::settings::settings;
+use crate::trade::local::simulate_local_trade;
+
+pub const QUOTE_STATUS_OK: u8 = 0;
+pub const QUOTE_STATUS_UNKNOWN_POOL: u8 = 1;
+pub const QUOTE_STATUS_TOKEN_MISMATCH: u8 = 2;
+pub const QUOTE_STATUS_SIMULATION_FAILED: u8 = 3;
+// The frame was too large or didn't decode to a QuoteRequest
+pub const QUOTE_STATUS_BAD_REQUEST: u8 = 4;
+
+// Largest frame we accept from a client; requests are a few dozen bytes
+const MAX_FRAME_SIZE: u32 = 1024;
+
+#[derive(Encode, Decode, Debug, Clone, Default)]
+#[repr(C)]
+pub struct QuoteRequest {
+    pub buy_pool: [u8; 20],
+    pub sell_pool: [u8; 20],
+    // In the pools' base token, which is WETH unless the pair sets another base
+    pub amount_base: f64,
+}
+
+#[derive(Encode, Decode, Debug, Clone, Default)]
+#[repr(C)]
+pub struct QuoteResponse {
+    pub status: u8,
+    pub block_number: u64,
+    pub buy_fee: u32,
+    pub sell_fee: u32,
+    pub buy_amount_out: f64,
+    pub sell_amount_out: f64,
+    // In the base token, like amount_base
+    pub profit_base: f64,
+    pub profit_usdc: f64,
+    // The pools' base token and the token bought with it, zero unless the status is OK
+    pub base_token: [u8; 20],
+    pub token: [u8; 20],
+}
+
+// Serves on-demand quotes over a Unix socket. Each frame is a big-endian u32 length
+// followed by a bincode-encoded QuoteRequest; the reply uses the same framing. An empty
+// socket path turns the service off, and so does a socket that can't be bound: quotes are
+// optional, so neither stops the scanner.
+pub async fn handle_quote_service(pool_cache: Arc<PoolCache>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let socket_path = settings().ipc.quote_socket.clone();
+    if socket_path.is_empty() {
+        return Ok(());
+    }
+
+    // Remove a stale socket left behind by a previous run
+    let _ = std::fs::remove_file(&socket_path);
+    let listener = match UnixListener::bind(&socket_path) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("❌ Quote service disabled, failed to bind {}: {}", socket_path, e);
+            return Ok(());
+        }
+    };
+
+    info!("📨 Quote service listening on {}", socket_path);
+
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            // e.g. out of file descriptors; the clients already connected keep being served
+            Err(e) => {
+                warn!("Quote service failed to accept a connection: {}", e);
+                tokio::time::sleep(ACCEPT_RETRY_DELAY).await;
+                continue;
+            }
+        };
+        let pool_cache = pool_cache.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_client(stream, pool_cache).await {
+                error!("❌ Quote client error: {}", e);
+            }
+        });
+    }
+}
+
+async fn handle_client(
+    mut stream: UnixStream,
+    pool_cache: Arc<PoolCache>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let bad_request = QuoteResponse { status: QUOTE_STATUS_BAD_REQUEST, ..Default::default() };
+    loop {
+        let frame_len = match stream.read_u32().await {
+            Ok(len) => len,
+            // Client closed the connection
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(Box::new(e)),
+        };
+        // The body isn't read, so the stream can't be resynchronised; answer and hang up
+        if frame_len > MAX_FRAME_SIZE {
+            write_response(&mut stream, &bad_request).await?;
+            return Err(format!("Quote request frame too large: {} bytes", frame_len).into());
+        }
+
+        let mut buf = vec![0u8; frame_len as usize];
+        stream.read_exact(&mut buf).await?;
+        let response = match bincode::decode_from_slice::<QuoteRequest, _>(&buf, bincode::config::standard()) {
+            Ok((request, _)) => quote(&request, &pool_cache),
+            Err(e) => {
+                warn!("Undecodable quote request: {}", e);
+                bad_request.clone()
+            }
+        };
+        write_response(&mut stream, &response).await?;
+    }
+}
+
+async fn write_response(
+    stream: &mut UnixStream,
+    response: &QuoteResponse,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let payload = bincode::encode_to_vec(response, bincode::config::standard())?;
+    stream.write_u32(payload.len() as u32).await?;
+    stream.write_all(&payload).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+// Prices both legs locally on the pools' cached states, so the quote is exactly what the
+// scanner saw: block_number is the older of the two pools' blocks, and nothing newer is
+// mixed in. Like the backtest, the local model keeps the in-range liquidity constant.
+fn quote(request: &QuoteRequest, pool_cache: &PoolCache) -> QuoteResponse {
+    let buy_addr = format!("0x{}", hex::encode(request.buy_pool));
+    let sell_addr = format!("0x{}", hex::encode(request.sell_pool));
+
+    let (buy_pool, sell_pool) = match (pool_cache.get(&buy_addr), pool_cache.get(&sell_addr)) {
+        (Some(buy), Some(sell)) => (buy, sell),
+        _ => {
+            return QuoteResponse {
+                status: QUOTE_STATUS_UNKNOWN_POOL,
+                ..Default::default()
+            };
+        }
+    };
+
+    // Both legs must trade the same base token against the same token
+    let other_token = |state: &PoolState| {
+        let (token0, token1) = state.tokens();
+        if token0 == state.base() { token1 } else { token0 }
+    };
+    if buy_pool.state.base() != sell_pool.state.base()
+        || other_token(&buy_pool.state) != other_token(&sell_pool.state)
+    {
+        return QuoteResponse {
+            status: QUOTE_STATUS_TOKEN_MISMATCH,
+            ..Default::default()
+        };
+    }
+
+    let block_number = buy_pool.block_number.min(sell_pool.block_number);
+    let base_token = buy_pool.state.base();
+    let token = other_token(&buy_pool.state);
+
+    // BUY spends the base token, SELL spends the other one
+    let leg = |pool: &CachedPool, amount_in: f64, direction: &str| {
+        let snapshot = pool.state.snapshot(pool.weth_usdc, pool.base_usd);
+        let token0_is_base = pool.state.tokens().0 == base_token;
+        let zero_for_one = if direction == "BUY" { token0_is_base } else { !token0_is_base };
+        let fee = pool.state.fee_for(zero_for_one);
+        simulate_local_trade(&snapshot, amount_in, direction).map(|amount_out| (amount_out, fee))
+    };
+
+    let (buy_amount_out, buy_fee) = match leg(&buy_pool, request.amount_base, "BUY") {
+        Ok(result) => result,
+        Err(e) => {
+            warn!("Quote buy leg failed on {} {}: {}", buy_pool.pair_name, buy_addr, e);
+            return QuoteResponse {
+                status: QUOTE_STATUS_SIMULATION_FAILED,
+                block_number,
+                ..Default::default()
+            };
+        }
+    };
+
+    let (sell_amount_out, sell_fee) = match leg(&sell_pool, buy_amount_out, "SELL") {
+        Ok(result) => result,
+        Err(e) => {
+            warn!("Quote sell leg failed on {} {}: {}", sell_pool.pair_name, sell_addr, e);
+            return QuoteResponse {
+                status: QUOTE_STATUS_SIMULATION_FAILED,
+                block_number,
+                ..Default::default()
+            };
+        }
+    };
+
+    let profit_base = sell_amount_out - request.amount_base;
+
+    QuoteResponse {
+        status: QUOTE_STATUS_OK,
+        block_number,
+        buy_fee,
+        sell_fee,
+        buy_amount_out,
+        sell_amount_out,
+        profit_base,
+        profit_usdc: profit_base * sell_pool.base_usd,
+        base_token: base_token.0,
+        token: token.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::uniswap::UniswapPoolInfo;
+    use ethers::prelude::{Address as EthersAddress, U256};
+
+    const PRICE: f64 = 0.0003;
+
+    fn address(byte: u8) -> EthersAddress {
+        EthersAddress::from([byte; 20])
+    }
+
+    // A deep 0.05% pool of token against WETH at weth_per_token
+    fn pool(pool: u8, token: EthersAddress, weth_per_token: f64) -> PoolState {
+        let weth = address(0xee);
+        PoolState::Uniswap(UniswapPoolInfo {
+            price: weth_per_token,
+            liquidity: 0,
+            tick: (weth_per_token.ln() / 1.0001f64.ln()).floor() as i32,
+            sqrt_price_x96: U256::from_dec_str(&format!("{:.0}", weth_per_token.sqrt() * 2f64.powi(96))).unwrap(),
+            active_liquidity: 10u128.pow(26),
+            initialized_ticks: Vec::new(),
+            token0: token,
+            token1: weth,
+            token0_decimals: 18,
+            token1_decimals: 18,
+            token0_amount: U256::zero(),
+            token1_amount: U256::zero(),
+            token0_amount_adjusted: 0.0,
+            token1_amount_adjusted: 0.0,
+            pair_address: format!("0x{}", hex::encode([pool; 20])),
+            base: weth,
+            fee: 500,
+        })
+    }
+
+    async fn send(client: &mut UnixStream, frame: &[u8]) -> QuoteResponse {
+        client.write_u32(frame.len() as u32).await.unwrap();
+        client.write_all(frame).await.unwrap();
+        read_response(client).await
+    }
+
+    async fn read_response(client: &mut UnixStream) -> QuoteResponse {
+        let mut buf = vec![0u8; client.read_u32().await.unwrap() as usize];
+        client.read_exact(&mut buf).await.unwrap();
+        bincode::decode_from_slice(&buf, bincode::config::standard()).unwrap().0
+    }
+
+    fn request(buy_pool: u8, sell_pool: u8, amount_base: f64) -> Vec<u8> {
+        let request = QuoteRequest { buy_pool: [buy_pool; 20], sell_pool: [sell_pool; 20], amount_base };
+        bincode::encode_to_vec(&request, bincode::config::standard()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn quotes_cached_pools_over_the_socket() {
+        let (arb, gmx) = (address(0x11), address(0x22));
+        let cache = Arc::new(PoolCache::new(None, None));
+        let pools = [(0xaa, arb, PRICE, 100), (0xbb, arb, PRICE * 1.02, 101), (0xcc, gmx, PRICE, 101)];
+        for (byte, token, price, block) in pools {
+            let address = format!("0x{}", hex::encode([byte; 20]));
+            cache.update(&address, "ARB/WETH", pool(byte, token, price), block, 3000.0, 3000.0);
+        }
+        let (mut client, server) = UnixStream::pair().unwrap();
+        let service = tokio::spawn(handle_client(server, cache));
+
+        // Bought on the cheaper pool, sold 2% higher, less two 0.05% fees
+        let quote = send(&mut client, &request(0xaa, 0xbb, 1.0)).await;
+        assert_eq!(quote.status, QUOTE_STATUS_OK);
+        // Priced on the cached states, the older of which is from block 100
+        assert_eq!((quote.block_number, quote.buy_fee, quote.sell_fee), (100, 500, 500));
+        assert!((quote.buy_amount_out - 0.9995 / PRICE).abs() < 0.1, "{}", quote.buy_amount_out);
+        assert!((quote.profit_base - (1.02 * 0.9995 * 0.9995 - 1.0)).abs() < 1e-5, "{}", quote.profit_base);
+        assert_eq!(quote.profit_usdc, quote.profit_base * 3000.0);
+        assert_eq!((quote.base_token, quote.token), (address(0xee).0, arb.0));
+
+        assert_eq!(send(&mut client, &request(0xaa, 0x99, 1.0)).await.status, QUOTE_STATUS_UNKNOWN_POOL);
+        assert_eq!(send(&mut client, &request(0xaa, 0xcc, 1.0)).await.status, QUOTE_STATUS_TOKEN_MISMATCH);
+
+        // A frame that doesn't decode is answered and the connection stays usable
+        assert_eq!(send(&mut client, &[0xff; 3]).await.status, QUOTE_STATUS_BAD_REQUEST);
+        assert_eq!(send(&mut client, &request(0xaa, 0xbb, 1.0)).await.status, QUOTE_STATUS_OK);
+
+        // An oversized frame is answered, then the connection is dropped
+        client.write_u32(MAX_FRAME_SIZE + 1).await.unwrap();
+        assert_eq!(read_response(&mut client).await.status, QUOTE_STATUS_BAD_REQUEST);
+        let error = service.await.unwrap().unwrap_err();
+        assert_eq!(error.to_string(), "Quote request frame too large: 1025 bytes");
+        assert_eq!(client.read_u8().await.unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,96 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::sync::mpsc;
+use std::thread;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::pool_state::PoolSnapshot;
+use crate::settings::settings;
+
+// One line of a recording: the state of a single pool as seen on a given block
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecordedPool {
+    pub block_number: u64,
+    pub timestamp: u64,
+    pub pair_name: String,
+    pub pool: PoolSnapshot,
+}
+
+// Appends pool states to a JSON lines file. Writes happen on a dedicated thread
+// so the block handlers never wait on disk I/O.
+pub struct BlockRecorder {
+    tx: mpsc::Sender<RecordedPool>,
+}
+
+impl BlockRecorder {
+    // Enabled by [recording] blocks_file or RECORD_BLOCKS_FILE
+    pub fn from_settings() -> io::Result<Option<Self>> {
+        let path = settings().recording.blocks_file.clone();
+        if path.is_empty() {
+            return Ok(None);
+        }
+        Self::new(&path).map(Some)
+    }
+
+    pub fn new(path: &str) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        let (tx, rx) = mpsc::channel::<RecordedPool>();
+
+        info!("📼 Recording pool states to {}", path);
+
+        thread::spawn(move || {
+            let mut writer = BufWriter::new(file);
+            while let Ok(record) = rx.recv() {
+                if let Err(e) = write_record(&mut writer, &record) {
+                    error!("❌ Failed to record pool state: {}", e);
+                    continue;
+                }
+                // Drain whatever else is queued before flushing
+                while let Ok(record) = rx.try_recv() {
+                    if let Err(e) = write_record(&mut writer, &record) {
+                        error!("❌ Failed to record pool state: {}", e);
+                    }
+                }
+                if let Err(e) = writer.flush() {
+                    error!("❌ Failed to flush recording: {}", e);
+                }
+            }
+        });
+
+        Ok(Self { tx })
+    }
+
+    pub fn record(&self, record: RecordedPool) {
+        // The writer thread only stops if the process is shutting down
+        let _ = self.tx.send(record);
+    }
+}
+
+fn write_record(writer: &mut BufWriter<File>, record: &RecordedPool) -> io::Result<()> {
+    serde_json::to_writer(&mut *writer, record)?;
+    writer.write_all(b"\n")
+}
+
+pub fn load_recording(path: &str) -> io::Result<Vec<RecordedPool>> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+    let mut records = Vec::new();
+
+    for (line_number, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<RecordedPool>(&line) {
+            Ok(record) => records.push(record),
+            // A crash can leave a truncated last line behind
+            Err(e) => warn!("Skipping malformed record on line {}: {}", line_number + 1, e),
+        }
+    }
+
+    Ok(records)
+}
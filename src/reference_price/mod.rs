@@ -0,0 +1,310 @@
+use ethers::providers::Middleware;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::config::WETH_USDC_REFERENCE_POOLS;
+use crate::error::ScanResult;
+use crate::exchange::model::Dex;
+use crate::ipc_handle::PriceReference;
+use crate::metrics::{REFERENCE_BLOCK, REFERENCE_WETH_USD};
+use crate::oracle::{ChainlinkOracle, deviation};
+use crate::settings::settings;
+use crate::pool_state::PoolState;
+use crate::pricing::{known_usd_price, usd_price, weth};
+use ethers::prelude::Address as EthersAddress;
+
+type SendError = Box<dyn std::error::Error + Send + Sync>;
+
+// How the per-block WETH/USD price is combined from the reference pools
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReferenceMethod {
+    #[default]
+    LiquidityWeighted,
+    Median,
+}
+
+impl ReferenceMethod {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "liquidity-weighted" => Some(ReferenceMethod::LiquidityWeighted),
+            "median" => Some(ReferenceMethod::Median),
+            _ => None,
+        }
+    }
+
+    // [reference] method, or REFERENCE_PRICE_METHOD; validated when the settings load
+    pub fn from_settings() -> Self {
+        Self::from_name(&settings().reference.method).unwrap_or_default()
+    }
+
+    // Code sent over IPC
+    pub fn code(&self) -> u8 {
+        match self {
+            ReferenceMethod::LiquidityWeighted => 0,
+            ReferenceMethod::Median => 1,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReferenceMethod::LiquidityWeighted => "liquidity-weighted",
+            ReferenceMethod::Median => "median",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReferencePrice {
+    pub weth_usd: f64,
+    pub method: ReferenceMethod,
+    // Reference pools that answered for this price
+    pub pool_count: usize,
+    pub block_number: u64,
+    pub updated_at_ms: u64,
+    // Chainlink ETH/USD for the block when the oracle check is enabled
+    pub oracle_usd: Option<f64>,
+    // Set when the price is too far from Chainlink; the block's opportunities aren't published
+    pub suspect: Option<String>,
+}
+
+impl ReferencePrice {
+    pub fn age_ms(&self) -> u64 {
+        now_ms().saturating_sub(self.updated_at_ms)
+    }
+
+    pub fn to_ipc(&self) -> PriceReference {
+        PriceReference {
+            weth_usd: self.weth_usd,
+            method: self.method.code(),
+            pool_count: self.pool_count as u8,
+            block_number: self.block_number,
+            updated_at_ms: self.updated_at_ms,
+            oracle_usd: self.oracle_usd.unwrap_or_default(),
+        }
+    }
+
+    pub fn source(&self) -> String {
+        let pools = format!("{} over {}/{} pools", self.method.name(), self.pool_count, WETH_USDC_REFERENCE_POOLS.len());
+        match self.oracle_usd {
+            Some(oracle_usd) => format!(
+                "{}, Chainlink {:.2} ({:.2}%)",
+                pools,
+                oracle_usd,
+                deviation(self.weth_usd, oracle_usd) * 100.0
+            ),
+            None => pools,
+        }
+    }
+}
+
+// One WETH/USD price per block shared by every monitor. The first task to ask for a new
+// block fetches the reference pools; the others wait on the lock and reuse the result.
+// With the Chainlink cross-check enabled the price is also compared against the ETH/USD feed.
+// Base tokens other than WETH and stablecoins are priced the same way, once per block.
+pub struct ReferencePriceService {
+    method: ReferenceMethod,
+    oracle: Option<ChainlinkOracle>,
+    latest: Mutex<Option<ReferencePrice>>,
+    // USD price of each other base token and the block it was fetched for
+    bases: Mutex<HashMap<EthersAddress, (u64, f64)>>,
+}
+
+impl ReferencePriceService {
+    pub fn from_settings() -> Self {
+        Self {
+            method: ReferenceMethod::from_settings(),
+            oracle: ChainlinkOracle::from_settings(),
+            latest: Mutex::new(None),
+            bases: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // USD price of a pair's base token on the reference's block
+    pub async fn base_usd<M: Middleware + 'static>(
+        &self,
+        token: EthersAddress,
+        reference: &ReferencePrice,
+        provider: Arc<M>,
+    ) -> ScanResult<f64> {
+        if let Some(price) = known_usd_price(token, reference.weth_usd) {
+            return Ok(price);
+        }
+        let mut bases = self.bases.lock().await;
+        if let Some((_, price)) = bases.get(&token).filter(|(block, _)| *block == reference.block_number) {
+            return Ok(*price);
+        }
+        let price = usd_price(token, reference.weth_usd, provider).await?;
+        bases.insert(token, (reference.block_number, price));
+        Ok(price)
+    }
+
+    // Reason a pair's DEX-derived USD price for a token is suspect, if the oracle check is
+    // enabled and Chainlink has a feed for it
+    pub async fn check_token<M: Middleware + 'static>(
+        &self,
+        token: EthersAddress,
+        dex_usd: f64,
+        block_number: u64,
+        provider: Arc<M>,
+    ) -> Option<String> {
+        self.oracle.as_ref()?.check(token, dex_usd, block_number, provider).await
+    }
+
+    pub async fn for_block<M: Middleware + 'static>(
+        &self,
+        block_number: u64,
+        provider: Arc<M>,
+    ) -> Result<ReferencePrice, SendError> {
+        let mut latest = self.latest.lock().await;
+        if let Some(reference) = latest.as_ref().filter(|r| r.block_number >= block_number) {
+            return Ok(reference.clone());
+        }
+
+        match fetch_reference_price(self.method, block_number, provider.clone()).await {
+            Ok(mut reference) => {
+                if let Some(oracle) = &self.oracle {
+                    reference.oracle_usd = oracle.usd_price(weth(), block_number, provider.clone()).await;
+                    reference.suspect = oracle.check(weth(), reference.weth_usd, block_number, provider).await;
+                }
+                if let Some(reason) = &reference.suspect {
+                    warn!("🚩 Block #{} is suspect, holding opportunities: {}", block_number, reason);
+                }
+                info!(
+                    "💵 WETH/USD {:.2} at #{} ({})",
+                    reference.weth_usd,
+                    reference.block_number,
+                    reference.source()
+                );
+                REFERENCE_WETH_USD.set(reference.weth_usd);
+                REFERENCE_BLOCK.set(reference.block_number as i64);
+                *latest = Some(reference.clone());
+                Ok(reference)
+            }
+            // Keep trading on the last price for a few blocks rather than stalling every monitor
+            Err(e) => match latest.as_ref() {
+                Some(previous) if previous.block_number + settings().thresholds.max_reference_price_age >= block_number => {
+                    warn!(
+                        "⚠️ WETH/USD reference failed at #{} ({}), reusing #{} ({} ms old)",
+                        block_number,
+                        e,
+                        previous.block_number,
+                        previous.age_ms()
+                    );
+                    Ok(previous.clone())
+                }
+                _ => Err(format!("No WETH/USD reference price for #{}: {}", block_number, e).into()),
+            },
+        }
+    }
+}
+
+// Fetches every reference pool and combines the prices that came back
+pub async fn fetch_reference_price<M: Middleware + 'static>(
+    method: ReferenceMethod,
+    block_number: u64,
+    provider: Arc<M>,
+) -> Result<ReferencePrice, SendError> {
+    let fetched = futures::future::join_all(WETH_USDC_REFERENCE_POOLS.iter().filter_map(|(dex, address)| {
+        let dex = Dex::from_name(dex)?;
+        let provider = provider.clone();
+        Some(async move { PoolState::fetch_price(dex, address, weth(), provider).await })
+    }))
+    .await;
+
+    // WETH-based, so price is WETH per USDC. Each pool is weighted by its ±1% depth in USD at
+    // its own price, rather than its TVL, which also counts liquidity parked far from the price.
+    // The pools are fetched without their ticks, so that depth is the active liquidity's.
+    let quotes: Vec<(f64, f64)> = fetched
+        .into_iter()
+        .filter_map(|state| state.ok())
+        .filter(|state| state.price() > 0.0)
+        .map(|state| {
+            let weth_usd = 1.0 / state.price();
+            (weth_usd, state.depth_ladder(weth_usd).tier_depth())
+        })
+        .collect();
+    if quotes.is_empty() {
+        return Err("no reference pool could be fetched".into());
+    }
+
+    let weth_usd = combine(method, &quotes);
+
+    Ok(ReferencePrice {
+        weth_usd,
+        method,
+        pool_count: quotes.len(),
+        block_number,
+        updated_at_ms: now_ms(),
+        oracle_usd: None,
+        suspect: None,
+    })
+}
+
+// Combines (price, weight) quotes; weights that add up to nothing fall back to the median
+fn combine(method: ReferenceMethod, quotes: &[(f64, f64)]) -> f64 {
+    match method {
+        ReferenceMethod::LiquidityWeighted => weighted_price(quotes).unwrap_or_else(|| median_price(quotes)),
+        ReferenceMethod::Median => median_price(quotes),
+    }
+}
+
+fn weighted_price(quotes: &[(f64, f64)]) -> Option<f64> {
+    let total_weight: f64 = quotes.iter().map(|(_, weight)| weight).sum();
+    (total_weight > 0.0).then(|| quotes.iter().map(|(price, weight)| price * weight).sum::<f64>() / total_weight)
+}
+
+fn median_price(quotes: &[(f64, f64)]) -> f64 {
+    let mut prices: Vec<f64> = quotes.iter().map(|(price, _)| *price).collect();
+    prices.sort_by(|a, b| a.total_cmp(b));
+    let mid = prices.len() / 2;
+    if prices.len() % 2 == 0 {
+        (prices[mid - 1] + prices[mid]) / 2.0
+    } else {
+        prices[mid]
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ReferenceMethod, combine, median_price, weighted_price};
+
+    #[test]
+    fn median_of_odd_and_even_counts() {
+        assert_eq!(median_price(&[(3010.0, 1.0), (2990.0, 1.0), (3000.0, 1.0)]), 3000.0);
+        assert_eq!(median_price(&[(3010.0, 1.0), (2990.0, 1.0), (3000.0, 1.0), (3020.0, 1.0)]), 3005.0);
+        assert_eq!(median_price(&[(3000.0, 0.0)]), 3000.0);
+    }
+
+    #[test]
+    fn weighted_price_follows_the_deepest_pools() {
+        let quotes = [(3000.0, 3_000_000.0), (3010.0, 1_000_000.0)];
+        assert_eq!(weighted_price(&quotes), Some(3002.5));
+        assert_eq!(weighted_price(&[(3000.0, 0.0), (3010.0, 0.0)]), None);
+    }
+
+    #[test]
+    fn zero_total_weight_falls_back_to_the_median() {
+        let quotes = [(2990.0, 0.0), (3000.0, 0.0), (3100.0, 0.0)];
+        assert_eq!(combine(ReferenceMethod::LiquidityWeighted, &quotes), 3000.0);
+        assert_eq!(combine(ReferenceMethod::Median, &[(2990.0, 5.0), (3000.0, 1.0), (3100.0, 1.0)]), 3000.0);
+    }
+
+    #[test]
+    fn shallow_outlier_barely_moves_the_price() {
+        // A pool with $1k of depth left at 2000 next to two deep ones at 3000
+        let quotes = [(3000.0, 2_000_000.0), (3000.0, 1_000_000.0), (2000.0, 1_000.0)];
+        let weighted = combine(ReferenceMethod::LiquidityWeighted, &quotes);
+        assert!((weighted - 3000.0).abs() < 0.5, "{}", weighted);
+        assert_eq!(combine(ReferenceMethod::Median, &quotes), 3000.0);
+    }
+}
@@ -0,0 +1,542 @@
+use ethers::prelude::{Address, U256};
+use ethers::providers::Middleware;
+use futures_util::StreamExt;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+use tracing::{Instrument, info, info_span, warn};
+
+use crate::config::MAX_ROUTE_HOPS;
+use crate::error::{ScanError, ScanResult, write_lock};
+use crate::exchange::model::Dex;
+use crate::failures::FailureTracker;
+use crate::metrics::{BLOCK_SECONDS, OPPORTUNITIES_FOUND, OPPORTUNITIES_SUSPECT};
+use crate::ipc_handle::{BestRoute, RouteLeg, StreamResults};
+use crate::pool_state::{PoolCache, PoolSnapshot, PoolState};
+use crate::pricing::weth;
+use crate::reference_price::ReferencePriceService;
+use crate::rpc_replay;
+use crate::trade::local::simulate_local_swap;
+use crate::websocket::block_source::BlockStream;
+use crate::settings::settings;
+use crate::tiers::{TierTable, tier_table};
+
+// Pool that only serves as a route leg (e.g. ARB/USDC), listed in pairs.route_pools_file
+#[derive(Deserialize, Debug, Clone)]
+pub struct RoutePool {
+    #[serde(rename = "Pair")]
+    pub pair: String,
+    #[serde(rename = "Dex")]
+    pub dex: String,
+    #[serde(rename = "PairAddress")]
+    pub pair_address: String,
+}
+
+pub fn load_route_pools() -> std::io::Result<Vec<RoutePool>> {
+    let file = match File::open(&settings().pairs.route_pools_file) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    Ok(serde_json::from_reader(BufReader::new(file))?)
+}
+
+// Whether "A/B" pair names link WETH into any cycle of three or more legs. With only
+// X/WETH pairs and no route pool between two other tokens, no route can ever form.
+pub fn has_route_cycle<'a>(pair_names: impl IntoIterator<Item = &'a str>) -> bool {
+    let mut links: HashMap<String, HashSet<String>> = HashMap::new();
+    for name in pair_names {
+        let Some((a, b)) = name.split_once('/') else {
+            continue;
+        };
+        let (a, b) = (a.trim().to_uppercase(), b.trim().to_uppercase());
+        if a != b {
+            links.entry(a.clone()).or_default().insert(b.clone());
+            links.entry(b).or_default().insert(a);
+        }
+    }
+
+    fn extend(links: &HashMap<String, HashSet<String>>, path: &mut Vec<String>) -> bool {
+        let token = path[path.len() - 1].clone();
+        for next in links.get(&token).into_iter().flatten() {
+            if *next == path[0] && path.len() >= 3 {
+                return true;
+            }
+            if path.len() < MAX_ROUTE_HOPS && !path.contains(next) {
+                path.push(next.clone());
+                if extend(links, path) {
+                    return true;
+                }
+                path.pop();
+            }
+        }
+        false
+    }
+    extend(&links, &mut vec!["WETH".to_string()])
+}
+
+// Swap through one pool in one direction. The weight is -ln(rate after fee),
+// so a cycle whose weights sum below zero returns more than it started with.
+#[derive(Debug, Clone)]
+pub struct Edge {
+    pub pool: usize,
+    pub token_in: String,
+    pub token_out: String,
+    pub weight: f64,
+}
+
+pub struct TokenGraph {
+    pub pools: Vec<PoolSnapshot>,
+    edges: HashMap<String, Vec<Edge>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RouteOpportunity {
+    pub legs: Vec<Edge>,
+    pub tier: u8,
+    pub amount_usdc: f64,
+    // Input of each leg followed by the final output, in token units
+    pub amounts: Vec<f64>,
+    pub profit_usdc: f64,
+}
+
+impl TokenGraph {
+    pub fn build(pools: Vec<PoolSnapshot>) -> Self {
+        let mut edges: HashMap<String, Vec<Edge>> = HashMap::new();
+
+        for (index, pool) in pools.iter().enumerate() {
+            let Some(price) = spot_price(pool) else {
+                continue;
+            };
+            let fee_factor_zto = 1.0 - pool.fee_for(true) as f64 / 1_000_000.0;
+            let fee_factor_otz = 1.0 - pool.fee_for(false) as f64 / 1_000_000.0;
+            let token0 = pool.token0.to_lowercase();
+            let token1 = pool.token1.to_lowercase();
+
+            edges.entry(token0.clone()).or_default().push(Edge {
+                pool: index,
+                token_in: token0.clone(),
+                token_out: token1.clone(),
+                weight: -(price * fee_factor_zto).ln(),
+            });
+            edges.entry(token1.clone()).or_default().push(Edge {
+                pool: index,
+                token_in: token1,
+                token_out: token0,
+                weight: -(fee_factor_otz / price).ln(),
+            });
+        }
+
+        Self { pools, edges }
+    }
+
+    // Every cycle of exactly `hops` legs from start back to start with a negative total
+    // weight. Pools and intermediate tokens are used at most once per cycle.
+    pub fn negative_cycles(&self, start: &str, hops: usize) -> Vec<Vec<Edge>> {
+        let mut cycles = Vec::new();
+        let mut path = Vec::new();
+        self.extend_path(start, start, hops, &mut path, &mut cycles);
+        cycles
+    }
+
+    fn extend_path(&self, start: &str, token: &str, hops: usize, path: &mut Vec<Edge>, cycles: &mut Vec<Vec<Edge>>) {
+        for edge in self.edges.get(token).into_iter().flatten() {
+            if path.iter().any(|e| e.pool == edge.pool) {
+                continue;
+            }
+            let closes = edge.token_out == start;
+
+            if path.len() + 1 == hops {
+                let weight: f64 = path.iter().map(|e| e.weight).sum::<f64>() + edge.weight;
+                if closes && weight < 0.0 {
+                    let mut cycle = path.clone();
+                    cycle.push(edge.clone());
+                    cycles.push(cycle);
+                }
+            } else if !closes && !path.iter().any(|e| e.token_in == edge.token_out) {
+                path.push(edge.clone());
+                self.extend_path(start, &edge.token_out, hops, path, cycles);
+                path.pop();
+            }
+        }
+    }
+
+    // Sizes a cycle starting from WETH on the tier grid of its shallowest pool,
+    // simulating each leg locally. Returns the most profitable size, if any is profitable.
+    pub fn size_route(&self, cycle: &[Edge], weth_usdc: f64, tiers: &TierTable) -> Option<RouteOpportunity> {
+        let depth_usd = cycle
+            .iter()
+            .map(|edge| self.pools[edge.pool].depth_ladder().tier_depth())
+            .fold(f64::MAX, f64::min);
+        let tier = tiers.tier_for_depth(depth_usd).filter(|tier| tier.enabled)?;
+
+        let mut best: Option<RouteOpportunity> = None;
+        for amount_usdc in tier.trade_amounts(depth_usd) {
+            let mut amounts = vec![amount_usdc / weth_usdc];
+            for edge in cycle {
+                let amount_in = *amounts.last()?;
+                amounts.push(simulate_local_swap(&self.pools[edge.pool], &edge.token_in, amount_in).ok()?);
+            }
+
+            let profit_usdc = (amounts[amounts.len() - 1] - amounts[0]) * weth_usdc;
+            if best.as_ref().is_none_or(|b| profit_usdc > b.profit_usdc) {
+                best = Some(RouteOpportunity {
+                    legs: cycle.to_vec(),
+                    tier: tier.tier,
+                    amount_usdc,
+                    amounts,
+                    profit_usdc,
+                });
+            }
+        }
+
+        best.filter(|route| route.profit_usdc > 0.0)
+    }
+}
+
+// Human-unit price of token0 in token1
+fn spot_price(pool: &PoolSnapshot) -> Option<f64> {
+    let sqrt_price = pool.sqrt_price_x96.parse::<f64>().ok()? / 2.0f64.powi(96);
+    let price = sqrt_price * sqrt_price * 10f64.powi(pool.token0_decimals as i32 - pool.token1_decimals as i32);
+    (price.is_finite() && price > 0.0).then_some(price)
+}
+
+pub fn router_address(dex: Dex) -> String {
+    settings().dex(dex).router.clone()
+}
+
+pub fn address_bytes(address: &str) -> [u8; 20] {
+    let mut bytes = [0u8; 20];
+    match hex::decode(address.trim_start_matches("0x")) {
+        Ok(decoded) if decoded.len() == 20 => bytes.copy_from_slice(&decoded),
+        _ => {}
+    }
+    bytes
+}
+
+async fn fetch_route_pool<M: Middleware + 'static>(
+    pool: &RoutePool,
+    base: Option<Address>,
+    provider: Arc<M>,
+) -> ScanResult<(Address, PoolState)> {
+    let dex = Dex::from_name(&pool.dex).ok_or_else(|| ScanError::Config(format!("unknown DEX {}", pool.dex)))?;
+    // Route pools are quoted in WETH when they hold it and in token1 otherwise; the
+    // token is looked up on the first fetch and kept after that
+    let base = match base {
+        Some(base) => base,
+        None => PoolState::quote_token(&pool.pair_address, weth(), provider.clone()).await?,
+    };
+    Ok((base, PoolState::fetch(dex, &pool.pair_address, base, provider).await?))
+}
+
+// Searches WETH cycles of three or more legs over every fresh pool in the cache plus
+// the route-only pools, and publishes the most profitable one as a multi-leg opportunity
+pub async fn handle_route_scan<M: Middleware + 'static>(
+    provider: Arc<M>,
+    mut blocks: BlockStream,
+    pool_cache: Arc<PoolCache>,
+    stream_results: Arc<StreamResults>,
+    reference_price: Arc<ReferencePriceService>,
+    failures: Arc<FailureTracker>,
+    pair_names: Vec<String>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let route_pools = load_route_pools()?;
+    let mut bases: HashMap<String, Address> = HashMap::new();
+    // Graph tokens are keyed the way pool snapshots write them
+    let start_token = format!("0x{}", hex::encode(weth().as_bytes()));
+
+    info!("🔺 Route scanner started with {} route-only pools, up to {} hops", route_pools.len(), MAX_ROUTE_HOPS);
+    let names = pair_names.iter().map(String::as_str).chain(route_pools.iter().map(|pool| pool.pair.as_str()));
+    if !has_route_cycle(names) {
+        warn!(
+            "🔺 No WETH cycle of 3+ legs can form from the pairs and {}; add route-only legs such as ARB/USDC \
+             there, or routes will only appear once discovery adds pools",
+            settings().pairs.route_pools_file
+        );
+    }
+
+    while let Some(block_number) = blocks.next().await {
+        let block_span = info_span!("block", number = block_number, monitor = "routes");
+        let block_work = async {
+            let _timer = BLOCK_SECONDS.with_label_values(&["routes"]).start_timer();
+            // A route only holds on the block it was found on, so the last one stops being
+            // published whether or not this block finds another
+            *write_lock(&stream_results.best_route) = BestRoute::default();
+            // Prices are converted with the block's shared WETH/USD reference
+            let reference = match reference_price.for_block(block_number, provider.clone()).await {
+                Ok(reference) => reference,
+                Err(e) => {
+                    warn!("Skipping route scan at #{}: {}", block_number, e);
+                    return;
+                }
+            };
+            let weth_usdc = reference.weth_usd;
+
+            let active: Vec<&RoutePool> = route_pools
+                .iter()
+                .filter(|pool| !failures.is_quarantined(&pool.pair_address, block_number))
+                .collect();
+            let fetched = futures::future::join_all(active.iter().map(|pool| {
+                fetch_route_pool(pool, bases.get(&pool.pair_address).copied(), provider.clone())
+            }))
+            .await;
+            for (pool, state) in active.into_iter().zip(fetched) {
+                let state = match state {
+                    Ok((base, state)) => {
+                        bases.insert(pool.pair_address.clone(), base);
+                        state
+                    }
+                    Err(e) => {
+                        failures.record_failure(&pool.pair_address, &pool.pair, &e, block_number);
+                        warn!("Failed to fetch route pool {} {}: {}", pool.pair, pool.pair_address, e);
+                        continue;
+                    }
+                };
+                failures.record_success(&pool.pair_address, block_number);
+                let base_usd = match reference_price.base_usd(state.base(), &reference, provider.clone()).await {
+                    Ok(price) => price,
+                    Err(e) => {
+                        warn!("Failed to price route pool {} {}: {}", pool.pair, pool.pair_address, e);
+                        continue;
+                    }
+                };
+                pool_cache.update(&pool.pair_address, &pool.pair, state, block_number, weth_usdc, base_usd);
+            }
+
+            let max_pool_age = settings().thresholds.max_route_pool_age;
+            let pools: Vec<PoolSnapshot> = pool_cache
+                .all()
+                .into_iter()
+                .filter(|p| p.block_number + max_pool_age >= block_number)
+                .map(|p| p.state.snapshot(p.weth_usdc, p.base_usd))
+                .collect();
+            let graph = TokenGraph::build(pools);
+            let tiers = tier_table();
+
+            let mut best: Option<RouteOpportunity> = None;
+            for hops in 3..=MAX_ROUTE_HOPS {
+                let routes = graph
+                    .negative_cycles(&start_token, hops)
+                    .into_iter()
+                    .filter_map(|cycle| graph.size_route(&cycle, weth_usdc, &tiers));
+                for route in routes {
+                    if best.as_ref().is_none_or(|b| route.profit_usdc > b.profit_usdc) {
+                        best = Some(route);
+                    }
+                }
+            }
+
+            // size_route only returns profitable routes
+            let Some(route) = best else {
+                return;
+            };
+            OPPORTUNITIES_FOUND.with_label_values(&["route"]).inc();
+
+            let path = route
+                .legs
+                .iter()
+                .map(|edge| format!("{} {}", graph.pools[edge.pool].dex, graph.pools[edge.pool].pool_address))
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            info!(
+                "🔺 #{} Route TIER {} | {} | Size: ${:.2} | Profit: ${:.4}",
+                block_number, route.tier, path, route.amount_usdc, route.profit_usdc
+            );
+            // Each token the route passes through is checked like a pair's traded token, at
+            // its price in the pool the route buys it from
+            let mut suspect = reference.suspect.clone();
+            for (previous, leg) in route.legs.iter().zip(&route.legs[1..]) {
+                if suspect.is_some() {
+                    break;
+                }
+                let Ok(token) = leg.token_in.parse::<Address>() else {
+                    continue;
+                };
+                let token_usd = graph.pools[previous.pool].token_usd(&leg.token_in);
+                suspect = reference_price.check_token(token, token_usd, block_number, provider.clone()).await;
+            }
+            if let Some(reason) = suspect {
+                warn!("🚩 Suspect route at #{} not published: {}", block_number, reason);
+                OPPORTUNITIES_SUSPECT.with_label_values(&["route"]).inc();
+                return;
+            }
+
+            // The best route was cleared at the start of the block, and this is the only
+            // route published on it
+            let legs = route
+                .legs
+                .iter()
+                .zip(&route.amounts)
+                .map(|(edge, amount_in)| {
+                    let pool = &graph.pools[edge.pool];
+                    let dex = Dex::from_name(&pool.dex)
+                        .ok_or_else(|| ScanError::Config(format!("unknown DEX {} for pool {}", pool.dex, pool.pool_address)))?;
+                    let zero_for_one = edge.token_in.eq_ignore_ascii_case(&pool.token0);
+                    let decimals_in = if zero_for_one { pool.token0_decimals } else { pool.token1_decimals };
+                    let mut amount = [0u8; 32];
+                    U256::from((amount_in * 10f64.powi(decimals_in as i32)) as u128).to_big_endian(&mut amount);
+
+                    Ok(RouteLeg {
+                        dex: address_bytes(&router_address(dex)),
+                        token_in: address_bytes(&edge.token_in),
+                        token_out: address_bytes(&edge.token_out),
+                        fee: pool.fee_for(zero_for_one),
+                        amount,
+                    })
+                })
+                .collect::<ScanResult<Vec<RouteLeg>>>();
+            let legs = match legs {
+                Ok(legs) => legs,
+                Err(e) => {
+                    warn!("Route at #{} not published: {}", block_number, e);
+                    return;
+                }
+            };
+
+            *write_lock(&stream_results.best_route) = BestRoute {
+                profit_usdc: route.profit_usdc,
+                reference: reference.to_ipc(),
+                legs,
+            };
+        }
+        .instrument(block_span);
+        rpc_replay::at_block(block_number, block_work).await;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Edge, RoutePool, TokenGraph, has_route_cycle};
+    use crate::pool_state::PoolSnapshot;
+    use crate::tiers::{Tier, TierTable};
+    use serde_json::Value;
+    use std::collections::HashMap;
+
+    const WETH: &str = "0x000000000000000000000000000000000000000e";
+    const TOKEN_A: &str = "0x000000000000000000000000000000000000000a";
+    const TOKEN_B: &str = "0x000000000000000000000000000000000000000b";
+
+    // Very deep 0.05% pool between two 18-decimal tokens at `price` token1 per token0,
+    // priced in USD through its base token
+    fn pool(address: &str, token0: &str, token1: &str, price: f64, base: &str, base_usd: f64) -> PoolSnapshot {
+        PoolSnapshot {
+            dex: "Uniswap".to_string(),
+            pool_address: address.to_string(),
+            token0: token0.to_string(),
+            token1: token1.to_string(),
+            token0_decimals: 18,
+            token1_decimals: 18,
+            sqrt_price_x96: format!("{:.0}", price.sqrt() * 2f64.powi(96)),
+            active_liquidity: 10u128.pow(28),
+            tick: (price.ln() / 1.0001f64.ln()).round() as i32,
+            initialized_ticks: Vec::new(),
+            fee: 500,
+            fee_otz: None,
+            // The other token in the base
+            price: if base == token0 { 1.0 / price } else { price },
+            liquidity: 0,
+            base_token: base.to_string(),
+            weth_usdc: 3000.0,
+            base_usd,
+        }
+    }
+
+    // WETH at $3000 buys 1000 A, A buys 2 B, and B sells for 0.00051 WETH, 2% over the
+    // 0.0005 the first two pools imply
+    fn triangle() -> Vec<PoolSnapshot> {
+        vec![
+            pool("weth_a", WETH, TOKEN_A, 1000.0, WETH, 3000.0),
+            pool("a_b", TOKEN_A, TOKEN_B, 2.0, TOKEN_A, 3.0),
+            pool("b_weth", TOKEN_B, WETH, 0.00051, WETH, 3000.0),
+        ]
+    }
+
+    fn one_tier(enabled: bool) -> TierTable {
+        let tier = Tier { tier: 4, min_depth: 0.0, max_depth: None, min_spread: 0.0, start: 1000.0, step: 1000.0, count: 3, enabled };
+        TierTable { tiers: vec![tier], pairs: HashMap::new() }
+    }
+
+    fn route_of(cycle: &[Edge]) -> Vec<(usize, &str)> {
+        cycle.iter().map(|edge| (edge.pool, edge.token_out.as_str())).collect()
+    }
+
+    #[test]
+    fn finds_only_the_profitable_direction_of_a_triangle() {
+        let graph = TokenGraph::build(triangle());
+        let cycles = graph.negative_cycles(WETH, 3);
+        // WETH -> A -> B -> WETH; the reverse loses about 2% and isn't returned
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(route_of(&cycles[0]), vec![(0, TOKEN_A), (1, TOKEN_B), (2, WETH)]);
+        assert!(graph.negative_cycles(WETH, 4).is_empty());
+    }
+
+    #[test]
+    fn cycles_use_each_pool_and_token_once() {
+        let mut pools = triangle();
+        // A second A/B pool at 2.1 B per A, so A -> B there and B -> A in the first one
+        // gains 5%, and a second WETH/A pool at the first one's price
+        pools.push(pool("a_b_2", TOKEN_A, TOKEN_B, 2.1, TOKEN_A, 3.0));
+        pools.push(pool("weth_a_2", WETH, TOKEN_A, 1000.0, WETH, 3000.0));
+        let graph = TokenGraph::build(pools);
+
+        // Either WETH/A pool, either A/B pool, then B/WETH
+        let cycles = graph.negative_cycles(WETH, 3);
+        assert_eq!(cycles.len(), 4);
+        for cycle in &cycles {
+            let mut used: Vec<usize> = cycle.iter().map(|edge| edge.pool).collect();
+            used.sort();
+            used.dedup();
+            assert_eq!(used.len(), 3);
+            assert_eq!(cycle[2].pool, 2);
+        }
+        // WETH -> A -> B -> A -> WETH through four different pools would gain, but passes A twice
+        assert!(graph.negative_cycles(WETH, 4).is_empty());
+    }
+
+    #[test]
+    fn sizes_a_route_at_its_most_profitable_amount() {
+        let graph = TokenGraph::build(triangle());
+        let cycle = graph.negative_cycles(WETH, 3).remove(0);
+
+        let route = graph.size_route(&cycle, 3000.0, &one_tier(true)).unwrap();
+        assert_eq!(route.tier, 4);
+        // Price impact is negligible this deep, so the largest size earns the most
+        assert_eq!(route.amount_usdc, 3000.0);
+        assert_eq!(route.amounts.len(), 4);
+        assert!((route.amounts[0] - 1.0).abs() < 1e-12);
+        // 2% over three 0.05% fees on $3000
+        let expected = 3000.0 * (1.02 * 0.9995f64.powi(3) - 1.0);
+        assert!((route.profit_usdc - expected).abs() < 0.01, "{} != {}", route.profit_usdc, expected);
+
+        assert!(graph.size_route(&cycle, 3000.0, &one_tier(false)).is_none());
+    }
+
+    #[test]
+    fn shipped_pairs_and_route_pools_form_a_cycle() {
+        let root = env!("CARGO_MANIFEST_DIR");
+        let pairs: Vec<Value> = serde_json::from_str(&std::fs::read_to_string(format!("{}/src/data.json", root)).unwrap()).unwrap();
+        let route_pools: Vec<RoutePool> =
+            serde_json::from_str(&std::fs::read_to_string(format!("{}/src/route_pools.json", root)).unwrap()).unwrap();
+        let names = pairs
+            .iter()
+            .filter_map(|pair| pair["Pair"].as_str())
+            .chain(route_pools.iter().map(|pool| pool.pair.as_str()));
+        // WETH -> ARB -> USDC -> WETH and WETH -> WBTC -> USDC -> WETH
+        assert!(has_route_cycle(names));
+    }
+
+    #[test]
+    fn route_cycle_needs_a_leg_between_two_other_tokens() {
+        // Every pair through WETH, as with the WETH/USDC reference pools alone
+        assert!(!has_route_cycle(["ARB/WETH", "WBTC/WETH", "WETH/USDC", "WETH/USDC", "WETH/USDC"]));
+        // ARB/USDC closes WETH -> ARB -> USDC -> WETH
+        assert!(has_route_cycle(["ARB/WETH", "WETH/USDC", "ARB/USDC"]));
+        // USDT/USDC alone still leaves USDT hanging off USDC
+        assert!(!has_route_cycle(["ARB/WETH", "WETH/USDC", "USDT/USDC"]));
+        assert!(has_route_cycle(["USDT/WETH", "WETH/USDC", "USDT/USDC"]));
+    }
+}
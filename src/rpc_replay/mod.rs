@@ -0,0 +1,282 @@
+use async_trait::async_trait;
+use ethers::providers::{JsonRpcClient, ProviderError};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs::{File, OpenOptions};
+use std::future::Future;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::sync::{Mutex, mpsc};
+use std::thread;
+use tracing::{error, info};
+
+use crate::error::lock;
+
+tokio::task_local! {
+    // Block the current task is working on
+    static RPC_BLOCK: u64;
+}
+
+// Runs one block's work with its RPC calls tagged with the block number. Calls go to
+// "latest", so without the tag a replay couldn't tell which block a response belongs to
+// when monitors on different blocks make the same call.
+pub async fn at_block<F: Future>(block_number: u64, work: F) -> F::Output {
+    RPC_BLOCK.scope(block_number, work).await
+}
+
+fn current_block() -> Option<u64> {
+    RPC_BLOCK.try_with(|block| *block).ok()
+}
+
+// One line of an RPC fixture file
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FixtureEntry {
+    Call {
+        method: String,
+        params: Value,
+        result: Value,
+        // Block being scanned when the call was made, none for startup calls
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        block: Option<u64>,
+    },
+    Block {
+        number: u64,
+    },
+}
+
+// Appends fixture entries to a JSON lines file from a dedicated writer thread
+#[derive(Debug, Clone)]
+pub struct FixtureWriter {
+    tx: mpsc::Sender<FixtureEntry>,
+}
+
+impl FixtureWriter {
+    pub fn new(path: &str) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .write(true)
+            .open(path)?;
+        let (tx, rx) = mpsc::channel::<FixtureEntry>();
+
+        info!("📼 Recording RPC traffic to {}", path);
+
+        thread::spawn(move || {
+            let mut writer = BufWriter::new(file);
+            while let Ok(entry) = rx.recv() {
+                if let Err(e) = write_entry(&mut writer, &entry) {
+                    error!("❌ Failed to record RPC entry: {}", e);
+                }
+                while let Ok(entry) = rx.try_recv() {
+                    if let Err(e) = write_entry(&mut writer, &entry) {
+                        error!("❌ Failed to record RPC entry: {}", e);
+                    }
+                }
+                if let Err(e) = writer.flush() {
+                    error!("❌ Failed to flush RPC fixture: {}", e);
+                }
+            }
+        });
+
+        Ok(Self { tx })
+    }
+
+    pub fn write(&self, entry: FixtureEntry) {
+        let _ = self.tx.send(entry);
+    }
+}
+
+fn write_entry(writer: &mut BufWriter<File>, entry: &FixtureEntry) -> io::Result<()> {
+    serde_json::to_writer(&mut *writer, entry)?;
+    writer.write_all(b"\n")
+}
+
+// Transport wrapper that forwards every request to the inner client and writes
+// each successful request/response pair to the fixture
+#[derive(Debug)]
+pub struct RecordingClient<C> {
+    inner: C,
+    fixture: FixtureWriter,
+}
+
+impl<C: JsonRpcClient> RecordingClient<C> {
+    pub fn new(inner: C, fixture: FixtureWriter) -> Self {
+        Self { inner, fixture }
+    }
+}
+
+#[async_trait]
+impl<C: JsonRpcClient> JsonRpcClient for RecordingClient<C> {
+    type Error = ProviderError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, ProviderError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let params = serde_json::to_value(&params)?;
+        let result = self
+            .inner
+            .request::<Value, Value>(method, params.clone())
+            .await
+            .map_err(Into::<ProviderError>::into)?;
+
+        self.fixture.write(FixtureEntry::Call {
+            method: method.to_string(),
+            params,
+            result: result.clone(),
+            block: current_block(),
+        });
+
+        Ok(serde_json::from_value(result)?)
+    }
+}
+
+#[derive(Debug)]
+struct ReplayQueue {
+    responses: Vec<Value>,
+    cursor: usize,
+}
+
+// Transport that answers requests from a fixture instead of a node. A call made inside
+// at_block gets the responses recorded for that block; other calls, and block-tagged calls
+// the recording has nothing for at that block, fall back to every recorded response.
+// Responses to the same key are served in the order they were recorded; once exhausted
+// the last one keeps being returned. Within a block the node's answer to a call doesn't
+// change, so the order concurrent monitors take them in doesn't matter.
+#[derive(Debug)]
+pub struct ReplayClient {
+    queues: Mutex<HashMap<String, ReplayQueue>>,
+    blocks: Vec<u64>,
+}
+
+impl ReplayClient {
+    pub fn load(path: &str) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut entries = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            entries.push(serde_json::from_str::<FixtureEntry>(&line)?);
+        }
+        Ok(Self::from_entries(entries))
+    }
+
+    pub fn from_entries(entries: Vec<FixtureEntry>) -> Self {
+        let mut queues: HashMap<String, ReplayQueue> = HashMap::new();
+        let mut blocks = Vec::new();
+
+        for entry in entries {
+            match entry {
+                FixtureEntry::Call { method, params, result, block } => {
+                    let mut keys = vec![request_key(None, &method, &params)];
+                    if block.is_some() {
+                        keys.push(request_key(block, &method, &params));
+                    }
+                    for key in keys {
+                        queues
+                            .entry(key)
+                            .or_insert_with(|| ReplayQueue { responses: Vec::new(), cursor: 0 })
+                            .responses
+                            .push(result.clone());
+                    }
+                }
+                FixtureEntry::Block { number } => blocks.push(number),
+            }
+        }
+
+        Self {
+            queues: Mutex::new(queues),
+            blocks,
+        }
+    }
+
+    // Block numbers in the order the recording session saw them
+    pub fn block_numbers(&self) -> &[u64] {
+        &self.blocks
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for ReplayClient {
+    type Error = ProviderError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, ProviderError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let params = serde_json::to_value(&params)?;
+        let block_key = current_block().map(|block| request_key(Some(block), method, &params));
+
+        let result = {
+            let mut queues = lock(&self.queues);
+            let key = match block_key {
+                Some(key) if queues.contains_key(&key) => key,
+                _ => request_key(None, method, &params),
+            };
+            let queue = queues.get_mut(&key).ok_or_else(|| {
+                ProviderError::CustomError(format!("No recorded response for {} {}", method, params))
+            })?;
+            let result = queue.responses[queue.cursor].clone();
+            if queue.cursor + 1 < queue.responses.len() {
+                queue.cursor += 1;
+            }
+            result
+        };
+
+        Ok(serde_json::from_value(result)?)
+    }
+}
+
+// Params are serialized the same way on every run, so equal requests share a key
+fn request_key(block: Option<u64>, method: &str, params: &Value) -> String {
+    match block {
+        Some(block) => format!("{}@{}:{}", method, block, params),
+        None => format!("{}:{}", method, params),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn call(block: Option<u64>, result: &str) -> FixtureEntry {
+        FixtureEntry::Call {
+            method: "eth_call".to_string(),
+            params: json!(["0xpool", "latest"]),
+            result: json!(result),
+            block,
+        }
+    }
+
+    async fn eth_call(client: &ReplayClient) -> String {
+        client.request("eth_call", json!(["0xpool", "latest"])).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn block_tagged_calls_get_their_block_response() {
+        let client = ReplayClient::from_entries(vec![
+            call(None, "0x00"),
+            FixtureEntry::Block { number: 1 },
+            call(Some(1), "0x01"),
+            FixtureEntry::Block { number: 2 },
+            call(Some(2), "0x02"),
+        ]);
+
+        // Block 2's monitor asks before block 1's
+        assert_eq!(at_block(2, eth_call(&client)).await, "0x02");
+        assert_eq!(at_block(1, eth_call(&client)).await, "0x01");
+        assert_eq!(at_block(1, eth_call(&client)).await, "0x01");
+        // Untagged calls and blocks the recording never saw take every response in order
+        assert_eq!(eth_call(&client).await, "0x00");
+        assert_eq!(at_block(3, eth_call(&client)).await, "0x01");
+        assert_eq!(client.block_numbers(), &[1, 2]);
+    }
+}
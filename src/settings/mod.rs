@@ -0,0 +1,630 @@
+use ethers::prelude::Address as EthersAddress;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+use crate::config::{
+    API_LISTEN_ADDR, CAMELOT_V3_FACTORY_ADDRESS, CAMELOT_V3_QUOTER_ADDRESS, CAMELOT_V3_SWAP_ROUTER_ADDRESS,
+    EXPORT_FORMAT, IPC_CYCLE_TIME, IS_LOGGING_ENABLED, LOG_LEVEL, LOG_ROTATION, MAX_REFERENCE_PRICE_AGE, MAX_ROUTE_POOL_AGE,
+    METRICS_LISTEN_ADDR, QUARANTINE_AFTER_FAILURES, QUARANTINE_BLOCKS, PAIRS_FILE, PANCAKESWAP_V3_FACTORY_ADDRESS, PANCAKESWAP_V3_QUOTER_ADDRESS,
+    PANCAKESWAP_V3_SWAP_ROUTER_ADDRESS, QUOTE_SERVICE_SOCKET_PATH, ROUTE_POOLS_FILE,
+    UNISWAP_V3_FACTORY_ADDRESS, UNISWAP_V3_QUOTER_ADDRESS, UNISWAP_V3_SWAP_ROUTER_ADDRESS,
+    is_simulation_logging_enabled,
+};
+use crate::error::{read_lock, write_lock};
+use crate::exchange::model::{Dex, load_pairs};
+use crate::reference_price::ReferenceMethod;
+use crate::tiers::{Tier, TierOverride, TierTable, log_tier_table, set_tier_table, tier_table};
+
+// How often the config file's modification time is checked for hot reload
+const RELOAD_INTERVAL: Duration = Duration::from_secs(2);
+
+lazy_static! {
+    static ref SETTINGS: RwLock<Arc<Settings>> = RwLock::new(Arc::new(Settings::default()));
+}
+
+// Everything the scanner can be configured with, loaded from CONFIG_FILE (default
+// config.toml). Missing fields keep the defaults from config.rs and the environment, so
+// a partial file or no file at all behaves like before. The logging switches, oracle
+// thresholds, thresholds and tiers reload when the file changes; the rest needs a restart.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub endpoints: Endpoints,
+    pub pairs: PairFiles,
+    pub dex: DexContracts,
+    pub ipc: IpcSettings,
+    pub metrics: MetricsSettings,
+    pub api: ApiSettings,
+    pub history: HistorySettings,
+    pub export: ExportSettings,
+    pub logging: LoggingSettings,
+    pub oracle: OracleSettings,
+    pub reference: ReferenceSettings,
+    pub discovery: DiscoverySettings,
+    pub recording: RecordingSettings,
+    pub thresholds: Thresholds,
+    pub tiers: Vec<Tier>,
+    // Keyed by pair name as in the pairs file
+    pub tier_overrides: HashMap<String, TierOverride>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Endpoints {
+    pub ws_rpc_url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PairFiles {
+    pub file: String,
+    pub route_pools_file: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DexAddresses {
+    pub factory: String,
+    pub router: String,
+    pub quoter: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DexContracts {
+    pub uniswap: DexAddresses,
+    pub pancakeswap: DexAddresses,
+    pub camelot: DexAddresses,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IpcSettings {
+    pub cycle_ms: u64,
+    // "" turns the quote service off
+    pub quote_socket: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsSettings {
+    // host:port for the Prometheus endpoint, empty to disable it
+    pub listen: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiSettings {
+    // host:port for the HTTP/WebSocket API, empty to disable it
+    pub listen: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistorySettings {
+    // SQLite file every evaluated pair-block is stored in, empty to store nothing
+    pub database: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExportSettings {
+    // Directory the hourly pool and spread files go to, empty to export nothing
+    pub directory: String,
+    // parquet or arrow (Arrow IPC file)
+    pub format: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingSettings {
+    // Price and liquidity record per pool pair and block
+    pub price_liquidity: bool,
+    // Record per simulated trade size
+    pub simulation: bool,
+    // The output settings below need a restart; the two switches above reload.
+    // Tracing filter directives, e.g. "info,scanner_bot::routes=debug"
+    pub level: String,
+    // Human-readable lines on stderr
+    pub console: bool,
+    // JSON lines written here, empty for no file
+    pub file: String,
+    // minutely, hourly, daily or never
+    pub rotation: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OracleSettings {
+    // Needs a restart; the limits below reload
+    pub enabled: bool,
+    pub max_deviation: f64,
+    pub max_age_secs: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReferenceSettings {
+    // How the WETH/USD reference pools are combined: liquidity-weighted or median
+    pub method: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiscoverySettings {
+    // Pools below this TVL in USD are never added, at startup or at runtime
+    pub min_tvl: f64,
+    // Blocks a new pool must exist before it is judged
+    pub warmup_blocks: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecordingSettings {
+    // JSON lines file every monitored pool's state is appended to, empty to record nothing
+    pub blocks_file: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Thresholds {
+    pub max_reference_price_age: u64,
+    pub max_route_pool_age: u64,
+    pub quarantine_after: u32,
+    pub quarantine_blocks: u64,
+}
+
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    std::env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let tiers = TierTable::defaults();
+        Self {
+            endpoints: Endpoints::default(),
+            pairs: PairFiles::default(),
+            dex: DexContracts::default(),
+            ipc: IpcSettings::default(),
+            metrics: MetricsSettings::default(),
+            api: ApiSettings::default(),
+            history: HistorySettings::default(),
+            export: ExportSettings::default(),
+            logging: LoggingSettings::default(),
+            oracle: OracleSettings::default(),
+            reference: ReferenceSettings::default(),
+            discovery: DiscoverySettings::default(),
+            recording: RecordingSettings::default(),
+            thresholds: Thresholds::default(),
+            tiers: tiers.tiers,
+            tier_overrides: tiers.pairs,
+        }
+    }
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self { ws_rpc_url: std::env::var("WS_RPC_URL").unwrap_or_default() }
+    }
+}
+
+impl Default for PairFiles {
+    fn default() -> Self {
+        Self {
+            file: PAIRS_FILE.to_string(),
+            route_pools_file: ROUTE_POOLS_FILE.to_string(),
+        }
+    }
+}
+
+impl Default for DexContracts {
+    fn default() -> Self {
+        let addresses = |factory: &str, router: &str, quoter: &str| DexAddresses {
+            factory: factory.to_string(),
+            router: router.to_string(),
+            quoter: quoter.to_string(),
+        };
+        Self {
+            uniswap: addresses(UNISWAP_V3_FACTORY_ADDRESS, UNISWAP_V3_SWAP_ROUTER_ADDRESS, UNISWAP_V3_QUOTER_ADDRESS),
+            pancakeswap: addresses(PANCAKESWAP_V3_FACTORY_ADDRESS, PANCAKESWAP_V3_SWAP_ROUTER_ADDRESS, PANCAKESWAP_V3_QUOTER_ADDRESS),
+            camelot: addresses(CAMELOT_V3_FACTORY_ADDRESS, CAMELOT_V3_SWAP_ROUTER_ADDRESS, CAMELOT_V3_QUOTER_ADDRESS),
+        }
+    }
+}
+
+impl Default for IpcSettings {
+    fn default() -> Self {
+        Self {
+            cycle_ms: IPC_CYCLE_TIME.as_millis() as u64,
+            quote_socket: std::env::var("QUOTE_SOCKET_PATH").unwrap_or_else(|_| QUOTE_SERVICE_SOCKET_PATH.to_string()),
+        }
+    }
+}
+
+impl Default for MetricsSettings {
+    fn default() -> Self {
+        Self { listen: std::env::var("METRICS_ADDR").unwrap_or_else(|_| METRICS_LISTEN_ADDR.to_string()) }
+    }
+}
+
+impl Default for ApiSettings {
+    fn default() -> Self {
+        Self { listen: std::env::var("API_ADDR").unwrap_or_else(|_| API_LISTEN_ADDR.to_string()) }
+    }
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        Self { database: std::env::var("HISTORY_DB").unwrap_or_default() }
+    }
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            directory: std::env::var("EXPORT_DIR").unwrap_or_default(),
+            format: std::env::var("EXPORT_FORMAT")
+                .ok()
+                .filter(|format| !format.is_empty())
+                .unwrap_or_else(|| EXPORT_FORMAT.to_string()),
+        }
+    }
+}
+
+impl Default for LoggingSettings {
+    fn default() -> Self {
+        Self {
+            price_liquidity: IS_LOGGING_ENABLED,
+            simulation: is_simulation_logging_enabled(),
+            level: LOG_LEVEL.to_string(),
+            console: true,
+            file: std::env::var("LOG_FILE").unwrap_or_default(),
+            rotation: LOG_ROTATION.to_string(),
+        }
+    }
+}
+
+impl Default for OracleSettings {
+    fn default() -> Self {
+        Self {
+            enabled: std::env::var("ORACLE_CHECK_ENABLED").is_ok_and(|v| v.eq_ignore_ascii_case("true")),
+            max_deviation: env_or("ORACLE_MAX_DEVIATION", 0.02),
+            max_age_secs: env_or("ORACLE_MAX_AGE_SECS", 3600),
+        }
+    }
+}
+
+impl Default for ReferenceSettings {
+    fn default() -> Self {
+        Self {
+            method: std::env::var("REFERENCE_PRICE_METHOD")
+                .ok()
+                .filter(|method| !method.is_empty())
+                .unwrap_or_else(|| ReferenceMethod::default().name().to_string()),
+        }
+    }
+}
+
+impl Default for DiscoverySettings {
+    fn default() -> Self {
+        Self {
+            min_tvl: env_or("DISCOVER_MIN_TVL", 10_000.0),
+            warmup_blocks: env_or("POOL_DISCOVERY_WARMUP_BLOCKS", 240),
+        }
+    }
+}
+
+impl Default for RecordingSettings {
+    fn default() -> Self {
+        Self { blocks_file: std::env::var("RECORD_BLOCKS_FILE").unwrap_or_default() }
+    }
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            max_reference_price_age: MAX_REFERENCE_PRICE_AGE,
+            max_route_pool_age: MAX_ROUTE_POOL_AGE,
+            quarantine_after: QUARANTINE_AFTER_FAILURES,
+            quarantine_blocks: QUARANTINE_BLOCKS,
+        }
+    }
+}
+
+impl LoggingSettings {
+    // The fields the subscriber was built from
+    fn output(&self) -> (&str, bool, &str, &str) {
+        (&self.level, self.console, &self.file, &self.rotation)
+    }
+}
+
+impl Settings {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut settings: Settings = toml::from_str(&content).map_err(|e| format!("{}: {}", path, e))?;
+        settings.tiers.sort_by(|a, b| a.min_depth.total_cmp(&b.min_depth));
+        settings
+            .validate()
+            .map_err(|errors| format!("{} is invalid:\n  - {}", path, errors.join("\n  - ")))?;
+        Ok(settings)
+    }
+
+    // Every problem in the file, each prefixed with the field it is about
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+
+        let url = &self.endpoints.ws_rpc_url;
+        if !url.is_empty() && !url.starts_with("ws://") && !url.starts_with("wss://") {
+            errors.push(format!("endpoints.ws_rpc_url: expected a ws:// or wss:// URL, got '{}'", url));
+        }
+        // Tier overrides are checked against the pair names once the pair file reads
+        let pair_names: Option<HashSet<String>> = match load_pairs(&self.pairs.file) {
+            Ok(pairs) => Some(pairs.into_iter().map(|pair| pair.pair).collect()),
+            Err(e) => {
+                errors.push(format!("pairs.file: {}", e));
+                None
+            }
+        };
+        if !Path::new(&self.pairs.route_pools_file).exists() {
+            errors.push(format!("pairs.route_pools_file: '{}' does not exist", self.pairs.route_pools_file));
+        }
+        for dex in [Dex::Uniswap, Dex::PancakeSwap, Dex::Camelot] {
+            let addresses = self.dex(dex);
+            for (field, address) in [("factory", &addresses.factory), ("router", &addresses.router), ("quoter", &addresses.quoter)] {
+                if address.parse::<EthersAddress>().is_err() {
+                    errors.push(format!("dex.{}.{}: '{}' is not an address", dex.name().to_lowercase(), field, address));
+                }
+            }
+        }
+        if self.ipc.cycle_ms == 0 {
+            errors.push("ipc.cycle_ms: must be greater than 0".to_string());
+        }
+        if !self.metrics.listen.is_empty() && self.metrics.listen.parse::<std::net::SocketAddr>().is_err() {
+            errors.push(format!("metrics.listen: expected host:port, got '{}'", self.metrics.listen));
+        }
+        if crate::export::ExportFormat::from_name(&self.export.format).is_none() {
+            errors.push(format!("export.format: expected parquet or arrow, got '{}'", self.export.format));
+        }
+        if !self.api.listen.is_empty() && self.api.listen.parse::<std::net::SocketAddr>().is_err() {
+            errors.push(format!("api.listen: expected host:port, got '{}'", self.api.listen));
+        }
+        if let Err(e) = tracing_subscriber::EnvFilter::try_new(&self.logging.level) {
+            errors.push(format!("logging.level: '{}' is not a filter: {}", self.logging.level, e));
+        }
+        if crate::logging::rotation(&self.logging.rotation).is_none() {
+            errors.push(format!("logging.rotation: expected minutely, hourly, daily or never, got '{}'", self.logging.rotation));
+        }
+        if !(self.oracle.max_deviation > 0.0 && self.oracle.max_deviation < 1.0) {
+            errors.push(format!("oracle.max_deviation: expected a fraction between 0 and 1, got {}", self.oracle.max_deviation));
+        }
+        if self.oracle.max_age_secs == 0 {
+            errors.push("oracle.max_age_secs: must be greater than 0".to_string());
+        }
+        if ReferenceMethod::from_name(&self.reference.method).is_none() {
+            errors.push(format!("reference.method: expected liquidity-weighted or median, got '{}'", self.reference.method));
+        }
+        if self.discovery.min_tvl.is_nan() || self.discovery.min_tvl < 0.0 {
+            errors.push(format!("discovery.min_tvl: expected a USD amount of 0 or more, got {}", self.discovery.min_tvl));
+        }
+        if self.thresholds.quarantine_after == 0 {
+            errors.push("thresholds.quarantine_after: must be greater than 0".to_string());
+        }
+        if self.thresholds.quarantine_blocks == 0 {
+            errors.push("thresholds.quarantine_blocks: must be greater than 0".to_string());
+        }
+        if let Err(e) = self.tier_table().validate(pair_names.as_ref()) {
+            errors.push(format!("tiers: {}", e));
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+
+    pub fn dex(&self, dex: Dex) -> &DexAddresses {
+        match dex {
+            Dex::Uniswap => &self.dex.uniswap,
+            Dex::PancakeSwap => &self.dex.pancakeswap,
+            Dex::Camelot => &self.dex.camelot,
+        }
+    }
+
+    pub fn tier_table(&self) -> TierTable {
+        TierTable {
+            tiers: self.tiers.clone(),
+            pairs: self.tier_overrides.clone(),
+        }
+    }
+
+    // This file's reloadable sections on top of the running settings. Sections that need a
+    // restart are kept as they are and named so the change isn't silently ignored.
+    fn reloaded(&self, file: Settings) -> (Settings, Vec<&'static str>) {
+        let mut restart = Vec::new();
+        for (section, changed) in [
+            ("endpoints", file.endpoints != self.endpoints),
+            ("pairs", file.pairs != self.pairs),
+            ("dex", file.dex != self.dex),
+            ("ipc", file.ipc != self.ipc),
+            ("metrics", file.metrics != self.metrics),
+            ("api", file.api != self.api),
+            ("history", file.history != self.history),
+            ("export", file.export != self.export),
+            ("logging output", file.logging.output() != self.logging.output()),
+            ("oracle.enabled", file.oracle.enabled != self.oracle.enabled),
+            ("reference", file.reference != self.reference),
+            ("discovery", file.discovery != self.discovery),
+            ("recording", file.recording != self.recording),
+        ] {
+            if changed {
+                restart.push(section);
+            }
+        }
+
+        let mut next = self.clone();
+        next.logging.price_liquidity = file.logging.price_liquidity;
+        next.logging.simulation = file.logging.simulation;
+        next.oracle.max_deviation = file.oracle.max_deviation;
+        next.oracle.max_age_secs = file.oracle.max_age_secs;
+        next.thresholds = file.thresholds;
+        next.tiers = file.tiers;
+        next.tier_overrides = file.tier_overrides;
+        (next, restart)
+    }
+}
+
+pub fn settings() -> Arc<Settings> {
+    read_lock(&SETTINGS).clone()
+}
+
+fn apply(settings: Settings) {
+    set_tier_table(settings.tier_table());
+    *write_lock(&SETTINGS) = Arc::new(settings);
+}
+
+// --config, then CONFIG_FILE, then config.toml if it exists
+fn config_path(config: Option<&str>) -> (String, bool) {
+    if let Some(path) = config {
+        return (path.to_string(), true);
+    }
+    match std::env::var("CONFIG_FILE") {
+        Ok(path) => (path, true),
+        Err(_) => ("config.toml".to_string(), false),
+    }
+}
+
+// Loads the config file and returns the path that was loaded, None when running on the
+// defaults. A file that was asked for but is missing or invalid stops startup.
+pub fn init(config: Option<&str>) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let (path, explicit) = config_path(config);
+    if !explicit && !Path::new(&path).exists() {
+        apply(Settings::default());
+        return Ok(None);
+    }
+
+    apply(Settings::load(&path)?);
+    Ok(Some(path))
+}
+
+// Polls the config file and applies the reloadable sections whenever it changes. A file
+// that no longer parses or validates is reported and the running settings are kept.
+pub fn watch(path: String) {
+    tokio::spawn(async move {
+        let modified = |path: &str| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        let mut last_modified: Option<SystemTime> = modified(&path);
+        let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            let current = modified(&path);
+            if current.is_none() || current == last_modified {
+                continue;
+            }
+            last_modified = current;
+
+            match Settings::load(&path) {
+                Ok(file) => {
+                    let (next, restart) = settings().reloaded(file);
+                    apply(next);
+                    info!("🔄 Reloaded config from {}", path);
+                    log_tier_table(&tier_table());
+                    if !restart.is_empty() {
+                        warn!("⚠️ {} changed in {}; restart to apply", restart.join(", "), path);
+                    }
+                }
+                Err(e) => warn!("⚠️ Config reload failed, keeping the running settings: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_and_the_example_config_are_valid() {
+        assert_eq!(Settings::default().validate(), Ok(()));
+        let example = Settings::load("config.example.toml").unwrap();
+        assert_eq!(example.reference.method, "liquidity-weighted");
+        assert_eq!((example.discovery.min_tvl, example.discovery.warmup_blocks), (10_000.0, 240));
+    }
+
+    #[test]
+    fn validate_names_every_bad_field() {
+        let mut settings = Settings::default();
+        settings.endpoints.ws_rpc_url = "http://localhost:8545".to_string();
+        settings.pairs.file = "missing-pairs.json".to_string();
+        settings.ipc.cycle_ms = 0;
+        settings.export.format = "csv".to_string();
+        settings.oracle.max_deviation = 1.5;
+        settings.reference.method = "mean".to_string();
+        settings.discovery.min_tvl = -1.0;
+        settings.thresholds.quarantine_after = 0;
+        settings.tiers[1].min_depth += 1.0;
+
+        let errors = settings.validate().unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|error| error.split(':').next().unwrap()).collect();
+        assert_eq!(
+            fields,
+            [
+                "endpoints.ws_rpc_url",
+                "pairs.file",
+                "ipc.cycle_ms",
+                "export.format",
+                "oracle.max_deviation",
+                "reference.method",
+                "discovery.min_tvl",
+                "thresholds.quarantine_after",
+                "tiers",
+            ]
+        );
+    }
+
+    #[test]
+    fn tier_overrides_must_name_a_pair_in_the_pair_file() {
+        let mut settings = Settings::default();
+        settings.tier_overrides.insert("ARB/WETH".to_string(), TierOverride::default());
+        assert_eq!(settings.validate(), Ok(()));
+
+        settings.tier_overrides.insert("NOPE/WETH".to_string(), TierOverride::default());
+        assert_eq!(
+            settings.validate(),
+            Err(vec!["tiers: override for NOPE/WETH names no pair in the pair file".to_string()])
+        );
+    }
+
+    #[test]
+    fn reload_applies_the_reloadable_sections_and_names_the_rest() {
+        let running = Settings::default();
+        let mut file = running.clone();
+        file.logging.simulation = !running.logging.simulation;
+        file.oracle.max_deviation = 0.05;
+        file.thresholds.quarantine_after = 9;
+        file.tiers[0].min_spread = 0.05;
+        file.tier_overrides.insert("ARB/WETH".to_string(), TierOverride { enabled: Some(false), ..Default::default() });
+        file.endpoints.ws_rpc_url = "ws://elsewhere:8546".to_string();
+        file.oracle.enabled = !running.oracle.enabled;
+        file.discovery.min_tvl = 50_000.0;
+        file.recording.blocks_file = "blocks.jsonl".to_string();
+
+        let (next, restart) = running.reloaded(file.clone());
+        assert_eq!(restart, ["endpoints", "oracle.enabled", "discovery", "recording"]);
+
+        assert_eq!(next.logging.simulation, file.logging.simulation);
+        assert_eq!(next.oracle.max_deviation, 0.05);
+        assert_eq!(next.thresholds, file.thresholds);
+        assert_eq!(next.tiers, file.tiers);
+        assert_eq!(next.tier_overrides, file.tier_overrides);
+        // Sections that need a restart keep their running values
+        assert_eq!(next.endpoints, running.endpoints);
+        assert_eq!(next.oracle.enabled, running.oracle.enabled);
+        assert_eq!(next.discovery, running.discovery);
+        assert_eq!(next.recording, running.recording);
+
+        // Nothing changed, nothing to restart
+        assert!(running.reloaded(running.clone()).1.is_empty());
+    }
+}
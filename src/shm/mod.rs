@@ -1,20 +1,28 @@
-use std::fs::OpenOptions;
+use std::fs::{File, OpenOptions};
 use std::io::{self};
-use memmap2::{MmapMut, MmapOptions};
+use memmap2::{Mmap, MmapMut, MmapOptions};
 use serde::{Serialize, Deserialize};
 
-// Define the pool data structure
+use crate::depth::DepthLadder;
+
+// Bytes per pool slot; each slot holds a zero-padded JSON PoolFeed. Slot i is pool_a of the
+// pair at index i and slot i + 1 its pool_b, in load order of the pair file.
+pub const SLOT_SIZE: usize = 512;
+
+// Define the pool data structure. Price and liquidity are in USD.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PoolFeed {
     pub block_number: u64,
     pub price: f64,
-    pub liquidity: u128,
+    // balanceOf TVL of both tokens
+    pub liquidity_usd: f64,
     pub tick: i32,
     pub timestamp: u64,
+    // USD tradable within ±0.5/1/2% of the current price
+    pub depth: DepthLadder,
 }
 
 // Shared memory manager
-#[allow(dead_code)]
 pub struct SharedMemoryManager {
     mmap: MmapMut,
     pool_count: usize,
@@ -22,7 +30,7 @@ pub struct SharedMemoryManager {
 
 impl SharedMemoryManager {
     pub fn new(path: &str, pool_count: usize) -> io::Result<Self> {
-        let size = pool_count * std::mem::size_of::<PoolFeed>();
+        let size = pool_count * SLOT_SIZE;
         
         let file = OpenOptions::new()
             .read(true)
@@ -36,7 +44,12 @@ impl SharedMemoryManager {
         Ok(Self { mmap, pool_count })
     }
     
-    pub fn _update_pool(&mut self, index: usize, data: PoolFeed) -> io::Result<()> {
+    // Pools added at runtime are numbered past the slots allocated at startup
+    pub fn has_slot(&self, index: usize) -> bool {
+        index < self.pool_count
+    }
+
+    pub fn update_pool(&mut self, index: usize, data: PoolFeed) -> io::Result<()> {
         if index >= self.pool_count {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
@@ -47,8 +60,7 @@ impl SharedMemoryManager {
         let serialized = serde_json::to_vec(&data)
             .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
         
-        // Calculate exact size needed
-        let struct_size = std::mem::size_of::<PoolFeed>();
+        let struct_size = SLOT_SIZE;
 
         if serialized.len() > struct_size {
             return Err(io::Error::new(
@@ -63,9 +75,43 @@ impl SharedMemoryManager {
         let offset = index * struct_size;
         let end_offset = offset + struct_size;
         
+        // Readers map the same file shared, so they see the write without an msync
         self.mmap[offset..end_offset].copy_from_slice(&padded_data);
-        self.mmap.flush_async()?;
-        
+
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+// Read-only view of the file for consumers in other processes
+pub struct SharedMemoryReader {
+    mmap: Mmap,
+}
+
+impl SharedMemoryReader {
+    pub fn open(path: &str) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let mmap = unsafe { MmapOptions::new().map(&file)? };
+        Ok(Self { mmap })
+    }
+
+    pub fn slot_count(&self) -> usize {
+        self.mmap.len() / SLOT_SIZE
+    }
+
+    // None for a slot the scanner hasn't written yet
+    pub fn read_pool(&self, index: usize) -> io::Result<Option<PoolFeed>> {
+        let offset = index * SLOT_SIZE;
+        let slot = self.mmap.get(offset..offset + SLOT_SIZE).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("Pool index {} out of bounds (max {})", index, self.slot_count()))
+        })?;
+        decode_slot(slot)
+    }
+}
+
+pub fn decode_slot(slot: &[u8]) -> io::Result<Option<PoolFeed>> {
+    let len = slot.iter().position(|&b| b == 0).unwrap_or(slot.len());
+    if len == 0 {
+        return Ok(None);
+    }
+    serde_json::from_slice(&slot[..len]).map(Some).map_err(io::Error::other)
+}
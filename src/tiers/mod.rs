@@ -0,0 +1,377 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use tracing::info;
+
+use crate::config::{
+    TIER0_PRICE_DIFF, TIER1_PRICE_DIFF, TIER2_PRICE_DIFF, TIER3_PRICE_DIFF, TIER4_PRICE_DIFF,
+    TIER_MIN_DEPTH, get_trade_config,
+};
+use crate::error::{read_lock, write_lock};
+
+lazy_static! {
+    static ref TIER_TABLE: RwLock<Arc<TierTable>> = RwLock::new(Arc::new(TierTable::defaults()));
+}
+
+// One row of the tier table. A pair lands in the tier whose depth range holds its depth
+// at the tier band, and is only simulated once its spread reaches min_spread.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tier {
+    pub tier: u8,
+    pub min_depth: f64,
+    // Exclusive upper bound, open for the top tier
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<f64>,
+    pub min_spread: f64,
+    pub start: f64,
+    pub step: f64,
+    pub count: u32,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+// Per-pair changes applied on top of whichever tier the pair falls into
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TierOverride {
+    #[serde(default)]
+    pub min_spread: Option<f64>,
+    #[serde(default)]
+    pub start: Option<f64>,
+    #[serde(default)]
+    pub step: Option<f64>,
+    #[serde(default)]
+    pub count: Option<u32>,
+    #[serde(default)]
+    pub enabled: Option<bool>,
+}
+
+// Where a pool pair stands on a block before any size is simulated. The monitors and the
+// backtester both tier through TierTable::check, so they skip the same pairs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TierCheck {
+    // The depth falls in no tier's range
+    NoTier,
+    Disabled(Tier),
+    BelowSpread(Tier),
+    Simulate(Tier),
+}
+
+impl TierCheck {
+    pub fn tier(&self) -> Option<&Tier> {
+        match self {
+            TierCheck::NoTier => None,
+            TierCheck::Disabled(tier) | TierCheck::BelowSpread(tier) | TierCheck::Simulate(tier) => Some(tier),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TierTable {
+    pub tiers: Vec<Tier>,
+    // Keyed by pair name as in data.json
+    pub pairs: HashMap<String, TierOverride>,
+}
+
+impl Tier {
+    // Trade sizes in USDC to simulate. Sizes beyond the depth would move the price past
+    // the tier band, so they are dropped; the smallest size is always kept.
+    pub fn trade_amounts(&self, depth_usd: f64) -> Vec<f64> {
+        (0..self.count)
+            .map(|i| self.start + (self.step * i as f64))
+            .enumerate()
+            .filter(|(i, amount)| *i == 0 || *amount <= depth_usd)
+            .map(|(_, amount)| amount)
+            .collect()
+    }
+
+    // Whether the spread clears this tier's minimum. A disabled tier is checked for
+    // separately, before the spread.
+    pub fn accepts(&self, price_diff_pct: f64) -> bool {
+        price_diff_pct >= self.min_spread
+    }
+
+    fn contains(&self, depth_usd: f64) -> bool {
+        depth_usd >= self.min_depth && self.max_depth.is_none_or(|max| depth_usd < max)
+    }
+
+    fn apply(&mut self, over: &TierOverride) {
+        if let Some(min_spread) = over.min_spread {
+            self.min_spread = min_spread;
+        }
+        if let Some(start) = over.start {
+            self.start = start;
+        }
+        if let Some(step) = over.step {
+            self.step = step;
+        }
+        if let Some(count) = over.count {
+            self.count = count;
+        }
+        if let Some(enabled) = over.enabled {
+            self.enabled = enabled;
+        }
+    }
+}
+
+impl TierTable {
+    // The built-in table: TIER_MIN_DEPTH ranges, TIER*_PRICE_DIFF spreads and the
+    // START_AMOUNT/STEP/STEP_NUMBER_TIER* grid
+    pub fn defaults() -> Self {
+        let min_spreads = [TIER0_PRICE_DIFF, TIER1_PRICE_DIFF, TIER2_PRICE_DIFF, TIER3_PRICE_DIFF, TIER4_PRICE_DIFF];
+        let tiers = (0..TIER_MIN_DEPTH.len())
+            .map(|i| {
+                let (start, step, count) = get_trade_config(i as u8);
+                Tier {
+                    tier: i as u8,
+                    min_depth: TIER_MIN_DEPTH[i],
+                    max_depth: TIER_MIN_DEPTH.get(i + 1).copied(),
+                    min_spread: min_spreads[i],
+                    start,
+                    step,
+                    count,
+                    enabled: true,
+                }
+            })
+            .collect();
+        Self { tiers, pairs: HashMap::new() }
+    }
+
+    // Tiers must be listed by ascending min_depth, each ending where the next starts. When
+    // the pair names are known, every override must name one of them.
+    pub fn validate(&self, pair_names: Option<&HashSet<String>>) -> Result<(), String> {
+        if self.tiers.is_empty() {
+            return Err("no tiers defined".to_string());
+        }
+        for tier in &self.tiers {
+            if tier.min_depth < 0.0 || tier.max_depth.is_some_and(|max| max <= tier.min_depth) {
+                return Err(format!("tier {} has an empty depth range", tier.tier));
+            }
+            if tier.min_spread < 0.0 || tier.start <= 0.0 || tier.step < 0.0 || tier.count == 0 {
+                return Err(format!("tier {} needs min_spread >= 0, start > 0, step >= 0 and count > 0", tier.tier));
+            }
+        }
+        for pair in self.tiers.windows(2) {
+            let (lower, upper) = (&pair[0], &pair[1]);
+            if lower.tier == upper.tier {
+                return Err(format!("tier {} is defined twice", lower.tier));
+            }
+            if upper.min_depth < lower.min_depth {
+                return Err(format!(
+                    "tiers must be listed by ascending min_depth, but tier {} (${}) comes after tier {} (${})",
+                    upper.tier, upper.min_depth, lower.tier, lower.min_depth
+                ));
+            }
+            match lower.max_depth {
+                Some(max) if max == upper.min_depth => {}
+                Some(max) if max < upper.min_depth => {
+                    return Err(format!(
+                        "depths ${}-${} between tiers {} and {} fall in no tier",
+                        max, upper.min_depth, lower.tier, upper.tier
+                    ));
+                }
+                _ => return Err(format!("tiers {} and {} overlap", lower.tier, upper.tier)),
+            }
+        }
+        let mut pairs: Vec<_> = self.pairs.iter().collect();
+        pairs.sort_by(|a, b| a.0.cmp(b.0));
+        for (pair_name, over) in pairs {
+            if pair_names.is_some_and(|names| !names.contains(pair_name)) {
+                return Err(format!("override for {} names no pair in the pair file", pair_name));
+            }
+            if over.min_spread.is_some_and(|v| v < 0.0)
+                || over.start.is_some_and(|v| v <= 0.0)
+                || over.step.is_some_and(|v| v < 0.0)
+                || over.count == Some(0)
+            {
+                return Err(format!("override for {} is out of range", pair_name));
+            }
+        }
+        Ok(())
+    }
+
+    // Tier for a depth, without pair overrides
+    pub fn tier_for_depth(&self, depth_usd: f64) -> Option<Tier> {
+        self.tiers.iter().find(|tier| tier.contains(depth_usd)).cloned()
+    }
+
+    // Tier for a pair at a depth, with the pair's overrides applied
+    pub fn tier_for(&self, pair_name: &str, depth_usd: f64) -> Option<Tier> {
+        let mut tier = self.tier_for_depth(depth_usd)?;
+        if let Some(over) = self.pairs.get(pair_name) {
+            tier.apply(over);
+        }
+        Some(tier)
+    }
+
+    // Tier for a pair at a depth, and whether its spread gets it simulated
+    pub fn check(&self, pair_name: &str, depth_usd: f64, price_diff_pct: f64) -> TierCheck {
+        match self.tier_for(pair_name, depth_usd) {
+            None => TierCheck::NoTier,
+            Some(tier) if !tier.enabled => TierCheck::Disabled(tier),
+            Some(tier) if !tier.accepts(price_diff_pct) => TierCheck::BelowSpread(tier),
+            Some(tier) => TierCheck::Simulate(tier),
+        }
+    }
+}
+
+// Index of the most profitable of a tier's trade sizes, the smallest on a tie, when that
+// profit is positive
+pub fn best_size(profits: impl IntoIterator<Item = f64>) -> Option<usize> {
+    let mut best: Option<(usize, f64)> = None;
+    for (index, profit) in profits.into_iter().enumerate() {
+        if best.is_none_or(|(_, best_profit)| profit > best_profit) {
+            best = Some((index, profit));
+        }
+    }
+    best.filter(|(_, profit)| *profit > 0.0).map(|(index, _)| index)
+}
+
+// Replaces the table the monitors tier by, at startup and on config reload
+pub fn set_tier_table(table: TierTable) {
+    *write_lock(&TIER_TABLE) = Arc::new(table);
+}
+
+pub fn tier_table() -> Arc<TierTable> {
+    read_lock(&TIER_TABLE).clone()
+}
+
+// Logged rather than printed, so it stays off stdout under --json and off the dashboard
+pub fn log_tier_table(table: &TierTable) {
+    info!("🎯 Tier table ({} pair overrides):", table.pairs.len());
+    for tier in &table.tiers {
+        info!(
+            "   TIER {} | depth ${:.0}-{} | min spread {:.2}% | sizes {} + {} x {} | {}",
+            tier.tier,
+            tier.min_depth,
+            tier.max_depth.map(|max| format!("${:.0}", max)).unwrap_or_else(|| "∞".to_string()),
+            tier.min_spread * 100.0,
+            tier.start,
+            tier.step,
+            tier.count,
+            if tier.enabled { "enabled" } else { "disabled" }
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tier(tier: u8, min_depth: f64, max_depth: Option<f64>) -> Tier {
+        Tier { tier, min_depth, max_depth, min_spread: 0.01, start: 100.0, step: 50.0, count: 4, enabled: true }
+    }
+
+    fn table(tiers: Vec<Tier>) -> TierTable {
+        TierTable { tiers, pairs: HashMap::new() }
+    }
+
+    #[test]
+    fn default_table_is_valid() {
+        assert_eq!(TierTable::defaults().validate(None), Ok(()));
+    }
+
+    #[test]
+    fn validate_rejects_overlaps_gaps_and_unsorted_tiers() {
+        let overlap = table(vec![tier(0, 0.0, Some(6000.0)), tier(1, 5000.0, None)]);
+        assert_eq!(overlap.validate(None), Err("tiers 0 and 1 overlap".to_string()));
+
+        let open_below_top = table(vec![tier(0, 0.0, None), tier(1, 5000.0, None)]);
+        assert_eq!(open_below_top.validate(None), Err("tiers 0 and 1 overlap".to_string()));
+
+        let gap = table(vec![tier(0, 0.0, Some(4000.0)), tier(1, 5000.0, None)]);
+        assert_eq!(gap.validate(None), Err("depths $4000-$5000 between tiers 0 and 1 fall in no tier".to_string()));
+
+        let unsorted = table(vec![tier(1, 5000.0, None), tier(0, 0.0, Some(5000.0))]);
+        let error = unsorted.validate(None).unwrap_err();
+        assert!(error.starts_with("tiers must be listed by ascending min_depth"), "{}", error);
+
+        let twice = table(vec![tier(1, 0.0, Some(5000.0)), tier(1, 5000.0, None)]);
+        assert_eq!(twice.validate(None), Err("tier 1 is defined twice".to_string()));
+
+        let empty_range = table(vec![tier(0, 5000.0, Some(5000.0))]);
+        assert_eq!(empty_range.validate(None), Err("tier 0 has an empty depth range".to_string()));
+
+        assert_eq!(table(Vec::new()).validate(None), Err("no tiers defined".to_string()));
+    }
+
+    #[test]
+    fn validate_rejects_unknown_and_out_of_range_overrides() {
+        let mut tiers = table(vec![tier(0, 0.0, Some(5000.0)), tier(1, 5000.0, None)]);
+        tiers.pairs.insert("ARB/WETH".to_string(), TierOverride { min_spread: Some(0.003), ..Default::default() });
+        let names: HashSet<String> = ["ARB/WETH".to_string()].into_iter().collect();
+        assert_eq!(tiers.validate(Some(&names)), Ok(()));
+
+        tiers.pairs.insert("ARB/WTEH".to_string(), TierOverride::default());
+        assert_eq!(tiers.validate(Some(&names)), Err("override for ARB/WTEH names no pair in the pair file".to_string()));
+        // Without the pair file, only the values are checked
+        assert_eq!(tiers.validate(None), Ok(()));
+
+        tiers.pairs.remove("ARB/WTEH");
+        tiers.pairs.insert("ARB/WETH".to_string(), TierOverride { count: Some(0), ..Default::default() });
+        assert_eq!(tiers.validate(Some(&names)), Err("override for ARB/WETH is out of range".to_string()));
+    }
+
+    #[test]
+    fn tier_for_applies_the_pair_override_to_the_depth_tier() {
+        let mut tiers = table(vec![tier(0, 0.0, Some(5000.0)), tier(1, 5000.0, None)]);
+        tiers.pairs.insert(
+            "ARB/WETH".to_string(),
+            TierOverride { min_spread: Some(0.003), count: Some(2), enabled: Some(false), ..Default::default() },
+        );
+
+        let overridden = tiers.tier_for("ARB/WETH", 7000.0).unwrap();
+        assert_eq!(overridden.tier, 1);
+        assert_eq!((overridden.min_spread, overridden.count, overridden.enabled), (0.003, 2, false));
+        assert_eq!((overridden.start, overridden.step), (100.0, 50.0));
+
+        assert_eq!(tiers.tier_for("GMX/WETH", 7000.0), Some(tier(1, 5000.0, None)));
+        assert_eq!(tiers.tier_for("ARB/WETH", 4999.0).map(|tier| tier.tier), Some(0));
+        assert_eq!(tiers.tier_for("ARB/WETH", -1.0), None);
+    }
+
+    #[test]
+    fn accepts_checks_only_the_spread() {
+        let disabled = Tier { enabled: false, ..tier(0, 0.0, None) };
+        assert!(disabled.accepts(0.01));
+        assert!(!disabled.accepts(0.009));
+    }
+
+    #[test]
+    fn check_tiers_then_filters_disabled_tiers_before_the_spread() {
+        let mut tiers = table(vec![tier(0, 100.0, Some(5000.0)), tier(1, 5000.0, None)]);
+        tiers.pairs.insert("ARB/WETH".to_string(), TierOverride { enabled: Some(false), ..Default::default() });
+
+        assert_eq!(tiers.check("GMX/WETH", 50.0, 0.02), TierCheck::NoTier);
+        assert_eq!(tiers.check("GMX/WETH", 50.0, 0.02).tier(), None);
+        assert_eq!(tiers.check("GMX/WETH", 7000.0, 0.02), TierCheck::Simulate(tier(1, 5000.0, None)));
+        assert_eq!(tiers.check("GMX/WETH", 7000.0, 0.005), TierCheck::BelowSpread(tier(1, 5000.0, None)));
+        // Disabled wins even when the spread would clear the tier
+        let disabled = TierCheck::Disabled(Tier { enabled: false, ..tier(0, 100.0, Some(5000.0)) });
+        assert_eq!(tiers.check("ARB/WETH", 1000.0, 0.02), disabled);
+        assert_eq!(tiers.check("ARB/WETH", 1000.0, 0.005), disabled);
+    }
+
+    #[test]
+    fn best_size_takes_the_first_highest_positive_profit() {
+        assert_eq!(best_size([1.0, 3.0, 2.0]), Some(1));
+        assert_eq!(best_size([1.0, 3.0, 3.0]), Some(1));
+        assert_eq!(best_size([-1.0, 0.5]), Some(1));
+        assert_eq!(best_size([-1.0, 0.0]), None);
+        assert_eq!(best_size(Vec::new()), None);
+    }
+
+    #[test]
+    fn trade_amounts_stop_at_the_depth_but_keep_the_smallest_size() {
+        let tier = tier(0, 0.0, None);
+        assert_eq!(tier.trade_amounts(1000.0), vec![100.0, 150.0, 200.0, 250.0]);
+        assert_eq!(tier.trade_amounts(200.0), vec![100.0, 150.0, 200.0]);
+        assert_eq!(tier.trade_amounts(10.0), vec![100.0]);
+    }
+}
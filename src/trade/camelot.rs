@@ -1,49 +1,34 @@
-use crate::config::WETH_ADDRESS;
 use ethers::prelude::*;
 use std::sync::Arc;
 
+use crate::error::{ScanError, ScanResult, decimals_scale, parse_address, to_u128};
+use crate::exchange::model::Dex;
+use crate::settings::settings;
+use crate::trade::orient_swap;
+
 pub async fn simulate_camelot_trade_with_slippage<M: Middleware + 'static>(
-    mut token_in: Address,
-    mut token_in_decimals: u8,
-    mut token_out: Address,
-    mut token_out_decimals: u8,
+    token_in: Address,
+    token_in_decimals: u8,
+    token_out: Address,
+    token_out_decimals: u8,
     amount_in: f64,
+    base: Address,
     direction: &str,
     provider: Arc<M>,
-) -> Result<(f64, u32), Box<dyn std::error::Error + Send + Sync>> {
+) -> ScanResult<(f64, u32)> {
     let client = Arc::new(provider.clone());
-    let quoter_address: Address = "0x0Fc73040b26E9bC8514fA028D998E73A254Fa76E".parse()?;
+    let quoter_address = parse_address(&settings().dex(Dex::Camelot).quoter)?;
     abigen!(CamelotQuoter, "./src/abis/CamelotQuoter.json",);
 
-    match direction {
-        "BUY" => {
-            if hex::encode(token_in.as_bytes()) != WETH_ADDRESS.to_lowercase() {
-                // Swap tokens if token_in is not WETH
-                std::mem::swap(&mut token_in, &mut token_out);
-                std::mem::swap(&mut token_in_decimals, &mut token_out_decimals);
-            }
-        }
-        "SELL" => {
-            if hex::encode(token_in.as_bytes()) == WETH_ADDRESS.to_lowercase() {
-                // Swap tokens if token_in is WETH
-                std::mem::swap(&mut token_in, &mut token_out);
-                std::mem::swap(&mut token_in_decimals, &mut token_out_decimals);
-            }
-        }
-        _ => {
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                "Direction must be either 'BUY' or 'SELL'",
-            )));
-        }
-    }
+    let ((token_in, token_in_decimals), (token_out, token_out_decimals)) =
+        orient_swap((token_in, token_in_decimals), (token_out, token_out_decimals), base, direction)?;
 
     let quoter: CamelotQuoter<Arc<M>> = CamelotQuoter::new(quoter_address, client.clone());
 
     // Simulate quote
     let sqrt_price_limit_x96 = U256::zero();
     let adjusted_amount_in_unit =
-        U256::from((1.0 * (10u64.pow(token_in_decimals as u32) as f64)) as u64);
+        U256::from(decimals_scale(token_in_decimals)?);
 
     let (amount_out_unit, fee) = match quoter
         .quote_exact_input_single(
@@ -57,15 +42,12 @@ pub async fn simulate_camelot_trade_with_slippage<M: Middleware + 'static>(
     {
         Ok((amount_out, fee)) => (amount_out, fee),
         Err(e) => {
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("Failed to get quote from Camelot: {}", e),
-            )));
+            return Err(ScanError::from(e).context("Camelot quote"));
         }
     };
 
-    let amount_with_decimals = amount_in * amount_out_unit.as_u128() as f64
-        / (10u128.pow(token_out_decimals as u32) as f64);
+    let amount_with_decimals = amount_in * to_u128(amount_out_unit, "quoted amount")? as f64
+        / decimals_scale(token_out_decimals)? as f64;
 
     Ok((amount_with_decimals, fee.into()))
 }
@@ -0,0 +1,74 @@
+use crate::pool_state::PoolSnapshot;
+
+// Simulates a swap against a recorded pool state without touching the node.
+// The swap is priced with the pool's active liquidity as if it stayed within the
+// current tick range, which holds for sizes that are small relative to in-range depth.
+pub fn simulate_local_trade(
+    pool: &PoolSnapshot,
+    amount_in: f64,
+    direction: &str,
+) -> Result<f64, Box<dyn std::error::Error + Send + Sync>> {
+    let token0_is_base = pool.token0.eq_ignore_ascii_case(&pool.base_token);
+
+    // BUY spends the base token for the other token, SELL spends the other token for the base
+    let zero_for_one = match direction {
+        "BUY" => token0_is_base,
+        "SELL" => !token0_is_base,
+        _ => {
+            return Err(Box::new(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Direction must be either 'BUY' or 'SELL'",
+            )));
+        }
+    };
+
+    swap_local(pool, zero_for_one, amount_in)
+}
+
+// Same as simulate_local_trade for any pool, with the input token given explicitly.
+// Used by multi-hop routes whose legs don't touch the pool's base token.
+pub fn simulate_local_swap(
+    pool: &PoolSnapshot,
+    token_in: &str,
+    amount_in: f64,
+) -> Result<f64, Box<dyn std::error::Error + Send + Sync>> {
+    if token_in.eq_ignore_ascii_case(&pool.token0) {
+        swap_local(pool, true, amount_in)
+    } else if token_in.eq_ignore_ascii_case(&pool.token1) {
+        swap_local(pool, false, amount_in)
+    } else {
+        Err(format!("Token {} is not traded by pool {}", token_in, pool.pool_address).into())
+    }
+}
+
+fn swap_local(
+    pool: &PoolSnapshot,
+    zero_for_one: bool,
+    amount_in: f64,
+) -> Result<f64, Box<dyn std::error::Error + Send + Sync>> {
+    let (decimals_in, decimals_out) = if zero_for_one {
+        (pool.token0_decimals, pool.token1_decimals)
+    } else {
+        (pool.token1_decimals, pool.token0_decimals)
+    };
+
+    let sqrt_price = pool.sqrt_price_x96.parse::<f64>()? / 2.0f64.powi(96);
+    let liquidity = pool.active_liquidity as f64;
+    if sqrt_price <= 0.0 || liquidity <= 0.0 {
+        return Err(format!("Pool {} has no active liquidity", pool.pool_address).into());
+    }
+
+    // Fees are in hundredths of a bip; Camelot charges a different fee each way
+    let fee = pool.fee_for(zero_for_one);
+    let amount_in_raw = amount_in * 10f64.powi(decimals_in as i32) * (1.0 - fee as f64 / 1_000_000.0);
+
+    let amount_out_raw = if zero_for_one {
+        let sqrt_price_next = 1.0 / (1.0 / sqrt_price + amount_in_raw / liquidity);
+        liquidity * (sqrt_price - sqrt_price_next)
+    } else {
+        let sqrt_price_next = sqrt_price + amount_in_raw / liquidity;
+        liquidity * (1.0 / sqrt_price - 1.0 / sqrt_price_next)
+    };
+
+    Ok(amount_out_raw / 10f64.powi(decimals_out as i32))
+}
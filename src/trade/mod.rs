@@ -1,3 +1,101 @@
 pub mod camelot;
 pub mod uniswap;
-pub mod pancake;
\ No newline at end of file
+pub mod pancake;
+pub mod local;
+
+use ethers::types::Address;
+
+use crate::error::{ScanError, ScanResult};
+
+// Orders a quote's (token, decimals) pairs: BUY spends the base token for the other one,
+// SELL spends the other token for the base
+pub fn orient_swap(
+    token_in: (Address, u8),
+    token_out: (Address, u8),
+    base: Address,
+    direction: &str,
+) -> ScanResult<((Address, u8), (Address, u8))> {
+    let spends_base = match direction {
+        "BUY" => true,
+        "SELL" => false,
+        _ => return Err(ScanError::Config(format!("Direction must be either 'BUY' or 'SELL', got '{}'", direction))),
+    };
+    if (token_in.0 == base) == spends_base {
+        Ok((token_in, token_out))
+    } else {
+        Ok((token_out, token_in))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::local::simulate_local_trade;
+    use super::orient_swap;
+    use crate::pool_state::PoolSnapshot;
+    use ethers::types::Address;
+
+    const WETH: &str = "0x82af49447d8a07e3bd95bd0d56f35241523fbab1";
+    const USDC: &str = "0xaf88d065e77c8cc2239327c5edb3a432268e5831";
+    const WBTC: &str = "0x2f2a2543b76a4166549f7aab2e75bef0aefc5b0f";
+
+    fn address(address: &str) -> Address {
+        address.parse().unwrap()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() <= expected.abs() * 1e-5, "{} != {}", actual, expected);
+    }
+
+    // Deep 0.05% pool at a raw token1/token0 price, so small swaps barely move it
+    fn pool(token0: &str, token0_decimals: u8, token1: &str, token1_decimals: u8, raw_price: f64, base: &str) -> PoolSnapshot {
+        PoolSnapshot {
+            dex: "Uniswap".to_string(),
+            pool_address: "pool".to_string(),
+            token0: token0.to_string(),
+            token1: token1.to_string(),
+            token0_decimals,
+            token1_decimals,
+            sqrt_price_x96: format!("{:.0}", raw_price.sqrt() * 2f64.powi(96)),
+            active_liquidity: 10u128.pow(20),
+            tick: 0,
+            initialized_ticks: Vec::new(),
+            fee: 500,
+            fee_otz: None,
+            price: 0.0,
+            liquidity: 0,
+            base_token: base.to_string(),
+            weth_usdc: 3000.0,
+            base_usd: 0.0,
+        }
+    }
+
+    #[test]
+    fn buy_spends_base_and_sell_receives_it() {
+        let (weth, usdc, wbtc) = ((address(WETH), 18), (address(USDC), 6), (address(WBTC), 8));
+        // USDC is token1 of WETH/USDC; either order in gives the same quote
+        assert_eq!(orient_swap(weth, usdc, usdc.0, "BUY").unwrap(), (usdc, weth));
+        assert_eq!(orient_swap(usdc, weth, usdc.0, "BUY").unwrap(), (usdc, weth));
+        assert_eq!(orient_swap(usdc, weth, usdc.0, "SELL").unwrap(), (weth, usdc));
+        // WBTC is token0 of WBTC/WETH and keeps its 8 decimals
+        assert_eq!(orient_swap(weth, wbtc, wbtc.0, "BUY").unwrap(), (wbtc, weth));
+        assert_eq!(orient_swap(wbtc, weth, wbtc.0, "SELL").unwrap(), (weth, wbtc));
+        assert!(orient_swap(wbtc, weth, wbtc.0, "HOLD").is_err());
+    }
+
+    #[test]
+    fn local_trade_with_usdc_base_as_token1() {
+        // 3000 USDC per WETH
+        let pool = pool(WETH, 18, USDC, 6, 3000.0 * 1e-12, USDC);
+        // BUY spends 3000 USDC for about one WETH, SELL spends one WETH for about 3000 USDC
+        assert_close(simulate_local_trade(&pool, 3000.0, "BUY").unwrap(), 0.9995);
+        assert_close(simulate_local_trade(&pool, 1.0, "SELL").unwrap(), 3000.0 * 0.9995);
+    }
+
+    #[test]
+    fn local_trade_with_wbtc_base_as_token0() {
+        // 20 WETH per WBTC
+        let pool = pool(WBTC, 8, WETH, 18, 20.0 * 1e10, WBTC);
+        assert_close(simulate_local_trade(&pool, 1.0, "BUY").unwrap(), 20.0 * 0.9995);
+        assert_close(simulate_local_trade(&pool, 20.0, "SELL").unwrap(), 0.9995);
+    }
+}
@@ -1,49 +1,36 @@
 use ethers::prelude::*;
 use std::sync::Arc;
-use crate::config::WETH_ADDRESS;
+
+use crate::error::{ScanError, ScanResult, decimals_scale, parse_address, to_u128};
+use crate::exchange::model::Dex;
+use crate::settings::settings;
+use crate::trade::orient_swap;
 
 pub async fn simulate_pancake_trade_with_slippage<M: Middleware + 'static>(
-    mut token_in: Address,
-    mut token_in_decimals: u8,
-    mut token_out: Address,
-    mut token_out_decimals: u8,
+    token_in: Address,
+    token_in_decimals: u8,
+    token_out: Address,
+    token_out_decimals: u8,
     amount_in: f64,
     fee: u32,
+    base: Address,
     direction: &str,
     provider: Arc<M>,
-) -> Result<f64, Box<dyn std::error::Error + Send + Sync>> {
+) -> ScanResult<f64> {
     let client = Arc::new(provider.clone());
-    let quoter_address: Address = "0xB048Bbc1Ee6b733FFfCFb9e9CeF7375518e25997".parse()?;
+    let quoter_address = parse_address(&settings().dex(Dex::PancakeSwap).quoter)?;
     abigen!(
         QuoterV3,
         "./src/abis/QuoterV3.json",
     );
-    match direction {
-        "BUY" => {
-            if hex::encode(token_in.as_bytes()) != WETH_ADDRESS.to_lowercase() {
-                // Swap tokens if token_in is not WETH
-                std::mem::swap(&mut token_in, &mut token_out);
-                std::mem::swap(&mut token_in_decimals, &mut token_out_decimals);
-            }
-        },
-        "SELL" => {
-            if hex::encode(token_in.as_bytes()) == WETH_ADDRESS.to_lowercase() {
-                // Swap tokens if token_in is WETH
-                std::mem::swap(&mut token_in, &mut token_out);
-                std::mem::swap(&mut token_in_decimals, &mut token_out_decimals);
-            }
-        },
-        _ => return Err(Box::new(std::io::Error::new(
-            std::io::ErrorKind::InvalidInput,
-            "Direction must be either 'BUY' or 'SELL'"
-        ))),
-    }
+    let ((token_in, token_in_decimals), (token_out, token_out_decimals)) =
+        orient_swap((token_in, token_in_decimals), (token_out, token_out_decimals), base, direction)?;
 
     let quoter: QuoterV3<Arc<M>> = QuoterV3::new(quoter_address, client.clone());
     // Simulate quote
     // let sqrt_price_limit_x96 = U256::zero();
     let sqrt_price_limit_x96 = get_price_limit(&token_in, &token_out);
-    let adjusted_amount_in_unit = U256::from((1.0 * (10u64.pow(token_in_decimals as u32) as f64)) as u64);
+    let adjusted_amount_in_unit = U256::from(decimals_scale(token_in_decimals)?);
     // Create the params struct for the quote
     let params_input = QuoteExactInputSingleParams {
         token_in,
@@ -66,10 +53,7 @@ pub async fn simulate_pancake_trade_with_slippage<M: Middleware + 'static>(
             let (amount_out, _, _, _) = match quoter.quote_exact_input_single(params_input).call().await {
                 Ok(quote_result) => quote_result,
                 Err(e) => {
-                    return Err(Box::new(std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        format!("Failed to get quote from PancakeSwap Buy: {}", e)
-                    )));
+                    return Err(ScanError::from(e).context("PancakeSwap Buy quote"));
                 }
             };
             amount_out
@@ -78,18 +62,15 @@ pub async fn simulate_pancake_trade_with_slippage<M: Middleware + 'static>(
             let (amount_out, _, _, _) = match quoter.quote_exact_input_single(params_output).call().await {
                 Ok(quote_result) => quote_result,
                 Err(e) => {
-                    return Err(Box::new(std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        format!("Failed to get quote from PancakeSwap Sell: {}", e)
-                    )));
+                    return Err(ScanError::from(e).context("PancakeSwap Sell quote"));
                 }
             };
             amount_out
         },
-        _ => unreachable!()
+        _ => return Err(ScanError::Config(format!("Direction must be either 'BUY' or 'SELL', got '{}'", direction))),
     };
 
-    let amount_with_decimals = amount_in * amount_out_unit.as_u128() as f64 / (10u128.pow(token_out_decimals as u32) as f64);
+    let amount_with_decimals = amount_in * to_u128(amount_out_unit, "quoted amount")? as f64 / decimals_scale(token_out_decimals)? as f64;
     Ok(amount_with_decimals)
 }
 
@@ -0,0 +1,218 @@
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::TUI_REFRESH_INTERVAL;
+use crate::live::{LiveBoard, PairSpread};
+use crate::metrics::{QUARANTINED_POOLS, RpcStats, rpc_stats};
+
+struct Dashboard {
+    board: Arc<LiveBoard>,
+    table: TableState,
+    // Rows as last drawn, so keys act on what is on screen
+    rows: Vec<PairSpread>,
+    // RPC totals at the previous redraw and the latency since then, by endpoint
+    rpc: HashMap<String, RpcStats>,
+    recent_latency: HashMap<String, f64>,
+}
+
+// Runs the dashboard until q, Esc or Ctrl-C is pressed or `stop` is set, then gives the
+// terminal back. Blocking; run it off the async runtime.
+pub fn run(board: Arc<LiveBoard>, stop: Arc<AtomicBool>) -> std::io::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = Dashboard::new(board).run(&mut terminal, &stop);
+    ratatui::restore();
+    result
+}
+
+impl Dashboard {
+    fn new(board: Arc<LiveBoard>) -> Self {
+        Self {
+            board,
+            table: TableState::default().with_selected(Some(0)),
+            rows: Vec::new(),
+            rpc: HashMap::new(),
+            recent_latency: HashMap::new(),
+        }
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal, stop: &AtomicBool) -> std::io::Result<()> {
+        while !stop.load(Ordering::Relaxed) {
+            self.refresh();
+            terminal.draw(|frame| self.draw(frame))?;
+            if !event::poll(TUI_REFRESH_INTERVAL)? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+                KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+                KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+                KeyCode::Char(' ') | KeyCode::Char('p') => {
+                    if let Some(row) = self.table.selected().and_then(|index| self.rows.get(index)) {
+                        self.board.toggle_pause(&row.pair);
+                    }
+                }
+                KeyCode::Char('u') => self.board.resume_all(),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn refresh(&mut self) {
+        self.rows = self.board.spreads();
+        for stats in rpc_stats() {
+            if let Some(previous) = self.rpc.get(&stats.endpoint) {
+                let requests = stats.requests.saturating_sub(previous.requests);
+                if requests > 0 {
+                    let seconds = stats.avg_seconds * stats.requests as f64 - previous.avg_seconds * previous.requests as f64;
+                    self.recent_latency.insert(stats.endpoint.clone(), seconds / requests as f64);
+                }
+            }
+            self.rpc.insert(stats.endpoint.clone(), stats);
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header, pairs, bottom] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(6), Constraint::Length(12)]).areas(frame.area());
+        let [opportunities, health] =
+            Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(bottom);
+
+        self.draw_header(frame, header);
+        self.draw_pairs(frame, pairs);
+        self.draw_opportunities(frame, opportunities);
+        self.draw_health(frame, health);
+    }
+
+    fn draw_header(&self, frame: &mut Frame, area: Rect) {
+        let latest = self.board.monitors().iter().map(|monitor| monitor.block_number).max();
+        let text = format!(
+            " Scanner_Bot | block {} | {} pool pairs | {} paused | {} quarantined pools | q quit, ↑↓ select, space pause, u resume all",
+            latest.map_or("-".to_string(), |block| format!("#{}", block)),
+            self.rows.len(),
+            self.board.paused_count(),
+            QUARANTINED_POOLS.get()
+        );
+        frame.render_widget(Paragraph::new(text).style(Style::new().add_modifier(Modifier::BOLD)), area);
+    }
+
+    fn draw_pairs(&mut self, frame: &mut Frame, area: Rect) {
+        let rows = self.rows.iter().map(|spread| {
+            let paused = self.board.is_paused(&spread.pair);
+            let row = Row::new(vec![
+                format!("{}{}", if paused { "⏸ " } else { "" }, spread.pair),
+                format!("{} ${:.6}", spread.dex_a, spread.price_a),
+                format!("{} ${:.6}", spread.dex_b, spread.price_b),
+                format!("{:.4}%", spread.spread * 100.0),
+                spread.tier.map_or("-".to_string(), |tier| tier.to_string()),
+                spread.outcome.to_string(),
+                format!("#{}", spread.block_number),
+            ]);
+            if paused {
+                row.style(Style::new().fg(Color::DarkGray))
+            } else if spread.best_profit_usd.is_some_and(|profit| profit > 0.0) {
+                row.style(Style::new().fg(Color::Green))
+            } else {
+                row
+            }
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(2),
+                Constraint::Fill(2),
+                Constraint::Fill(2),
+                Constraint::Length(10),
+                Constraint::Length(4),
+                Constraint::Length(12),
+                Constraint::Length(11),
+            ],
+        )
+        .header(
+            Row::new(["Pair", "Pool A", "Pool B", "Spread", "Tier", "Outcome", "Block"])
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+        .block(Block::bordered().title(" Pairs "));
+        frame.render_stateful_widget(table, area, &mut self.table);
+    }
+
+    fn draw_opportunities(&self, frame: &mut Frame, area: Rect) {
+        let rows = self.board.opportunities().into_iter().map(|spread| {
+            Row::new(vec![
+                format!("#{}", spread.block_number),
+                spread.pair,
+                format!("{}-{}", spread.dex_a, spread.dex_b),
+                format!("{:.4}%", spread.spread * 100.0),
+                spread.best_amount_usd.map_or("-".to_string(), |amount| format!("${:.0}", amount)),
+                spread.best_profit_usd.map_or("-".to_string(), |profit| format!("${:.4}", profit)),
+                spread.outcome.to_string(),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(11),
+                Constraint::Fill(2),
+                Constraint::Fill(2),
+                Constraint::Length(9),
+                Constraint::Length(8),
+                Constraint::Length(10),
+                Constraint::Length(10),
+            ],
+        )
+        .header(
+            Row::new(["Block", "Pair", "DEXes", "Spread", "Size", "Profit", "Outcome"])
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().title(" Recent opportunities "));
+        frame.render_widget(table, area);
+    }
+
+    fn draw_health(&self, frame: &mut Frame, area: Rect) {
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let mut lines = vec![Line::from("Blocks").style(Style::new().add_modifier(Modifier::BOLD))];
+        for monitor in self.board.monitors() {
+            let age = now_ms.saturating_sub(monitor.finished_ms) as f64 / 1000.0;
+            lines.push(Line::from(format!(
+                "{:<20} #{} in {} ms, {:.0}s ago",
+                monitor.monitor, monitor.block_number, monitor.duration_ms, age
+            )));
+        }
+        lines.push(Line::from("RPC").style(Style::new().add_modifier(Modifier::BOLD)));
+        let mut endpoints: Vec<&RpcStats> = self.rpc.values().collect();
+        endpoints.sort_by(|a, b| a.endpoint.cmp(&b.endpoint));
+        for stats in endpoints {
+            let error_pct = if stats.requests > 0 { stats.errors as f64 * 100.0 / stats.requests as f64 } else { 0.0 };
+            let latency = self.recent_latency.get(&stats.endpoint).copied().unwrap_or(stats.avg_seconds);
+            let line = Line::from(format!(
+                "{} {} calls, {} errors ({:.1}%), {:.0} ms",
+                stats.endpoint,
+                stats.requests,
+                stats.errors,
+                error_pct,
+                latency * 1000.0
+            ));
+            lines.push(if stats.errors > 0 { line.style(Style::new().fg(Color::Yellow)) } else { line });
+        }
+        frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(" Health ")), area);
+    }
+}
@@ -1,29 +1,21 @@
-use std::fs::{File, OpenOptions};
-use std::io::{BufRead, BufReader, Write};
-use std::sync::Mutex;
-use lazy_static::lazy_static;
-use chrono::{Local, Utc};
+use tracing::info;
 
-lazy_static! {
-    static ref LOG_MUTEX: Mutex<()> = Mutex::new(());
-}
+use crate::depth::DepthLadder;
+use crate::logging::{PRICE_LIQUIDITY_TARGET, SIMULATION_TARGET};
 
-// Function to log price and liquidity information
+// Price and liquidity of both pools of a pair on one block, as a structured event
 pub fn log_price_liquidity(
-    file: &mut File,
-    timestamp: u64,
     pair_name: &str,
     pool_a_addr: &str,
     pool_b_addr: &str,
     min_liquidity: f64,
+    depth: &DepthLadder,
     price_a: f64,
     price_b: f64,
     price_diff_pct: f64,
     exchange_a: &str,
     exchange_b: &str,
-) -> std::io::Result<()> {
-    let datetime = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-    
+) {
     // Choose emoji based on price difference
     let emoji = if price_diff_pct > 0.01 {
         "🔥" // Hot opportunity
@@ -43,36 +35,34 @@ pub fn log_price_liquidity(
     } else {
         "💸" // Low liquidity
     };
-    
-    // Format the log entry
-    let log_entry = format!(
-        "{} | {} {} | Pair: {} | {} Pool: {} | {} Pool: {} | Min Liquidity: ${:.2} {} | {} Price: ${:.6} | {} Price: ${:.6} | Diff: {:.4}% {}\n",
-        datetime,
-        timestamp,
+
+    info!(
+        target: PRICE_LIQUIDITY_TARGET,
+        pair = pair_name,
+        dex_a = exchange_a,
+        pool_a = pool_a_addr,
+        price_a,
+        dex_b = exchange_b,
+        pool_b = pool_b_addr,
+        price_b,
+        diff_pct = price_diff_pct * 100.0,
+        min_liquidity,
+        depth = %depth.summary(),
+        "{} {} {} ${:.6} / {} ${:.6} diff {:.4}% {} liquidity ${:.2}",
         emoji,
         pair_name,
         exchange_a,
-        pool_a_addr,
-        exchange_b,
-        pool_b_addr,
-        min_liquidity,
-        liq_emoji,
-        exchange_a,
         price_a,
         exchange_b,
         price_b,
         price_diff_pct * 100.0,
-        emoji
+        liq_emoji,
+        min_liquidity
     );
-    
-    file.write_all(log_entry.as_bytes())?;
-    file.flush()?;
-    
-    Ok(())
 }
 
+// One simulated trade size: both pools, the amounts through them and the profit
 pub fn log_simulation(
-    file_path: &str, 
     buy_dex: &str,
     buy_addr: &str,
     buy_dex_token0_amount: f64,
@@ -93,10 +83,7 @@ pub fn log_simulation(
     profit_weth: f64,
     profit_usdc: f64,
     tier: u8,
-) -> std::io::Result<()> {
-    let _lock = LOG_MUTEX.lock().unwrap(); // Lock the mutex to ensure thread safety
-    let now = Utc::now();
-    let timestamp = now.format("%Y-%m-%d %H:%M:%S%.3f").to_string();
+) {
     let tier_indicator = match tier {
         4 => "🔴 TIER 4",
         3 => "🟣 TIER 3",
@@ -105,85 +92,36 @@ pub fn log_simulation(
         0 => "⚪ TIER 0",
         _ => "⚫ NO TIER",
     };
-    let mut file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(file_path)?;
-
-    writeln!(file, "🕒 [{timestamp}] Trade Simulation Started")?;
-    writeln!(file, "📊 {tier_indicator}")?;
-    writeln!(file, "💰 Buy on {buy_dex}")?;
-    writeln!(file, "   ├─ Pool Address: {buy_addr} 📍")?;
-    writeln!(file, "   ├─ Token0 Amount: {:.6} 💎 Token1 Amount: {:.6} 💎", buy_dex_token0_amount, buy_dex_token1_amount)?;
-    writeln!(file, "   ├─ Price WETH: {:.6} 🔷 USDC: {:.6} 💵", buy_weth_price, buy_usdc_price)?;
-    writeln!(file, "   ├─ Amount In: {:.6} WETH {:.6} USDC 📥", amount_in_weth, amount_in_usdc)?;
-    writeln!(file, "   └─ Amount Out: {:.6} TOKEN 📤", buy_amount_out)?;
-    writeln!(file, "💱 Sell on {sell_dex}")?;
-    writeln!(file, "   ├─ Pool Address: {sell_addr} 📍")?;
-    writeln!(file, "   ├─ Token0 Amount: {:.6} 💎 Token1 Amount: {:.6} 💎", sell_dex_token0_amount, sell_dex_token1_amount)?;
-    writeln!(file, "   ├─ Price WETH: {:.6} 🔷 USDC: {:.6} 💵", sell_weth_price, sell_usdc_price)?;
-    writeln!(file, "   ├─ Amount In: {:.6} TOKEN 📥", buy_amount_out)?;
-    writeln!(file, "   └─ Amount Out: {:.6} WETH {:.6} USDC 📤", sell_amount_out, sell_amount_out_usdc)?;
-    writeln!(file, "💫 Profit: {:.6} WETH {:.6} USDC {}", profit_weth, profit_usdc, if profit_weth > 0.0 { "📈" } else { "📉" })?;
-    writeln!(file, "━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━")?;
-
-    file.flush()?;
-
-    Ok(())
-}
-
-pub fn log_fee_data(
-    dex: &str,
-    pair_name: &str, 
-    pair_address: &str,
-    fee: u32,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let file_path = "fee.log";
-    let _lock = LOG_MUTEX.lock().unwrap(); // Add thread safety
-    
-    // Validate inputs
-    if dex.is_empty() || pair_name.is_empty() || pair_address.len() != 42 {
-        return Err("Invalid input parameters".into());
-    }
-
-    // Read existing entries with proper error handling
-    let mut entries = Vec::new();
-    if let Ok(file) = File::open(file_path) {
-        let reader = BufReader::new(file);
-        for line in reader.lines() {
-            if let Ok(line) = line {
-                // Skip malformed lines
-                if line.split(',').count() != 4 {
-                    continue;
-                }
-                entries.push(line);
-            }
-        }
-    }
 
-    // Remove existing entry for this pair if exists
-    entries.retain(|line| !line.contains(pair_address));
-    
-    // Add new entry with proper formatting
-    let new_entry = format!("{},{},{},{}%", 
-        dex.trim(),
-        pair_name.trim(),
-        pair_address.trim(),
-        fee as f64 / 10000.0
+    info!(
+        target: SIMULATION_TARGET,
+        tier,
+        buy_dex,
+        buy_pool = buy_addr,
+        buy_token0_amount = buy_dex_token0_amount,
+        buy_token1_amount = buy_dex_token1_amount,
+        buy_price = buy_weth_price,
+        buy_usdc_price,
+        sell_dex,
+        sell_pool = sell_addr,
+        sell_token0_amount = sell_dex_token0_amount,
+        sell_token1_amount = sell_dex_token1_amount,
+        sell_price = sell_weth_price,
+        sell_usdc_price,
+        amount_in = amount_in_weth,
+        amount_in_usdc,
+        buy_amount_out,
+        sell_amount_out,
+        sell_amount_out_usdc,
+        profit = profit_weth,
+        profit_usdc,
+        "{} {} -> {} in {:.6} out {:.6} profit {:.6} USDC {}",
+        tier_indicator,
+        buy_dex,
+        sell_dex,
+        amount_in_weth,
+        sell_amount_out,
+        profit_usdc,
+        if profit_weth > 0.0 { "📈" } else { "📉" }
     );
-    entries.push(new_entry);
-
-    // Write entries with proper error handling
-    let mut file = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(file_path)?;
-
-    for entry in entries {
-        writeln!(file, "{}", entry)?;
-    }
-    file.flush()?;
-
-    Ok(())
 }
@@ -0,0 +1,81 @@
+use alloy::providers::{Provider, ProviderBuilder, WsConnect};
+use futures_util::StreamExt;
+use futures_util::stream::Stream;
+use std::future::Future;
+use std::pin::Pin;
+use tokio::sync::broadcast;
+
+use crate::rpc_replay::{FixtureEntry, FixtureWriter};
+
+pub type BlockStream = Pin<Box<dyn Stream<Item = u64> + Send>>;
+
+// Where the monitors get their block numbers from
+pub enum BlockSource {
+    // One newHeads subscription fanned out to every monitor
+    Live(broadcast::Receiver<u64>),
+    // Block numbers taken from an RPC fixture
+    Replay(Vec<u64>),
+}
+
+impl BlockSource {
+    pub async fn live(fixture: Option<FixtureWriter>) -> Result<Self, Box<dyn std::error::Error>> {
+        let ws = WsConnect::new(crate::websocket::ws_rpc_url()?);
+        let provider_alloy = ProviderBuilder::new().on_ws(ws).await?;
+
+        // Subscribe to new blocks.
+        let mut block_stream = provider_alloy
+            .subscribe_blocks()
+            .await
+            .map_err(|e| format!("failed to subscribe to new blocks: {}", e))?
+            .into_stream();
+
+        let (tx, rx) = broadcast::channel(64);
+
+        // The WebSocket transport reconnects and resubscribes on its own; the stream only
+        // ends once it gives up, which closes the channel
+        tokio::spawn(async move {
+            // The subscription only lives as long as its provider
+            let _provider_alloy = provider_alloy;
+            while let Some(block) = block_stream.next().await {
+                if let Some(fixture) = &fixture {
+                    fixture.write(FixtureEntry::Block { number: block.number });
+                }
+                // Fails only when no monitor is listening yet
+                let _ = tx.send(block.number);
+            }
+        });
+
+        Ok(BlockSource::Live(rx))
+    }
+
+    pub fn subscribe(&self) -> BlockStream {
+        match self {
+            BlockSource::Live(rx) => {
+                Box::pin(futures_util::stream::unfold(rx.resubscribe(), |mut rx| async move {
+                    loop {
+                        match rx.recv().await {
+                            Ok(block_number) => return Some((block_number, rx)),
+                            // A slow monitor drops the blocks it fell behind on
+                            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                            Err(broadcast::error::RecvError::Closed) => return None,
+                        }
+                    }
+                }))
+            }
+            BlockSource::Replay(blocks) => Box::pin(futures_util::stream::iter(blocks.clone())),
+        }
+    }
+
+    // Resolves once the newHeads subscription is gone for good; never for a replay
+    pub fn closed(&self) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        match self {
+            BlockSource::Live(rx) => {
+                let mut rx = rx.resubscribe();
+                Box::pin(async move {
+                    while !matches!(rx.recv().await, Err(broadcast::error::RecvError::Closed)) {}
+                })
+            }
+            BlockSource::Replay(_) => Box::pin(futures_util::future::pending()),
+        }
+    }
+}
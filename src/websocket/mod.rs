@@ -1,75 +1,81 @@
-pub mod camelot_uniswap;
-pub mod camelot_pancakeswap;
-pub mod pancakeswap_uniswap;
+pub mod pair_monitor;
 pub mod stream_handler;
 pub mod categorize_pairs;
+pub mod block_source;
 
 use ethers::providers::{Provider, Ws};
-use std::{sync::Arc, fs::File, io::BufReader};
-use crate::exchange::model::PairData;
-use crate::websocket::stream_handler::PairCategories;
+use std::sync::Arc;
+use tracing::info;
 
-pub async fn create_ws_provider() -> Result<Arc<Provider<Ws>>, Box<dyn std::error::Error>> {
-    let ws_url = std::env::var("WS_RPC_URL")
-        .expect("RPC_URL must be set in environment variables");
-    let ws = Ws::connect(ws_url).await?;
-    let provider = Provider::new(ws);
+use crate::exchange::model::load_pairs;
+use crate::metrics::rpc::MeteredClient;
+use crate::pricing::{base_symbol, resolve_base, weth};
+use crate::rpc_replay::{FixtureWriter, RecordingClient};
+use crate::settings::settings;
+use crate::websocket::stream_handler::{PairCategories, PoolPair, PoolRef};
+
+// endpoints.ws_rpc_url, or WS_RPC_URL without a config file
+pub fn ws_rpc_url() -> Result<String, Box<dyn std::error::Error>> {
+    let url = settings().endpoints.ws_rpc_url.clone();
+    if url.is_empty() {
+        return Err("WS_RPC_URL or endpoints.ws_rpc_url must be set".into());
+    }
+    Ok(url)
+}
+
+// Requests are counted and timed for the metrics endpoint
+pub async fn create_ws_provider() -> Result<Arc<Provider<MeteredClient<Ws>>>, Box<dyn std::error::Error>> {
+    let url = ws_rpc_url()?;
+    let ws = Ws::connect(&url).await?;
+    let provider = Provider::new(MeteredClient::new(ws, &url));
     
     Ok(Arc::new(provider))
 }
 
+// Same as create_ws_provider, but every request and response is written to the fixture
+pub async fn create_recording_ws_provider(
+    fixture: FixtureWriter,
+) -> Result<Arc<Provider<MeteredClient<RecordingClient<Ws>>>>, Box<dyn std::error::Error>> {
+    let url = ws_rpc_url()?;
+    let ws = Ws::connect(&url).await?;
+    let provider = Provider::new(MeteredClient::new(RecordingClient::new(ws, fixture), &url));
+
+    Ok(Arc::new(provider))
+}
+
 pub fn load_pair_categories() -> std::io::Result<(PairCategories, usize)> {
-    let file = File::open("src/data.json")?;
-    let reader = BufReader::new(file);
-    let pairs: Vec<PairData> = serde_json::from_reader(reader)?;
-    
+    let pairs = load_pairs(&settings().pairs.file)?;
+
     let mut current_index = 0;
-    let mut categories = PairCategories {
-        camelot_uniswap: Vec::new(),
-        camelot_pancakeswap: Vec::new(),
-        pancakeswap_uniswap: Vec::new(),
-    };
+    let mut categories = PairCategories::new();
 
     for pair in pairs {
-        let pair_name = pair.pair.clone();
-        // Check for Camelot + Uniswap pairs
-        if let (Some(camelot), Some(uniswap)) = (&pair.camelot, &pair.uni_swap) {
-            categories.camelot_uniswap.push((
-                camelot.pair_address.clone(),
-                uniswap.pair_address.clone(),
-                pair_name.clone(),
-                current_index
-            ));
-            current_index += 2;
-        }
-        
-        // Check for Camelot + PancakeSwap pairs
-        if let (Some(camelot), Some(pancake)) = (&pair.camelot, &pair.pancake_swap) {
-            categories.camelot_pancakeswap.push((
-                camelot.pair_address.clone(),
-                pancake.pair_address.clone(),
-                pair_name.clone(),
-                current_index
-            ));
-            current_index += 2;
+        let base = resolve_base(pair.base.as_deref())
+            .map_err(|e| std::io::Error::other(format!("{}: {}", pair.pair, e)))?;
+        if base != weth() {
+            info!("{} is quoted in {}", pair.pair, base_symbol(base));
         }
-        
-        // Check for PancakeSwap + Uniswap pairs
-        if let (Some(pancake), Some(uniswap)) = (&pair.pancake_swap, &pair.uni_swap) {
-            categories.pancakeswap_uniswap.push((
-                pancake.pair_address.clone(),
-                uniswap.pair_address.clone(),
-                pair_name.clone(),
-                current_index
-            ));
-            current_index += 2;
+        // Every pool-to-pool combination of the pair, including fee tiers on the same DEX
+        let pools = pair.pools();
+        for (i, (dex_a, info_a)) in pools.iter().enumerate() {
+            for (dex_b, info_b) in &pools[i + 1..] {
+                categories.push(PoolPair::new(
+                    PoolRef { dex: *dex_a, address: info_a.pair_address.clone() },
+                    PoolRef { dex: *dex_b, address: info_b.pair_address.clone() },
+                    &pair.pair,
+                    base,
+                    current_index,
+                ));
+                current_index += 2;
+            }
         }
     }
     
     // Print summary of loaded pairs
-    println!("Loaded {} Camelot-Uniswap pairs --> Will be allocated to WebSocket 1", categories.camelot_uniswap.len());
-    println!("Loaded {} Camelot-PancakeSwap pairs --> Will be allocated to WebSocket 2", categories.camelot_pancakeswap.len());
-    println!("Loaded {} PancakeSwap-Uniswap pairs --> Will be allocated to WebSocket 3", categories.pancakeswap_uniswap.len());
+    info!("Loaded {} Camelot-Uniswap pairs --> Will be allocated to WebSocket 1", categories.camelot_uniswap.len());
+    info!("Loaded {} Camelot-PancakeSwap pairs --> Will be allocated to WebSocket 2", categories.camelot_pancakeswap.len());
+    info!("Loaded {} PancakeSwap-Uniswap pairs --> Will be allocated to WebSocket 3", categories.pancakeswap_uniswap.len());
+    info!("Loaded {} same-DEX fee tier pairs --> Will be allocated to WebSocket 4", categories.same_dex.len());
 
     Ok((categories, current_index))
 }
@@ -0,0 +1,465 @@
+use ethers::prelude::U256;
+use ethers::providers::Middleware;
+use futures_util::StreamExt;
+use std::sync::{Arc, RwLock};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tracing::{Instrument, info, info_span, warn};
+
+use crate::error::{ScanError, read_lock, write_lock};
+use crate::exchange::model::Dex;
+use crate::failures::FailureTracker;
+use crate::export::SnapshotExporter;
+use crate::history::{HistoryWriter, Outcome, PairBlockRecord, SimulationRecord};
+use crate::live::LiveBoard;
+use crate::metrics::{
+    BLOCK_SECONDS, OPPORTUNITIES_FOUND, OPPORTUNITIES_SUSPECT, PAIRS_EVALUATED, PAIRS_SKIPPED, QUARANTINED_POOLS,
+    observe_spread,
+};
+use crate::ipc_handle::{BestTrade, StreamResults};
+use crate::pool_state::PoolCache;
+use crate::reference_price::ReferencePriceService;
+use crate::rpc_replay;
+use crate::routes::{address_bytes, router_address};
+use crate::settings::settings;
+use crate::shm::{PoolFeed, SharedMemoryManager};
+use crate::utils::write_log::{log_price_liquidity, log_simulation};
+use crate::websocket::block_source::BlockStream;
+use crate::tiers::{TierCheck, best_size, tier_table};
+use crate::websocket::categorize_pairs::price_diff_pct;
+use crate::websocket::stream_handler::{PoolPair, PoolRef};
+
+type SendError = Box<dyn std::error::Error + Send + Sync>;
+
+// Short tag for the buy amount log, e.g. "C-U" or "U-U" for two Uniswap fee tiers
+fn dex_tag(dex: Dex) -> &'static str {
+    match dex {
+        Dex::Uniswap => "U",
+        Dex::PancakeSwap => "P",
+        Dex::Camelot => "C",
+    }
+}
+
+// Counts the failure against the pool and says which pool it was
+fn pool_failed(failures: &FailureTracker, pool: &PoolRef, pair_name: &str, block_number: u64, what: &str, error: ScanError) -> ScanError {
+    failures.record_failure(&pool.address, pair_name, &error, block_number);
+    error.context(&format!("{} {} {}", pool.dex.name(), pool.address, what))
+}
+
+// Where evaluated pair-blocks go besides the IPC publisher, each one optional
+#[derive(Clone, Default)]
+pub struct PairSinks {
+    pub history: Option<HistoryWriter>,
+    pub export: Option<SnapshotExporter>,
+    pub live: Option<Arc<LiveBoard>>,
+}
+
+// Hands the pair-block to the live view, the history database and the spread export,
+// when configured
+fn finish(sinks: &PairSinks, mut record: PairBlockRecord, outcome: Outcome) {
+    record.outcome = outcome;
+    if let Some(live) = &sinks.live {
+        live.record(&record);
+    }
+    if let Some(export) = &sinks.export {
+        export.spread(record.clone());
+    }
+    if let Some(history) = &sinks.history {
+        history.record(record);
+    }
+}
+
+// Profit a trade found on `block_number` has to beat to replace the stored one. A trade from
+// an earlier block no longer holds, and one a monitor ahead of this one found on a later block
+// is never replaced.
+fn profit_to_beat(trade: &BestTrade, block_number: u64) -> f64 {
+    match &trade.pair_block {
+        Some(key) if key.block_number > block_number => f64::INFINITY,
+        Some(key) if key.block_number == block_number => trade.profit_usdc,
+        _ => 0.0,
+    }
+}
+
+struct Candidate {
+    profit: f64,
+    amount_base: f64,
+    buy_amount: f64,
+    buy_fee: u32,
+    sell_fee: u32,
+}
+
+// Compares every pool pairing in the list on each block. Pool A and pool B may sit on
+// different DEXes or be two fee tiers of the same pair on one DEX; the cheaper one is
+// bought and the other sold. Each block runs in a "block" span and each pool pair in a
+// "pair" span under it, so the fetch and quote spans below them carry both.
+pub async fn monitor_pairs<M: Middleware + 'static>(
+    label: &str,
+    provider: Arc<M>,
+    mut blocks: BlockStream,
+    live_pairs: Arc<RwLock<Vec<PoolPair>>>,
+    shm_manager: Arc<RwLock<SharedMemoryManager>>,
+    stream_results: Arc<StreamResults>,
+    pool_cache: Arc<PoolCache>,
+    reference_price: Arc<ReferencePriceService>,
+    failures: Arc<FailureTracker>,
+    sinks: PairSinks,
+) -> Result<(), SendError> {
+    while let Some(block_number) = blocks.next().await {
+        let block_span = info_span!("block", number = block_number, monitor = label);
+        let block_work = async {
+            let _timer = BLOCK_SECONDS.with_label_values(&[label]).start_timer();
+            let started = Instant::now();
+            // A trade only holds on the block it was found on. The monitors share it, so only a
+            // trade from an earlier block is cleared, never one another monitor found on this one.
+            {
+                let mut best_trade = write_lock(&stream_results.best_trade);
+                if best_trade.pair_block.as_ref().is_some_and(|key| key.block_number < block_number) {
+                    *best_trade = BestTrade::default();
+                }
+            }
+            // Pools discovered at runtime are picked up on the next block; pairs with a
+            // quarantined pool sit out until the quarantine ends, paused pairs until resumed
+            let (active, paused): (Vec<PoolPair>, Vec<PoolPair>) = read_lock(&live_pairs)
+                .iter()
+                .cloned()
+                .partition(|pair| !sinks.live.as_ref().is_some_and(|live| live.is_paused(&pair.pair_name)));
+            let (pairs, quarantined): (Vec<PoolPair>, Vec<PoolPair>) = active
+                .into_iter()
+                .partition(|pair| {
+                    !failures.is_quarantined(&pair.pool_a.address, block_number)
+                        && !failures.is_quarantined(&pair.pool_b.address, block_number)
+                });
+            // Logging switches are reloadable, so they are read once per block
+            let logging = settings().logging.clone();
+            // Every pair in every monitor converts with the same WETH/USD for this block
+            let reference = match reference_price.for_block(block_number, provider.clone()).await {
+                Ok(reference) => reference,
+                Err(e) => {
+                    warn!("Skipping block #{} in {} monitor: {}", block_number, label, e);
+                    return;
+                }
+            };
+            *write_lock(&stream_results.oracle_hold) = reference.suspect.clone();
+            QUARANTINED_POOLS.set(failures.quarantined_count(block_number) as i64);
+            PAIRS_SKIPPED.with_label_values(&[label, "quarantined"]).inc_by(quarantined.len() as u64);
+            PAIRS_SKIPPED.with_label_values(&[label, "paused"]).inc_by(paused.len() as u64);
+            let quarantine_note = if quarantined.is_empty() {
+                String::new()
+            } else {
+                format!(" ({} quarantined)", quarantined.len())
+            };
+            info!(
+                pairs = pairs.len(),
+                quarantined = quarantined.len(),
+                "---------------------------Latest block number: {} ----- {} Pair Length: {}{}",
+                block_number,
+                label,
+                pairs.len(),
+                quarantine_note
+            );
+            let timestamp_duration = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            let timestamp_ms =
+                timestamp_duration.as_secs() * 1000 + timestamp_duration.subsec_millis() as u64;
+
+            let tasks = pairs.iter().cloned().map(|pair| {
+                let provider = provider.clone();
+                let reference_price = reference_price.clone();
+                let failures = failures.clone();
+                let stream_results = stream_results.clone();
+                let pool_cache = pool_cache.clone();
+                let reference = reference.clone();
+                let shm_manager = shm_manager.clone();
+                let sinks = sinks.clone();
+                let pair_span = info_span!(
+                    "pair",
+                    pair = %pair.pair_name,
+                    pool_a = %pair.pool_a.address,
+                    pool_b = %pair.pool_b.address
+                );
+
+                async move {
+                    let PoolPair { pool_a, pool_b, pair_name, base, index } = pair;
+
+                    // Get latest prices, fetched once per block however many pairings share a pool
+                    let state_a = pool_cache
+                        .fetch(pool_a.dex, &pool_a.address, base, block_number, provider.clone())
+                        .await
+                        .map_err(|e| pool_failed(&failures, &pool_a, &pair_name, block_number, "fetch", e))?;
+                    let state_b = pool_cache
+                        .fetch(pool_b.dex, &pool_b.address, base, block_number, provider.clone())
+                        .await
+                        .map_err(|e| pool_failed(&failures, &pool_b, &pair_name, block_number, "fetch", e))?;
+                    let weth_usdc = reference.weth_usd;
+                    let base_usd = reference_price.base_usd(base, &reference, provider.clone()).await?;
+                    let (usdc_price_a, usdc_liquidity_a) = state_a.usdc_values(base_usd);
+                    let (usdc_price_b, usdc_liquidity_b) = state_b.usdc_values(base_usd);
+
+                    // Keep the quote service and the fee registry in sync with what this block saw
+                    pool_cache.update(&pool_a.address, &pair_name, state_a.clone(), block_number, weth_usdc, base_usd);
+                    pool_cache.update(&pool_b.address, &pair_name, state_b.clone(), block_number, weth_usdc, base_usd);
+
+                    // Tier and sizes follow how much the thinner pool can absorb near the current price
+                    let depth_a = state_a.depth_ladder(base_usd);
+                    let depth_b = state_b.depth_ladder(base_usd);
+                    let depth = depth_a.min(&depth_b);
+                    let min_liquidity = usdc_liquidity_a.min(usdc_liquidity_b);
+                    let price_diff_pct = price_diff_pct(usdc_price_a, usdc_price_b);
+                    PAIRS_EVALUATED.with_label_values(&[label]).inc();
+                    observe_spread(&pair_name, block_number, price_diff_pct);
+
+                    {
+                        let mut shm = write_lock(&shm_manager);
+                        for (slot, state, usdc_price, usdc_liquidity, depth) in [
+                            (index, &state_a, usdc_price_a, usdc_liquidity_a, depth_a),
+                            (index + 1, &state_b, usdc_price_b, usdc_liquidity_b, depth_b),
+                        ] {
+                            if !shm.has_slot(slot) {
+                                continue;
+                            }
+                            let feed = PoolFeed {
+                                block_number,
+                                price: usdc_price,
+                                liquidity_usd: usdc_liquidity,
+                                tick: state.tick(),
+                                timestamp: timestamp_ms,
+                                depth,
+                            };
+                            if let Err(e) = shm.update_pool(slot, feed) {
+                                warn!("Failed to write pool {} to shared memory: {}", slot, ScanError::ipc(e));
+                            }
+                        }
+                    }
+
+                    if logging.price_liquidity {
+                        log_price_liquidity(
+                            &pair_name,
+                            &pool_a.address,
+                            &pool_b.address,
+                            min_liquidity,
+                            &depth,
+                            usdc_price_a,
+                            usdc_price_b,
+                            price_diff_pct,
+                            pool_a.dex.name(),
+                            pool_b.dex.name(),
+                        );
+                    }
+
+                    // Filled in as the evaluation goes; the outcome is set wherever it ends
+                    let mut record = PairBlockRecord {
+                        block_number,
+                        timestamp_ms,
+                        monitor: label.to_string(),
+                        pair_name: pair_name.clone(),
+                        dex_a: pool_a.dex.name(),
+                        pool_a: pool_a.address.clone(),
+                        price_a: usdc_price_a,
+                        dex_b: pool_b.dex.name(),
+                        pool_b: pool_b.address.clone(),
+                        price_b: usdc_price_b,
+                        spread: price_diff_pct,
+                        min_liquidity_usd: min_liquidity,
+                        depth_usd: depth.tier_depth(),
+                        tier: None,
+                        simulations: Vec::new(),
+                        outcome: Outcome::NoTier,
+                        suspect_reason: None,
+                    };
+
+                    let check = tier_table().check(&pair_name, depth.tier_depth(), price_diff_pct);
+                    record.tier = check.tier().map(|tier| tier.tier);
+                    let tier = match check {
+                        TierCheck::Simulate(tier) => tier,
+                        // Only pairs in an enabled tier whose spread clears its minimum are simulated
+                        skipped => {
+                            let (reason, outcome) = match skipped {
+                                TierCheck::Disabled(_) => ("tier_disabled", Outcome::TierDisabled),
+                                TierCheck::BelowSpread(_) => ("below_spread", Outcome::BelowSpread),
+                                _ => ("no_tier", Outcome::NoTier),
+                            };
+                            PAIRS_SKIPPED.with_label_values(&[label, reason]).inc();
+                            finish(&sinks, record, outcome);
+                            return Ok(());
+                        }
+                    };
+
+                    // Buy where the token is cheaper, sell on the other pool
+                    let ((buy_pool, buy_state, buy_usdc_price), (sell_pool, sell_state, sell_usdc_price)) =
+                        if usdc_price_a < usdc_price_b {
+                            ((&pool_a, &state_a, usdc_price_a), (&pool_b, &state_b, usdc_price_b))
+                        } else {
+                            ((&pool_b, &state_b, usdc_price_b), (&pool_a, &state_a, usdc_price_a))
+                        };
+
+                    let mut candidates = Vec::new();
+                    for amount in tier.trade_amounts(depth.tier_depth()) {
+                        let amount_base = amount / base_usd;
+                        let (buy_amount, buy_fee) = buy_state
+                            .simulate(amount_base, "BUY", provider.clone())
+                            .await
+                            .map_err(|e| pool_failed(&failures, buy_pool, &pair_name, block_number, "buy quote", e))?;
+                        let (sell_result_base, sell_fee) = sell_state
+                            .simulate(buy_amount, "SELL", provider.clone())
+                            .await
+                            .map_err(|e| pool_failed(&failures, sell_pool, &pair_name, block_number, "sell quote", e))?;
+                        let profit = sell_result_base * base_usd - amount;
+                        record.simulations.push(SimulationRecord {
+                            amount_usd: amount,
+                            amount_in: amount_base,
+                            amount_out: sell_result_base,
+                            profit_usd: profit,
+                        });
+
+                        if logging.simulation {
+                            let (buy_token0_amount, buy_token1_amount) = buy_state.token_amounts_adjusted();
+                            let (sell_token0_amount, sell_token1_amount) = sell_state.token_amounts_adjusted();
+                            log_simulation(
+                                buy_pool.dex.name(),
+                                &buy_pool.address,
+                                buy_token0_amount,
+                                buy_token1_amount,
+                                buy_state.price(),
+                                buy_usdc_price,
+                                sell_pool.dex.name(),
+                                &sell_pool.address,
+                                sell_token0_amount,
+                                sell_token1_amount,
+                                sell_state.price(),
+                                sell_usdc_price,
+                                amount_base,
+                                amount,
+                                buy_amount,
+                                sell_result_base,
+                                sell_result_base * base_usd,
+                                sell_result_base - amount_base,
+                                profit,
+                                tier.tier,
+                            );
+                        }
+
+                        candidates.push(Candidate { profit, amount_base, buy_amount, buy_fee, sell_fee });
+                    }
+
+                    let best_index = best_size(candidates.iter().map(|candidate| candidate.profit));
+                    let Some(best) = best_index.map(|index| candidates.swap_remove(index)) else {
+                        finish(&sinks, record, Outcome::Unprofitable);
+                        return Ok(());
+                    };
+                    OPPORTUNITIES_FOUND.with_label_values(&["pair"]).inc();
+                    if best.profit <= profit_to_beat(&read_lock(&stream_results.best_trade), block_number) {
+                        finish(&sinks, record, Outcome::Outbid);
+                        return Ok(());
+                    }
+
+                    let (buy_token0, buy_token1) = buy_state.tokens();
+                    let (buy_decimals0, buy_decimals1) = buy_state.decimals();
+                    let (buy_token, buy_decimals) = if buy_token0 == base {
+                        (buy_token1, buy_decimals0)
+                    } else {
+                        (buy_token0, buy_decimals1)
+                    };
+
+                    // Opportunities priced off a suspect block or token price are logged, never published
+                    let suspect = match &reference.suspect {
+                        Some(reason) => Some(reason.clone()),
+                        None => {
+                            let mid_usd = (buy_usdc_price + sell_usdc_price) / 2.0;
+                            reference_price.check_token(buy_token, mid_usd, block_number, provider.clone()).await
+                        }
+                    };
+                    if let Some(reason) = suspect {
+                        OPPORTUNITIES_SUSPECT.with_label_values(&["pair"]).inc();
+                        warn!(
+                            "🚩 Suspect {} {}-{} opportunity (${:.4}) not published: {}",
+                            pair_name,
+                            dex_tag(buy_pool.dex),
+                            dex_tag(sell_pool.dex),
+                            best.profit,
+                            reason
+                        );
+                        record.suspect_reason = Some(reason);
+                        finish(&sinks, record, Outcome::Suspect);
+                        return Ok(());
+                    }
+                    let (sell_token0, _) = sell_state.tokens();
+                    let (sell_decimals0, sell_decimals1) = sell_state.decimals();
+                    let sell_decimals = if sell_token0 == base { sell_decimals1 } else { sell_decimals0 };
+
+                    let mut best_trade = write_lock(&stream_results.best_trade);
+                    // Another pair may have stored a better trade while the token price was checked
+                    if best.profit <= profit_to_beat(&best_trade, block_number) {
+                        drop(best_trade);
+                        finish(&sinks, record, Outcome::Outbid);
+                        return Ok(());
+                    }
+                    best_trade.profit_usdc = best.profit;
+                    best_trade.reference = reference.to_ipc();
+
+                    let mut bytes = [0u8; 32];
+                    let buy_amount_wei = best.amount_base * 10f64.powi(buy_decimals as i32);
+                    let buy_amount_u256 = U256::from(buy_amount_wei as u128);
+                    buy_amount_u256.to_big_endian(&mut bytes);
+                    info!(
+                        profit_usdc = best.profit,
+                        "{}-{} Buy amount: {} {} {} {:?} (fees {}/{}, depth {}, WETH/USD {:.2} via {} from #{})",
+                        dex_tag(buy_pool.dex),
+                        dex_tag(sell_pool.dex),
+                        best.amount_base,
+                        buy_amount_wei,
+                        buy_amount_u256,
+                        bytes,
+                        best.buy_fee,
+                        best.sell_fee,
+                        depth.summary(),
+                        reference.weth_usd,
+                        reference.source(),
+                        reference.block_number
+                    );
+                    best_trade.buy_dex = address_bytes(&router_address(buy_pool.dex));
+                    best_trade.buy_token_in = base.0;
+                    best_trade.buy_token_out = buy_token.0;
+                    best_trade.buy_fee = best.buy_fee;
+                    best_trade.buy_amount = bytes;
+                    best_trade.sell_dex = address_bytes(&router_address(sell_pool.dex));
+                    best_trade.sell_token_in = buy_token.0;
+                    best_trade.sell_token_out = base.0;
+                    best_trade.sell_fee = best.sell_fee;
+
+                    let sell_amount_wei = best.buy_amount * 10f64.powi(sell_decimals as i32);
+                    let sell_amount_u256 = U256::from(sell_amount_wei as u128);
+                    sell_amount_u256.to_big_endian(&mut bytes);
+                    best_trade.sell_amount = bytes;
+                    best_trade.pair_block = Some(record.key());
+                    drop(best_trade);
+                    finish(&sinks, record, Outcome::Selected);
+
+                    Ok::<(), ScanError>(())
+                }
+                .instrument(pair_span)
+            });
+
+            // Execute all futures in parallel
+            let results = futures::future::join_all(tasks).await;
+            for (pair, result) in pairs.iter().zip(results) {
+                match result {
+                    Ok(()) => {
+                        failures.record_success(&pair.pool_a.address, block_number);
+                        failures.record_success(&pair.pool_b.address, block_number);
+                    }
+                    Err(e) => {
+                        PAIRS_SKIPPED.with_label_values(&[label, "failed"]).inc();
+                        failures.record_pair_failure(&pair.pair_name, &e);
+                        warn!(pair = %pair.pair_name, "Error processing {}: {}", pair.pair_name, e);
+                    }
+                }
+            }
+            if let Some(live) = &sinks.live {
+                live.end_block(label, block_number, started.elapsed());
+            }
+        }
+        .instrument(block_span);
+        rpc_replay::at_block(block_number, block_work).await;
+    }
+    Ok(())
+}
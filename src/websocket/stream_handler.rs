@@ -1,25 +1,203 @@
-use ethers::providers::{Provider, Ws};
+use ethers::prelude::Address as EthersAddress;
+use ethers::providers::Middleware;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tracing::info;
 
-use crate::websocket::{camelot_uniswap, camelot_pancakeswap, pancakeswap_uniswap};
+use crate::error::{read_lock, write_lock};
+use crate::exchange::model::Dex;
+use crate::failures::FailureTracker;
+use crate::api::serve_api;
+use crate::export::SnapshotExporter;
+use crate::live::LiveBoard;
+use crate::history::HistoryWriter;
+use crate::websocket::pair_monitor::{self, PairSinks};
+use crate::websocket::block_source::BlockSource;
 use crate::shm::SharedMemoryManager;
-use crate::ipc_handle::{BestTrade, StreamResults, handle_ipc_stream};
+use crate::camelot_fees::handle_camelot_fee_events;
+use crate::fees::handle_fee_snapshots;
+use crate::discover::watcher::handle_pool_discovery;
+use crate::ipc_handle::{BestRoute, BestTrade, PoolAnnouncer, PriceReference, StreamResults, handle_ipc_stream};
+use crate::reference_price::ReferencePriceService;
+use crate::pool_state::PoolCache;
+use crate::recorder::BlockRecorder;
+use crate::quote_service::handle_quote_service;
+use crate::metrics::serve_metrics;
+use crate::settings::settings;
+use crate::routes::handle_route_scan;
 
+type SendError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolRef {
+    pub dex: Dex,
+    pub address: String,
+}
+
+// Two pools of the same pair that are compared on every block
+#[derive(Debug, Clone)]
+pub struct PoolPair {
+    pub pool_a: PoolRef,
+    pub pool_b: PoolRef,
+    pub pair_name: String,
+    // Token both pools are quoted, bought and sold in
+    pub base: EthersAddress,
+    // Shared memory slot of pool_a; pool_b uses index + 1
+    pub index: usize,
+}
+
+impl PoolPair {
+    // Camelot first, then PancakeSwap, then Uniswap, matching the category names
+    pub fn new(a: PoolRef, b: PoolRef, pair_name: &str, base: EthersAddress, index: usize) -> Self {
+        let rank = |dex: Dex| match dex {
+            Dex::Camelot => 0,
+            Dex::PancakeSwap => 1,
+            Dex::Uniswap => 2,
+        };
+        let (pool_a, pool_b) = if rank(a.dex) <= rank(b.dex) { (a, b) } else { (b, a) };
+        Self {
+            pool_a,
+            pool_b,
+            pair_name: pair_name.to_string(),
+            base,
+            index,
+        }
+    }
+}
+
+#[derive(Default)]
 pub struct PairCategories {
-    pub camelot_uniswap: Vec<(String, String, String, usize)>,      // (Camelot address, Uniswap address, Pair Name)
-    pub camelot_pancakeswap: Vec<(String, String, String, usize)>,  // (Camelot address, PancakeSwap address, Pair Name)
-    pub pancakeswap_uniswap: Vec<(String, String, String, usize)>,  // (PancakeSwap address, Uniswap address, Pair Name)
+    pub camelot_uniswap: Vec<PoolPair>,
+    pub camelot_pancakeswap: Vec<PoolPair>,
+    pub pancakeswap_uniswap: Vec<PoolPair>,
+    // Different fee tiers of the same pair on one DEX
+    pub same_dex: Vec<PoolPair>,
+}
+
+impl PairCategories {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, pair: PoolPair) {
+        match (pair.pool_a.dex, pair.pool_b.dex) {
+            (Dex::Camelot, Dex::Uniswap) => self.camelot_uniswap.push(pair),
+            (Dex::Camelot, Dex::PancakeSwap) => self.camelot_pancakeswap.push(pair),
+            (Dex::PancakeSwap, Dex::Uniswap) => self.pancakeswap_uniswap.push(pair),
+            _ => self.same_dex.push(pair),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.camelot_uniswap.len() + self.camelot_pancakeswap.len() + self.pancakeswap_uniswap.len() + self.same_dex.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn pair_names(&self) -> impl Iterator<Item = &str> {
+        self.camelot_uniswap
+            .iter()
+            .chain(&self.camelot_pancakeswap)
+            .chain(&self.pancakeswap_uniswap)
+            .chain(&self.same_dex)
+            .map(|pair| pair.pair_name.as_str())
+    }
+}
+
+type PairList = Arc<RwLock<Vec<PoolPair>>>;
+
+// Pair lists the monitors read on every block, so pools can be added while running
+#[derive(Clone)]
+pub struct LivePairs {
+    pub camelot_uniswap: PairList,
+    pub camelot_pancakeswap: PairList,
+    pub pancakeswap_uniswap: PairList,
+    pub same_dex: PairList,
+    next_index: Arc<AtomicUsize>,
 }
 
-pub async fn handle_dex_streams(
-    ws_provider: Arc<Provider<Ws>>,
+impl LivePairs {
+    pub fn new(categories: PairCategories) -> Self {
+        let entries = categories.len();
+        Self {
+            camelot_uniswap: Arc::new(RwLock::new(categories.camelot_uniswap)),
+            camelot_pancakeswap: Arc::new(RwLock::new(categories.camelot_pancakeswap)),
+            pancakeswap_uniswap: Arc::new(RwLock::new(categories.pancakeswap_uniswap)),
+            same_dex: Arc::new(RwLock::new(categories.same_dex)),
+            next_index: Arc::new(AtomicUsize::new(entries * 2)),
+        }
+    }
+
+    fn lists(&self) -> [&PairList; 4] {
+        [&self.camelot_uniswap, &self.camelot_pancakeswap, &self.pancakeswap_uniswap, &self.same_dex]
+    }
+
+    fn list_for(&self, pair: &PoolPair) -> &PairList {
+        match (pair.pool_a.dex, pair.pool_b.dex) {
+            (Dex::Camelot, Dex::Uniswap) => &self.camelot_uniswap,
+            (Dex::Camelot, Dex::PancakeSwap) => &self.camelot_pancakeswap,
+            (Dex::PancakeSwap, Dex::Uniswap) => &self.pancakeswap_uniswap,
+            _ => &self.same_dex,
+        }
+    }
+
+    // Every monitored pool of a pair quoted in the given base, on any DEX
+    pub fn pools_for(&self, pair_name: &str, base: EthersAddress) -> Vec<PoolRef> {
+        let mut pools: Vec<PoolRef> = Vec::new();
+        for list in self.lists() {
+            for pair in read_lock(list).iter().filter(|pair| pair.pair_name == pair_name && pair.base == base) {
+                for pool in [&pair.pool_a, &pair.pool_b] {
+                    if !pools.iter().any(|p| p.address.eq_ignore_ascii_case(&pool.address)) {
+                        pools.push(pool.clone());
+                    }
+                }
+            }
+        }
+        pools
+    }
+
+    // Pairs a new pool with every pool of the same pair, including other fee tiers on
+    // its own DEX. Returns how many monitor entries were added.
+    pub fn add_pool(&self, dex: Dex, pool_address: &str, pair_name: &str, base: EthersAddress) -> usize {
+        let new_pool = PoolRef {
+            dex,
+            address: pool_address.to_string(),
+        };
+
+        let mut added = 0;
+        for other in self.pools_for(pair_name, base) {
+            if other.address.eq_ignore_ascii_case(pool_address) {
+                continue;
+            }
+            let index = self.next_index.fetch_add(2, Ordering::SeqCst);
+            let pair = PoolPair::new(new_pool.clone(), other, pair_name, base, index);
+            write_lock(self.list_for(&pair)).push(pair);
+            added += 1;
+        }
+        added
+    }
+}
+
+// `live` is the board of a dashboard running alongside; one is also made for the API when
+// it is enabled. `shutdown` stops the scan like ctrl-c does, for callers that own the
+// terminal and see ctrl-c as a key press.
+pub async fn handle_dex_streams<M: Middleware + 'static>(
+    ws_provider: Arc<M>,
+    block_source: BlockSource,
     categories: PairCategories,
     shm_manager: Arc<RwLock<SharedMemoryManager>>,
+    live: Option<Arc<LiveBoard>>,
+    shutdown: Option<oneshot::Receiver<()>>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // Spawn tasks for each category
     let stream_results = Arc::new(StreamResults {
         best_trade: Arc::new(RwLock::new(BestTrade {
             profit_usdc: 0.0,
+            reference: PriceReference::default(),
             buy_dex: [0u8; 20],
             buy_token_in: [0u8; 20],
             buy_token_out: [0u8; 20],
@@ -30,83 +208,175 @@ pub async fn handle_dex_streams(
             sell_token_out: [0u8; 20],
             sell_fee: 0,
             sell_amount: [0u8; 32],
-        }))
+            pair_block: None,
+        })),
+        best_route: Arc::new(RwLock::new(BestRoute::default())),
+        oracle_hold: Arc::new(RwLock::new(None)),
     });
-    let camelot_uni_handle: tokio::task::JoinHandle<Result<(), Box<dyn std::error::Error + Send + Sync>>> = {
+    let exporter = SnapshotExporter::from_settings().map_err(|e| format!("snapshot export: {}", e))?;
+    let pool_cache = Arc::new(PoolCache::new(BlockRecorder::from_settings()?, exporter.clone()));
+    let pair_names: Vec<String> = categories.pair_names().map(String::from).collect();
+    let live_pairs = LivePairs::new(categories);
+    let reference_price = Arc::new(ReferencePriceService::from_settings());
+    let failures = Arc::new(FailureTracker::new());
+    let api_listen = settings().api.listen.clone();
+    let live = live.or_else(|| (!api_listen.is_empty()).then(|| Arc::new(LiveBoard::new())));
+    let sinks = PairSinks {
+        history: HistoryWriter::from_settings().map_err(|e| format!("history database: {}", e))?,
+        export: exporter,
+        live: live.clone(),
+    };
+
+    // One monitor per category, each with its own share of the pairs
+    let monitor_handles: Vec<JoinHandle<Result<(), SendError>>> = [
+        ("camelot_uniswap", live_pairs.camelot_uniswap.clone()),
+        ("camelot_pancakeswap", live_pairs.camelot_pancakeswap.clone()),
+        ("pancakeswap_uniswap", live_pairs.pancakeswap_uniswap.clone()),
+        ("same_dex", live_pairs.same_dex.clone()),
+    ]
+    .into_iter()
+    .map(|(label, pairs)| {
         let provider = ws_provider.clone();
+        let blocks = block_source.subscribe();
         let shm = shm_manager.clone();
         let results = stream_results.clone();
+        let cache = pool_cache.clone();
+        let reference = reference_price.clone();
+        let failures = failures.clone();
+        let sinks = sinks.clone();
         tokio::spawn(async move {
-            match camelot_uniswap::monitor_pairs(
-                provider,
-                categories.camelot_uniswap,
-                shm,
-                results
-            ).await {
-                Ok(_) => Ok(()),
-                Err(e) => {
-                    eprintln!("Error in camelot_uniswap monitor: {}", e);
-                    Ok(())
-                }
-            }
+            pair_monitor::monitor_pairs(label, provider, blocks, pairs, shm, results, cache, reference, failures, sinks)
+                .await
+                .map_err(|e| format!("{} monitor stopped: {}", label, e).into())
         })
-    };
-    
+    })
+    .collect();
 
-    let camelot_pancake_handle: tokio::task::JoinHandle<Result<(), Box<dyn std::error::Error + Send + Sync>>> = {
+    let route_handle = {
         let provider = ws_provider.clone();
-        let shm = shm_manager.clone();
+        let blocks = block_source.subscribe();
         let results = stream_results.clone();
+        let cache = pool_cache.clone();
+        let reference = reference_price.clone();
+        let failures = failures.clone();
         tokio::spawn(async move {
-            match camelot_pancakeswap::monitor_pairs(
-                provider,
-                categories.camelot_pancakeswap,
-                shm,
-                results
-            ).await {
-                Ok(_) => Ok(()),
-                Err(e) => {
-                    eprintln!("Error in camelot_pancakeswap monitor: {}", e);
-                    Ok(()) // Convert the error to () to avoid Send issues
-                }
-            }
+            handle_route_scan(provider, blocks, cache, results, reference, failures, pair_names).await
         })
     };
 
-    let pancake_uni_handle: tokio::task::JoinHandle<Result<(), Box<dyn std::error::Error + Send + Sync>>> = {
+    let discovery_handle = {
         let provider = ws_provider.clone();
-        let shm = shm_manager.clone();
-        let results = stream_results.clone();
+        let blocks = block_source.subscribe();
+        let cache = pool_cache.clone();
+        let pairs = live_pairs.clone();
+        let reference = reference_price.clone();
         tokio::spawn(async move {
-            match pancakeswap_uniswap::monitor_pairs(
-                provider,
-                categories.pancakeswap_uniswap,
-                shm,
-                results
-            ).await {
-                Ok(_) => Ok(()),
-                Err(e) => {
-                    eprintln!("Error in pancakeswap_uniswap monitor: {}", e);
-                    Ok(()) // Convert the error to () to avoid Send issues
-                }
-            }
+            handle_pool_discovery(provider, blocks, cache, pairs, PoolAnnouncer::start(), reference).await
+        })
+    };
+
+    let camelot_fee_handle = {
+        let provider = ws_provider.clone();
+        let blocks = block_source.subscribe();
+        let cache = pool_cache.clone();
+        tokio::spawn(async move {
+            handle_camelot_fee_events(provider, blocks, cache).await
         })
     };
 
+    let fee_snapshot_handle = tokio::spawn(handle_fee_snapshots(pool_cache.fees()));
+
     let ipc_handle = {
         let results = stream_results.clone();
+        let history = sinks.history.clone();
         tokio::spawn(async move {
-            handle_ipc_stream(results).await
+            handle_ipc_stream(results, history).await
         })
     };
 
-    // Wait for all tasks
-    let _ = tokio::try_join!(
-        camelot_uni_handle,
-        camelot_pancake_handle, 
-        pancake_uni_handle,
-        ipc_handle
-    )?;
+    let metrics_handle = tokio::spawn(async move {
+        let listen = settings().metrics.listen.clone();
+        if listen.is_empty() {
+            return Ok(());
+        }
+        serve_metrics(&listen).await
+    });
 
-    Ok(())
-}
\ No newline at end of file
+    let api_handle = {
+        let cache = pool_cache.clone();
+        tokio::spawn(async move {
+            match live {
+                Some(board) if !api_listen.is_empty() => serve_api(&api_listen, board, cache).await,
+                _ => Ok(()),
+            }
+        })
+    };
+
+    let quote_handle = {
+        let cache = pool_cache.clone();
+        tokio::spawn(async move {
+            handle_quote_service(cache).await
+        })
+    };
+
+    let monitors_handle = tokio::spawn(futures::future::try_join_all(monitor_handles.into_iter().map(joined)));
+
+    // Run until a task stops with an error, ctrl-c, a shutdown request or the loss of the
+    // block subscription. Tasks that are turned off finish with Ok and the rest keep running.
+    // The history and export writers are closed on the way out either way, so the last batch
+    // is stored and the current hour's export files are complete.
+    let result = tokio::select! {
+        result = async {
+            tokio::try_join!(
+                joined(monitors_handle),
+                joined(route_handle),
+                joined(discovery_handle),
+                joined(camelot_fee_handle),
+                joined(fee_snapshot_handle),
+                joined(ipc_handle),
+                joined(quote_handle),
+                joined(metrics_handle),
+                joined(api_handle)
+            )
+        } => result.map(|_| ()),
+        _ = tokio::signal::ctrl_c() => {
+            info!("🛑 Stopping, flushing history and snapshot exports");
+            Ok(())
+        }
+        _ = requested(shutdown) => {
+            info!("🛑 Shutdown requested, flushing history and snapshot exports");
+            Ok(())
+        }
+        // The monitors would otherwise wait for blocks that never come
+        _ = block_source.closed() => {
+            Err("newHeads subscription ended: the node connection was lost and could not be re-established".into())
+        }
+    };
+    // Joining the writer threads waits on disk, off the runtime's workers
+    tokio::task::spawn_blocking(move || {
+        if let Some(history) = &sinks.history {
+            history.close();
+        }
+        if let Some(exporter) = &sinks.export {
+            exporter.close();
+        }
+    })
+    .await?;
+
+    result.map_err(|e| e as Box<dyn std::error::Error>)
+}
+
+// Resolves once the caller asks to stop or drops its sender; never without a channel
+async fn requested(shutdown: Option<oneshot::Receiver<()>>) {
+    match shutdown {
+        Some(shutdown) => {
+            let _ = shutdown.await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+// A task's own error, or its panic
+async fn joined<T>(handle: JoinHandle<Result<T, SendError>>) -> Result<T, SendError> {
+    handle.await?
+}
@@ -0,0 +1,127 @@
+// The whole scanner against the mock node on tests/fixtures/mock_state.json: blocks come in
+// over newHeads, the Camelot/PancakeSwap ARB/WETH pair is priced 2% apart and the trade
+// has to come out on the opportunity IPC service. The node then drops every connection and
+// the scanner has to reconnect and keep pricing new blocks. Needs the mock-node feature.
+
+use ethers::types::{Address, U256};
+use iceoryx2::prelude::*;
+use iceoryx2_bb_container::vec::FixedSizeVec;
+use scanner_bot::exchange::model::Dex;
+use scanner_bot::ipc_handle::protocol::{DATA_SIZE, OPPORTUNITY_SERVICE, Opportunity, decode};
+use scanner_bot::mock_node::{MockChainState, MockNode};
+use scanner_bot::settings;
+use scanner_bot::shm::SharedMemoryManager;
+use scanner_bot::websocket::block_source::BlockSource;
+use scanner_bot::websocket::create_ws_provider;
+use scanner_bot::websocket::stream_handler::{PairCategories, PoolPair, PoolRef, handle_dex_streams};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+const STATE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/mock_state.json");
+const WETH: &str = "0x82af49447d8a07e3bd95bd0d56f35241523fbab1";
+const ARB: &str = "0x912ce59144191c1204e64559fe8253a0e49e6548";
+const CAMELOT_ARB: &str = "0xe51635ae8136abac44906a8f230c2d235e9c195f";
+const PANCAKE_ARB: &str = "0x11d53ec50bc8f54b9357fbfe2a7de034fc00f8b3";
+
+fn address(address: &str) -> Address {
+    address.parse().unwrap()
+}
+
+#[tokio::test]
+async fn scanner_publishes_opportunities_across_dropped_connections() {
+    let node = MockNode::start(MockChainState::load(STATE).unwrap(), 1000).await.unwrap();
+
+    // Only the node and a private quote socket; nothing else listens or writes files
+    let dir = std::env::temp_dir().join(format!("scanner_e2e_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("config.toml");
+    std::fs::write(
+        &config,
+        format!(
+            "[endpoints]\nws_rpc_url = \"{}\"\n\n[ipc]\nquote_socket = \"{}\"\n\n[metrics]\nlisten = \"\"\n\n\
+             [api]\nlisten = \"\"\n\n[history]\ndatabase = \"\"\n\n[export]\ndirectory = \"\"\n",
+            node.ws_url(),
+            dir.join("quote.sock").display(),
+        ),
+    )
+    .unwrap();
+    settings::init(Some(config.to_str().unwrap())).unwrap();
+
+    let ipc_node = NodeBuilder::new().create::<ipc::Service>().unwrap();
+    let service = ipc_node
+        .service_builder(&OPPORTUNITY_SERVICE.try_into().unwrap())
+        .publish_subscribe::<FixedSizeVec<u8, DATA_SIZE>>()
+        .open_or_create()
+        .unwrap();
+    let subscriber = service.subscriber_builder().create().unwrap();
+
+    let categories = PairCategories {
+        camelot_pancakeswap: vec![PoolPair::new(
+            PoolRef { dex: Dex::Camelot, address: CAMELOT_ARB.to_string() },
+            PoolRef { dex: Dex::PancakeSwap, address: PANCAKE_ARB.to_string() },
+            "ARB/WETH",
+            address(WETH),
+            0,
+        )],
+        ..PairCategories::default()
+    };
+    let shm_path = dir.join("pools.shm");
+    // The pair writes both of its pools, to slots 0 and 1
+    let shm = Arc::new(RwLock::new(SharedMemoryManager::new(shm_path.to_str().unwrap(), 2).unwrap()));
+    let provider = create_ws_provider().await.unwrap();
+    let block_source = BlockSource::live(None).await.unwrap();
+
+    let scanner = handle_dex_streams(provider, block_source, categories, shm, None, None);
+    tokio::pin!(scanner);
+    // The first opportunity priced on a block after `after`, mining a block every 200ms
+    let (node_ref, subscriber_ref) = (&node, &subscriber);
+    let receive = move |after: u64| async move {
+        loop {
+            node_ref.mine_block();
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            while let Some(sample) = subscriber_ref.receive().unwrap() {
+                let opportunity = decode::<Opportunity>(sample.payload()).unwrap();
+                if opportunity.reference.block_number > after {
+                    return opportunity;
+                }
+            }
+        }
+    };
+
+    let opportunity = tokio::select! {
+        result = &mut scanner => panic!("scanner stopped: {:?}", result.err()),
+        opportunity = tokio::time::timeout(Duration::from_secs(30), receive(0)) => {
+            opportunity.expect("no opportunity published within 30s")
+        }
+    };
+
+    // Drop every socket, then widen the spread: ARB gets 2% cheaper on PancakeSwap, so only a
+    // block priced after the reconnect can beat the trade already published
+    let dropped_at = node.block_number();
+    node.drop_connections();
+    node.update_state(|state| {
+        let pool = state.pools.iter_mut().find(|pool| pool.address == address(PANCAKE_ARB)).unwrap();
+        pool.sqrt_price_x96 = pool.sqrt_price_x96 * U256::from(1_009_756) / U256::from(1_000_000);
+        pool.tick += 194;
+    });
+    let reconnected = tokio::select! {
+        result = &mut scanner => panic!("scanner stopped after the connections dropped: {:?}", result.err()),
+        opportunity = tokio::time::timeout(Duration::from_secs(30), receive(dropped_at)) => {
+            opportunity.expect("no opportunity priced after the reconnect within 30s")
+        }
+    };
+    let _ = std::fs::remove_dir_all(&dir);
+
+    // WETH buys ARB on the cheaper pool and the ARB is sold back for WETH on the other
+    let (buy, sell) = (&opportunity.first_transaction, &opportunity.second_transaction);
+    assert_eq!(buy.token_from, address(WETH).to_fixed_bytes());
+    assert_eq!(buy.token_to, address(ARB).to_fixed_bytes());
+    assert_eq!(sell.token_from, address(ARB).to_fixed_bytes());
+    assert_eq!(sell.token_to, address(WETH).to_fixed_bytes());
+    assert_ne!(buy.amount, [0u8; 32]);
+    assert_ne!(buy.dex, sell.dex);
+    assert!(opportunity.reference.weth_usd > 0.0);
+
+    assert!(reconnected.reference.block_number > dropped_at);
+    assert_eq!(reconnected.first_transaction.token_to, address(ARB).to_fixed_bytes());
+}
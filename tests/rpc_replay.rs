@@ -0,0 +1,187 @@
+// Fetchers, simulators and a pair monitor served from tests/fixtures/replay.jsonl, which was
+// recorded with RecordingClient against the mock node on tests/fixtures/mock_state.json:
+// ARB/WETH at 4000 ARB per WETH on Camelot and Uniswap and 4080 on PancakeSwap, with
+// PancakeSwap moved to 4040 after block 1001.
+
+use ethers::providers::Provider;
+use ethers::types::Address;
+use futures_util::stream;
+use scanner_bot::exchange::FetchDepth;
+use scanner_bot::exchange::camelot::get_camelot_info;
+use scanner_bot::exchange::model::Dex;
+use scanner_bot::exchange::pancakeswap::get_pancakeswap_info;
+use scanner_bot::exchange::uniswap::get_uniswap_info;
+use scanner_bot::failures::FailureTracker;
+use scanner_bot::ipc_handle::{BestRoute, BestTrade, PriceReference, StreamResults};
+use scanner_bot::live::LiveBoard;
+use scanner_bot::pool_state::PoolCache;
+use scanner_bot::reference_price::ReferencePriceService;
+use scanner_bot::rpc_replay::ReplayClient;
+use scanner_bot::shm::SharedMemoryManager;
+use scanner_bot::trade::camelot::simulate_camelot_trade_with_slippage;
+use scanner_bot::trade::pancake::simulate_pancake_trade_with_slippage;
+use scanner_bot::trade::uniswap::simulate_uniswap_trade_with_slippage;
+use scanner_bot::websocket::pair_monitor::{PairSinks, monitor_pairs};
+use scanner_bot::websocket::stream_handler::{PoolPair, PoolRef};
+use std::sync::{Arc, RwLock};
+
+const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/replay.jsonl");
+const WETH: &str = "0x82af49447d8a07e3bd95bd0d56f35241523fbab1";
+const ARB: &str = "0x912ce59144191c1204e64559fe8253a0e49e6548";
+const CAMELOT_ARB: &str = "0xe51635ae8136abac44906a8f230c2d235e9c195f";
+const PANCAKE_ARB: &str = "0x11d53ec50bc8f54b9357fbfe2a7de034fc00f8b3";
+const UNISWAP_ARB: &str = "0xc6f780497a95e246eb9449f5e4770916dcd6396a";
+
+fn replay() -> (Arc<Provider<ReplayClient>>, Vec<u64>) {
+    let client = ReplayClient::load(FIXTURE).unwrap();
+    let blocks = client.block_numbers().to_vec();
+    (Arc::new(Provider::new(client)), blocks)
+}
+
+fn address(address: &str) -> Address {
+    address.parse().unwrap()
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!((actual - expected).abs() <= expected.abs() * 1e-9, "{} != {}", actual, expected);
+}
+
+fn stream_results() -> Arc<StreamResults> {
+    Arc::new(StreamResults {
+        best_trade: Arc::new(RwLock::new(BestTrade {
+            profit_usdc: 0.0,
+            reference: PriceReference::default(),
+            buy_dex: [0u8; 20],
+            buy_token_in: [0u8; 20],
+            buy_token_out: [0u8; 20],
+            buy_fee: 0,
+            buy_amount: [0u8; 32],
+            sell_dex: [0u8; 20],
+            sell_token_in: [0u8; 20],
+            sell_token_out: [0u8; 20],
+            sell_fee: 0,
+            sell_amount: [0u8; 32],
+            pair_block: None,
+        })),
+        best_route: Arc::new(RwLock::new(BestRoute::default())),
+        oracle_hold: Arc::new(RwLock::new(None)),
+    })
+}
+
+#[tokio::test]
+async fn pool_info_replays_from_fixture() {
+    let (provider, _) = replay();
+
+    let pancake = get_pancakeswap_info(PANCAKE_ARB.to_string(), address(WETH), FetchDepth::WithTicks, provider.clone()).await.unwrap();
+    assert_eq!((pancake.token0, pancake.token1), (address(WETH), address(ARB)));
+    assert_eq!((pancake.token0_decimals, pancake.token1_decimals), (18, 18));
+    assert_eq!(pancake.fee, 500);
+    assert_close(pancake.price, 1.0 / 4080.0);
+
+    let camelot = get_camelot_info(CAMELOT_ARB.to_string(), address(WETH), FetchDepth::WithTicks, provider.clone()).await.unwrap();
+    assert_eq!(camelot.base, address(WETH));
+    assert_eq!((camelot.fee_zto, camelot.fee_otz), (500, 600));
+    assert_close(camelot.price, 1.0 / 4000.0);
+
+    let uniswap = get_uniswap_info(UNISWAP_ARB.to_string(), address(WETH), FetchDepth::WithTicks, provider).await.unwrap();
+    assert_eq!((uniswap.token0, uniswap.token1), (address(WETH), address(ARB)));
+    assert_eq!((uniswap.fee, uniswap.tick), (500, 82944));
+    assert_eq!(uniswap.active_liquidity, 10514561331791260549120);
+    assert!(uniswap.initialized_ticks.is_empty());
+    assert_close(uniswap.price, 1.0 / 4000.0);
+}
+
+#[tokio::test]
+async fn simulations_replay_from_fixture() {
+    let (provider, _) = replay();
+    let (weth, arb) = (address(WETH), address(ARB));
+
+    let bought = simulate_pancake_trade_with_slippage(weth, 18, arb, 18, 1.0, 500, weth, "BUY", provider.clone())
+        .await
+        .unwrap();
+    assert_close(bought, 4053.3486361369655);
+
+    let (sold, fee) = simulate_camelot_trade_with_slippage(weth, 18, arb, 18, 4000.0, weth, "SELL", provider.clone())
+        .await
+        .unwrap();
+    assert_close(sold, 0.999398497897048);
+    // ARB -> WETH is token1 -> token0, which pays the other-to-zero fee
+    assert_eq!(fee, 600);
+
+    let bought = simulate_uniswap_trade_with_slippage(weth, 18, arb, 18, 1.0, 500, weth, "BUY", provider)
+        .await
+        .unwrap();
+    assert_close(bought, 3974.1075190593074);
+}
+
+// Two monitors on the same pair run side by side over the recorded blocks. Each block's
+// calls must get that block's responses whichever monitor asks first.
+#[tokio::test]
+async fn monitor_pairs_replays_each_block() {
+    let (provider, blocks) = replay();
+    assert_eq!(blocks, vec![1001, 1002]);
+
+    let run = |label: &'static str, blocks: Vec<u64>| {
+        let provider = provider.clone();
+        async move {
+            let shm_path = std::env::temp_dir().join(format!("scanner_replay_test_{}_{}.shm", label, std::process::id()));
+            let shm = Arc::new(RwLock::new(SharedMemoryManager::new(shm_path.to_str().unwrap(), 2).unwrap()));
+            let pairs = Arc::new(RwLock::new(vec![PoolPair::new(
+                PoolRef { dex: Dex::Camelot, address: CAMELOT_ARB.to_string() },
+                PoolRef { dex: Dex::PancakeSwap, address: PANCAKE_ARB.to_string() },
+                "ARB/WETH",
+                address(WETH),
+                0,
+            )]));
+            let results = stream_results();
+            let board = Arc::new(LiveBoard::new());
+            let sinks = PairSinks { live: Some(board.clone()), ..PairSinks::default() };
+            monitor_pairs(
+                label,
+                provider,
+                Box::pin(stream::iter(blocks)),
+                pairs,
+                shm,
+                results.clone(),
+                Arc::new(PoolCache::new(None, None)),
+                Arc::new(ReferencePriceService::from_settings()),
+                Arc::new(FailureTracker::new()),
+                sinks,
+            )
+            .await
+            .unwrap();
+            let _ = std::fs::remove_file(shm_path);
+            (results, board)
+        }
+    };
+    let ((results_a, board_a), (results_b, board_b)) =
+        tokio::join!(run("replay_a", blocks.clone()), run("replay_b", blocks.clone()));
+
+    for (results, board) in [(results_a, board_a), (results_b, board_b)] {
+        // Only block 1001 had PancakeSwap 2% off Camelot
+        let opportunities = board.opportunities();
+        assert_eq!(opportunities.len(), 1);
+        assert_eq!(opportunities[0].block_number, 1001);
+        assert_eq!(opportunities[0].outcome, "selected");
+
+        // Block 1001's trade no longer holds on block 1002, which found none
+        let trade = results.best_trade.read().unwrap();
+        assert!(trade.pair_block.is_none());
+        assert_eq!(trade.profit_usdc, 0.0);
+
+        // The latest spread is from block 1002, with PancakeSwap at 4040
+        let spreads = board.spreads();
+        assert_eq!(spreads.len(), 1);
+        assert_eq!(spreads[0].block_number, 1002);
+    }
+
+    // Stopping after block 1001 leaves its trade in place
+    let (results, _) = run("replay_first", vec![1001]).await;
+    let trade = results.best_trade.read().unwrap();
+    assert_eq!(trade.pair_block.as_ref().map(|key| key.block_number), Some(1001));
+    assert_close(trade.profit_usdc, 6.363817301041365);
+    // WETH buys ARB where it is cheaper and the ARB is sold back for WETH
+    assert_eq!(trade.buy_token_in, address(WETH).to_fixed_bytes());
+    assert_eq!(trade.buy_token_out, address(ARB).to_fixed_bytes());
+    assert_eq!(trade.sell_token_out, address(WETH).to_fixed_bytes());
+}